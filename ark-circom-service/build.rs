@@ -1,6 +1,11 @@
 use std::env;
 
 fn main() {
+    //the wasm backend does not require the native libraries
+    if env::var("CARGO_FEATURE_WITNESSCALC").is_err() {
+        return;
+    }
+
     let target_os = env::var("CARGO_CFG_TARGET_OS").unwrap();
 
     if target_os == "android" {
@@ -0,0 +1,60 @@
+use std::{error::Error, fmt};
+
+use witness_utils::WitnessError;
+
+///Errors raised while creating or verifying a Groth16 proof.
+#[derive(Debug)]
+pub enum ProofError {
+    ///The proving key could not be parsed.
+    InvalidZkey(String),
+    ///The witness could not be created or does not fit the circuit.
+    Witness(WitnessError),
+    ///A public signal does not fit into the finite field.
+    FieldOverflow(String),
+    ///The prover failed to create the proof.
+    ProvingFailed(String),
+    ///The verifier failed to process the proof.
+    VerificationFailed(String),
+    ///The created proof does not verify.
+    ProofInvalid,
+    ///The verification key of the proof differs from the trusted one.
+    UntrustedVerificationKey,
+    ///No trusted verification key is known for the circuit.
+    MissingVerificationKey(String),
+}
+
+impl fmt::Display for ProofError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ProofError::InvalidZkey(msg) => write!(f, "Invalid proving key: {}", msg),
+            ProofError::Witness(err) => write!(f, "{}", err),
+            ProofError::FieldOverflow(value) => {
+                write!(f, "Public signal {} outside of the finite field", value)
+            }
+            ProofError::ProvingFailed(msg) => write!(f, "Proving failed: {}", msg),
+            ProofError::VerificationFailed(msg) => write!(f, "Verification failed: {}", msg),
+            ProofError::ProofInvalid => write!(f, "Proof invalid"),
+            ProofError::UntrustedVerificationKey => {
+                write!(f, "Verification key does not match the trusted key")
+            }
+            ProofError::MissingVerificationKey(circuit) => {
+                write!(f, "No trusted verification key for circuit {}", circuit)
+            }
+        }
+    }
+}
+
+impl Error for ProofError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            ProofError::Witness(err) => Some(err),
+            _ => None,
+        }
+    }
+}
+
+impl From<WitnessError> for ProofError {
+    fn from(err: WitnessError) -> Self {
+        ProofError::Witness(err)
+    }
+}
@@ -1,21 +1,25 @@
 
-pub use ark_bn254::{Bn254, FrParameters};
+pub mod error;
+
+pub use ark_bn254::{Bn254, Fr, FrParameters};
 pub use ark_ff::BigInteger;
+pub use error::ProofError;
 
-use std::{io::{Seek,SeekFrom, Cursor}, error::Error};
+use std::{io::Cursor, collections::HashMap};
 use color_eyre::Result;
 
 use ark_circom::{CircomReduction,read_zkey};
 use ark_ec::PairingEngine;
 use ark_ff::{Fp256,FromBytes, UniformRand, BigInteger256};
-use ark_serialize::{Write, CanonicalSerialize, CanonicalDeserialize};
+use ark_serialize::{CanonicalSerialize, CanonicalDeserialize};
 use ark_std::rand::thread_rng;
+use ark_relations::r1cs::ConstraintMatrices;
 use ark_groth16::{
-    VerifyingKey,Proof,create_proof_with_reduction_and_matrices,verify_proof as verify_proof_groth16,prepare_verifying_key
+    PreparedVerifyingKey,ProvingKey,VerifyingKey,Proof,create_proof_with_reduction_and_matrices,verify_proof as verify_proof_groth16,prepare_verifying_key
 };
 use num_bigint::{ BigInt};
 use serde::{Serialize, Deserialize};
-use witness_utils::Witness;
+use witness_utils::{Witness, WitnessError};
 
 ///Serializing function for arkworks structs with serde compatibility.
 fn ark_se<S, A: CanonicalSerialize>(a: &A, s: S) -> Result<S::Ok, S::Error> where S: serde::Serializer {
@@ -43,86 +47,194 @@ pub struct ArkCircomFullProof<E: PairingEngine>{
     pub outputs: Vec<BigInt>
 }
 
-///Creates a groth16 proof for a given witness and proving key
-pub fn create_proof_from_witness(witness: &Witness, zkey: &Vec<u8>)->
-Result<ArkCircomFullProof<Bn254>,Box<dyn Error>>{
+///Converts a public signal to an element of the scalar field.
+fn to_field_element(value: &BigInt) -> Result<Fp256<FrParameters>, ProofError> {
+    let mut bytes = value.to_bytes_le().1;
+    if value.sign() == num_bigint::Sign::Minus || bytes.len() > 32 {
+        Err(ProofError::FieldOverflow(value.to_string()))?
+    }
+    for _i in bytes.len()..32{
+        bytes.push(0);
+    }
+    match BigInteger256::read(bytes.as_slice()) {
+        Ok(res) => Ok(Fp256::from(res)),
+        Err(err) => Err(ProofError::FieldOverflow(err.to_string())),
+    }
+}
 
-    let mut outputs = Vec::<BigInt>::new();
+///Parses the proving key and the constraint matrices from a zkey.
+fn parse_zkey(zkey: &Vec<u8>)
+-> Result<(ProvingKey<Bn254>, ConstraintMatrices<Fr>), ProofError>{
+    let mut buf = Cursor::new(zkey.as_slice());
+    read_zkey(&mut buf).map_err(|err| ProofError::InvalidZkey(err.to_string()))
+}
 
-    let mut assignment: Vec<Fp256<FrParameters>> = witness.assignment.iter().map(|x| {
-        let y = x.clone();
-        Fp256::from(    
-            BigInteger256::read(y.as_slice()).unwrap()        
-        )
-    }).rev().collect();
-    
-    assignment.reverse();
-    let mut buf: Cursor<Vec<u8>> = Cursor::new(Vec::new());
-    buf.write_all(&zkey).unwrap();
-    buf.seek(SeekFrom::Start(0)).unwrap();
-    let (params, matrices) = read_zkey(&mut buf).unwrap();
+///A struct holding the parsed proving key of a circuit.
+/// 
+/// Parsing the zkey dominates the proving time of small circuits,
+///  so the context is meant to be reused across proofs.
+pub struct ProverContext{
+    params: ProvingKey<Bn254>,
+    matrices: ConstraintMatrices<Fr>,
+    pvk: PreparedVerifyingKey<Bn254>
+}
 
-    let num_inputs = matrices.num_instance_variables;
-    let num_constraints = matrices.num_constraints;
+impl ProverContext {
 
-    for i in 1..num_inputs {
-        outputs.push(
-            BigInt::from_bytes_le(num_bigint::Sign::Plus,&witness.assignment[i])
-        );
-    }
-    
-    let mut rng = thread_rng();
-    
-    let r = ark_bn254::Fr::rand(&mut rng);
-    let s = ark_bn254::Fr::rand(&mut rng);
+    ///Parses the proving key and the constraint matrices from a zkey.
+    pub fn from_zkey(zkey: &Vec<u8>) -> Result<Self, ProofError>{
+        let (params, matrices) = parse_zkey(zkey)?;
+        let pvk = prepare_verifying_key(&params.vk);
 
-    let proof = create_proof_with_reduction_and_matrices::<_,CircomReduction>(&params, r, s, &matrices,
-         num_inputs, num_constraints, assignment.as_slice()).unwrap();
+        Ok(ProverContext { params, matrices, pvk })
+    }
 
-    let pvk = prepare_verifying_key(&params.vk);
-    let inputs = &assignment[1..num_inputs];
-    
-    let verified = verify_proof_groth16(&pvk, &proof, inputs).unwrap();
-    if !verified {
-        Err("Proof invalid.")?
+    ///Retrieves the verification key of the circuit.
+    pub fn verifying_key(&self) -> &VerifyingKey<Bn254>{
+        &self.params.vk
     }
+
+    ///Creates a groth16 proof for a given witness.
+    pub fn prove(&self, witness: &Witness)
+    -> Result<ArkCircomFullProof<Bn254>,ProofError>{
+
+        let mut outputs = Vec::<BigInt>::new();
+
+        let mut assignment = Vec::<Fp256<FrParameters>>::with_capacity(witness.assignment.len());
+        for x in witness.assignment.iter() {
+            match BigInteger256::read(x.as_slice()) {
+                Ok(res) => assignment.push(Fp256::from(res)),
+                Err(err) => Err(WitnessError::MalformedWitness(err.to_string()))?,
+            }
+        }
+
+        let num_inputs = self.matrices.num_instance_variables;
+        let num_constraints = self.matrices.num_constraints;
+
+        if witness.assignment.len() < num_inputs {
+            Err(WitnessError::MalformedWitness(format!("Expected at least {} assignments, found {}",
+                num_inputs, witness.assignment.len())))?
+        }
+
+        for i in 1..num_inputs {
+            outputs.push(
+                BigInt::from_bytes_le(num_bigint::Sign::Plus,&witness.assignment[i])
+            );
+        }
         
-    Ok(ArkCircomFullProof { proof, verification_key: params.vk, outputs })
+        let mut rng = thread_rng();
+        
+        let r = ark_bn254::Fr::rand(&mut rng);
+        let s = ark_bn254::Fr::rand(&mut rng);
+
+        let proof = create_proof_with_reduction_and_matrices::<_,CircomReduction>(&self.params, r, s, &self.matrices,
+             num_inputs, num_constraints, assignment.as_slice())
+             .map_err(|err| ProofError::ProvingFailed(err.to_string()))?;
+
+        let inputs = &assignment[1..num_inputs];
+        
+        let verified = verify_proof_groth16(&self.pvk, &proof, inputs)
+            .map_err(|err| ProofError::VerificationFailed(err.to_string()))?;
+        if !verified {
+            Err(ProofError::ProofInvalid)?
+        }
+            
+        Ok(ArkCircomFullProof { proof, verification_key: self.params.vk.clone(), outputs })
+    }
+}
+
+///Creates a groth16 proof for a given witness and proving key
+/// 
+/// Parses the proving key on every call,
+///  use a 'ProverContext' for repeated proofs.
+pub fn create_proof_from_witness(witness: &Witness, zkey: &Vec<u8>)->
+Result<ArkCircomFullProof<Bn254>,ProofError>{
+    ProverContext::from_zkey(zkey)?.prove(witness)
 }
 
 ///Implement the functionality for groth16 proofs over the Bn254 curve
 impl ArkCircomFullProof<Bn254> {
 
     ///Checks if the proof is valid.
+    /// 
+    /// Uses the verification key shipped with the proof,
+    ///  which is only meaningful for the prover.
+    ///  Verifiers should use 'verify_with_key' instead.
     pub fn verify(&self)
-    -> Result<bool,Box<dyn Error>>{
-    
-        let pvk = prepare_verifying_key(&self.verification_key);
-        
-        let mut inputs: Vec<Fp256<FrParameters>> = self.outputs.iter().map(|x| {
-            let mut y = x.to_bytes_le().1;
-            for _i in y.len()..32{
-                y.push(0);
-            }
-            Fp256::from(    
-                BigInteger256::read(y.as_slice()).unwrap()
-            )
-        }).rev().collect();
+    -> Result<bool,ProofError>{
+        self.verify_with_key(&self.verification_key)
+    }
+
+    ///Checks if the proof is valid under the trusted verification key.
+    /// 
+    /// Fails, should the verification key embedded in the proof differ from the trusted one.
+    pub fn verify_with_key(&self, verifying_key: &VerifyingKey<Bn254>)
+    -> Result<bool,ProofError>{
+        if &self.verification_key != verifying_key {
+            Err(ProofError::UntrustedVerificationKey)?
+        }
+
+        let pvk = prepare_verifying_key(verifying_key);
         
-        inputs.reverse();
+        let inputs = self.outputs.iter().map(to_field_element).collect::<Result<Vec<_>, _>>()?;
 
-        match verify_proof_groth16(&pvk, &self.proof, &inputs) {
-            Ok(res) => Ok(res),
-            Err(err) => Err(err.to_string())?
-        } 
+        verify_proof_groth16(&pvk, &self.proof, &inputs)
+            .map_err(|err| ProofError::VerificationFailed(err.to_string()))
     }        
 }
 
+///A struct holding the trusted verification keys of the circuits.
+/// 
+/// Populated by the verifier per circuit, so proofs are checked against
+///  a pinned key instead of the key provided by the prover.
+#[derive(Clone, Default)]
+pub struct VerifierKeyStore{
+    keys: HashMap<String,VerifyingKey<Bn254>>
+}
+
+impl VerifierKeyStore {
+
+    ///Instatiates an empty key store.
+    pub fn new() -> Self{
+        VerifierKeyStore { keys: HashMap::new() }
+    }
+
+    ///Pins the verification key for the circuit.
+    /// Replaces a previously pinned key.
+    pub fn insert(&mut self, circuit: &str, verifying_key: VerifyingKey<Bn254>){
+        self.keys.insert(circuit.to_owned(), verifying_key);
+    }
+
+    ///Pins the verification key contained in the proving key of the circuit.
+    pub fn insert_from_zkey(&mut self, circuit: &str, zkey: &Vec<u8>)
+    -> Result<(),ProofError>{
+        let (params, _matrices) = parse_zkey(zkey)?;
+
+        self.insert(circuit, params.vk);
+        Ok(())
+    }
+
+    ///Retrieves the pinned verification key of the circuit.
+    pub fn get(&self, circuit: &str) -> Option<&VerifyingKey<Bn254>>{
+        self.keys.get(circuit)
+    }
+
+    ///Checks the proof against the pinned verification key of the circuit.
+    /// 
+    /// Fails, should no key be pinned for the circuit.
+    pub fn verify(&self, circuit: &str, proof: &ArkCircomFullProof<Bn254>)
+    -> Result<bool,ProofError>{
+        match self.get(circuit) {
+            Some(verifying_key) => proof.verify_with_key(verifying_key),
+            None => Err(ProofError::MissingVerificationKey(circuit.to_owned())),
+        }
+    }
+}
+
 ///Provides functionality required for the benchmarks.
+#[cfg(feature = "witnesscalc")]
 pub mod poseidon_witnesscalc{
-    use std::error::Error;
-
-    use witness_utils::{create::WITNESS_SIZE_GUESS, read::read_wtns, Witness};
+    use witness_utils::{create::{WitnessCalcFn, WitnessCalculator}, Witness, WitnessError};
 
 
     #[link(name = "poseidon_bench", kind = "static")]   
@@ -146,54 +258,179 @@ pub mod poseidon_witnesscalc{
     ///                 Needs to be consistant with the witnesscalc library.
     pub fn generate_poseidon_witness(
         json: &str,circuit:&Vec<u8>
-    ) -> Result<Witness, Box<dyn Error>> {
-
-        let mut wtns: Vec<u8> = Vec::with_capacity(WITNESS_SIZE_GUESS as usize);
-        let mut witness_size: u64 = WITNESS_SIZE_GUESS;
-
-        let mut error: Vec<u8> = Vec::with_capacity(200);
-
-        let mut result;
-        unsafe {
-            result = witnesscalc_poseidon_bench(
-                circuit.as_ptr(),
-                circuit.len() as u64,
-                json.as_ptr(),
-                json.len() as u64,
-                wtns.as_mut_ptr(),
-                &mut witness_size,
-                error.as_mut_ptr(),
-                200,
-            );
+    ) -> Result<Witness, WitnessError> {
+        let witnesscalc: WitnessCalcFn = witnesscalc_poseidon_bench;
+        witnesscalc.calculate_witness(json, circuit)
+    }
+}
+
+///Provides witness generation from the wasm artifacts of circom.
+/// 
+/// Does not depend on the native witnesscalc libraries.
+#[cfg(feature = "wasm")]
+pub mod wasm_witness{
+    use std::{collections::HashMap, str::FromStr};
+
+    use ark_circom::WitnessCalculator as CircomWitnessCalculator;
+    use ark_ff::{BigInteger, FpParameters};
+    use num_bigint::{BigInt, Sign};
+    use serde_json::Value;
+    use wasmer::{Module, Store};
+    use witness_utils::{create::WitnessCalculator, Witness, WitnessError};
+
+    use crate::FrParameters;
+
+    ///Witness generation executing the wasm artifact of a circuit.
+    #[derive(Debug, Clone, Copy, Default)]
+    pub struct WasmWitnessCalculator;
+
+    ///Implements the witness generation for the wasm artifacts.
+    /// 
+    /// The circuit definition is the .wasm file provided by circom.
+    impl WitnessCalculator for WasmWitnessCalculator {
+        fn calculate_witness(&self, json: &str, circuit: &Vec<u8>) -> Result<Witness, WitnessError> {
+            let inputs = parse_inputs(json)?;
+
+            let store = Store::default();
+            let module = Module::new(&store, circuit)
+                .map_err(|err| WitnessError::WitnessCalcFailed(format!("Invalid wasm: {}", err)))?;
+            let mut calculator = CircomWitnessCalculator::from_module(module)
+                .map_err(|err| WitnessError::WitnessCalcFailed(err.to_string()))?;
+
+            let values = calculator.calculate_witness(inputs, true)
+                .map_err(|err| WitnessError::WitnessCalcFailed(err.to_string()))?;
+
+            to_witness(values)
         }
+    }
 
-        if result == 1 {
-            Err(result.to_string())?
-        } else if result == 2 {
-            wtns = Vec::with_capacity(witness_size as usize);
-            unsafe {
-                result = witnesscalc_poseidon_bench(
-                    circuit.as_ptr(),
-                    circuit.len() as u64,
-                    json.as_ptr(),
-                    json.len() as u64,
-                    wtns.as_mut_ptr(),
-                    &mut witness_size,
-                    error.as_mut_ptr(),
-                    200,
-                );
-            };
-
-            if result != 0 {
-                Err(result.to_string())?
-            }
+    ///Parses the json input into the signals of the circuit.
+    fn parse_inputs(json: &str) -> Result<HashMap<String, Vec<BigInt>>, WitnessError>{
+        let signals = match serde_json::from_str::<Value>(json) {
+            Ok(Value::Object(signals)) => signals,
+            Ok(_) => Err(WitnessError::WitnessCalcFailed("Input is not a json object".to_owned()))?,
+            Err(err) => Err(WitnessError::WitnessCalcFailed(format!("Invalid input json: {}", err)))?,
+        };
+
+        let mut inputs = HashMap::new();
+        for (name, value) in signals {
+            let mut values = Vec::new();
+            flatten(&name, &value, &mut values)?;
+            inputs.insert(name, values);
         }
-        unsafe {
-            wtns.set_len(witness_size as usize);
+        Ok(inputs)
+    }
+
+    //appends the values of the signal, flattening nested arrays as circom does
+    fn flatten(name: &str, value: &Value, values: &mut Vec<BigInt>) -> Result<(), WitnessError>{
+        match value {
+            Value::Array(elements) => {
+                for element in elements {
+                    flatten(name, element, values)?;
+                }
+            },
+            Value::String(number) => values.push(parse_number(name, number)?),
+            Value::Number(number) => values.push(parse_number(name, &number.to_string())?),
+            Value::Bool(flag) => values.push(BigInt::from(*flag as u8)),
+            _ => Err(WitnessError::WitnessCalcFailed(format!("Invalid value for signal {}", name)))?,
+        }
+        Ok(())
+    }
+
+    //parses a decimal number of the signal
+    fn parse_number(name: &str, number: &str) -> Result<BigInt, WitnessError>{
+        BigInt::from_str(number)
+            .map_err(|_| WitnessError::WitnessCalcFailed(format!("Invalid number {} for signal {}", number, name)))
+    }
+
+    ///Converts the witness values into the representation of a binary witness.
+    fn to_witness(values: Vec<BigInt>) -> Result<Witness, WitnessError>{
+        let mut witness = Witness::new();
+        witness.field_element_size = 32;
+        witness.field_prime = BigInt::from_bytes_le(Sign::Plus, &FrParameters::MODULUS.to_bytes_le());
+        witness.num_constraints = values.len() as u32;
+
+        for value in values {
+            let (sign, bytes) = value.to_bytes_le();
+            if sign == Sign::Minus || bytes.len() > 32 {
+                Err(WitnessError::MalformedWitness(format!("Value {} outside of the finite field", value)))?
+            }
+
+            let mut element = [0u8;32];
+            element[..bytes.len()].copy_from_slice(&bytes);
+            witness.assignment.push(element);
         }
-        let witness = read_wtns(&wtns);
         Ok(witness)
     }
-}
 
+    #[cfg(test)]
+    mod test {
+        use super::*;
+
+        ///Circuit of the ark-circom test vectors constraining c <== a * b.
+        const MULTIPLIER: &[u8] = include_bytes!("../test-vectors/multiplier2.wasm");
+
+        ///TEST: parsing of the json input
+        #[test]
+        fn wasm_parse_inputs() {
+            let inputs = parse_inputs(r#"{"a":"3","b":[[1,2],["3"]],"c":true,"d":11}"#).unwrap();
+            assert_eq!(inputs.len(), 4);
+            assert_eq!(inputs["a"], vec![BigInt::from(3)]);
+            assert_eq!(inputs["b"], vec![BigInt::from(1), BigInt::from(2), BigInt::from(3)]);
+            assert_eq!(inputs["c"], vec![BigInt::from(1)]);
+            assert_eq!(inputs["d"], vec![BigInt::from(11)]);
 
+            assert!(parse_inputs("[1,2]").is_err());
+            assert!(parse_inputs("{\"a\":").is_err());
+            assert!(parse_inputs(r#"{"a":"x"}"#).is_err());
+            assert!(parse_inputs(r#"{"a":null}"#).is_err());
+        }
+
+        ///TEST: flattening of nested signal arrays
+        #[test]
+        fn wasm_flatten() {
+            let mut values = Vec::new();
+            flatten("a", &serde_json::json!([[["1"], 2], [], [false, "21888242871839275222246405745257275088548364400416034343698204186575808495616"]]), &mut values).unwrap();
+            assert_eq!(values, vec![
+                BigInt::from(1), BigInt::from(2), BigInt::from(0),
+                BigInt::from_str("21888242871839275222246405745257275088548364400416034343698204186575808495616").unwrap()
+            ]);
+
+            assert!(flatten("a", &serde_json::json!({"b":1}), &mut values).is_err());
+            assert!(flatten("a", &serde_json::json!(["1.5"]), &mut values).is_err());
+        }
+
+        ///TEST: conversion of the witness values into field elements
+        #[test]
+        fn wasm_to_witness() {
+            let prime = BigInt::from_bytes_le(Sign::Plus, &FrParameters::MODULUS.to_bytes_le());
+            let largest = &prime - BigInt::from(1);
+            let witness = to_witness(vec![BigInt::from(1), BigInt::from(258), largest.clone()]).unwrap();
+            assert_eq!(witness.field_element_size, 32);
+            assert_eq!(witness.field_prime, prime);
+            assert_eq!(witness.num_constraints, 3);
+            assert_eq!(witness.assignment.len(), 3);
+            assert_eq!(witness.assignment[0][0], 1);
+            assert_eq!(&witness.assignment[1][..3], &[2, 1, 0]);
+            assert_eq!(BigInt::from_bytes_le(Sign::Plus, &witness.assignment[2]), largest);
+
+            assert!(to_witness(vec![BigInt::from(-1)]).is_err());
+            assert!(to_witness(vec![BigInt::from(1) << 256]).is_err());
+        }
+
+        ///TEST: witness generation from a wasm artifact
+        #[test]
+        fn wasm_witness_calculator() {
+            let witness = WasmWitnessCalculator
+                .calculate_witness(r#"{"a":"3","b":"11"}"#, &MULTIPLIER.to_vec())
+                .unwrap();
+            let values: Vec<BigInt> = witness.assignment.iter()
+                .map(|element| BigInt::from_bytes_le(Sign::Plus, element))
+                .collect();
+            assert_eq!(values, vec![BigInt::from(1), BigInt::from(33), BigInt::from(3), BigInt::from(11)]);
+
+            assert!(WasmWitnessCalculator.calculate_witness(r#"{"a":"3","b":"11"}"#, &vec![0u8; 16]).is_err());
+            assert!(WasmWitnessCalculator.calculate_witness("[3,11]", &MULTIPLIER.to_vec()).is_err());
+        }
+    }
+}
@@ -0,0 +1,40 @@
+use crypto::{merkle_tree::{HashTree, SparseMerkleTree}, poseidon::PoseidonHasher};
+use criterion::{
+    criterion_group,
+    criterion_main,
+    BenchmarkId, Criterion, black_box
+};
+use std::time::Duration;
+
+//benchmarks the construction of binary hash trees with 2^10 to 2^20 leaves
+fn hash_tree_construction(c: &mut Criterion){
+    let mut group = c.benchmark_group("hash tree construction");
+    for depth in (10..=20).step_by(2) {
+        let leaves: Vec<String> = (0..1usize << depth).map(|x| x.to_string()).collect();
+        group.bench_with_input(BenchmarkId::from_parameter(depth), &leaves,
+            |b, leaves| b.iter(|| HashTree::<PoseidonHasher,2>::new(black_box(leaves)).unwrap()));
+    }
+    group.finish();
+}
+
+//benchmarks filling sparse Merkle trees with 2^10 to 2^20 leaves at once
+fn sparse_merkle_tree_batch(c: &mut Criterion){
+    let mut group = c.benchmark_group("sparse Merkle tree batch update");
+    for depth in (10..=20).step_by(2) {
+        let leaves: Vec<String> = (0..1usize << depth).map(|x| x.to_string()).collect();
+        group.bench_with_input(BenchmarkId::from_parameter(depth), &leaves,
+            |b, leaves| b.iter(|| {
+                let mut tree = SparseMerkleTree::<PoseidonHasher>::new(depth, "0").unwrap();
+                tree.update_batch(0, black_box(leaves)).unwrap();
+                tree
+            }));
+    }
+    group.finish();
+}
+
+criterion_group!(
+    name = benches;
+    config = Criterion::default().sample_size(10).measurement_time(Duration::from_millis(1000));
+    targets = hash_tree_construction, sparse_merkle_tree_batch
+);
+criterion_main!(benches);
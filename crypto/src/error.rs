@@ -0,0 +1,37 @@
+use std::{error::Error, fmt};
+
+///Errors raised by the hash and signature functions and the hash trees.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum CryptoError {
+    ///A value does not fit into the finite field.
+    FieldOverflow(String),
+    ///An input is not accepted by the hash or signature function.
+    InvalidInput(String),
+    ///A private key could not be imported.
+    InvalidKey(String),
+    ///An index exceeds the number of elements.
+    IndexOutOfRange { index: usize, len: usize },
+    ///The number of leaves is not a power of the branching factor.
+    InvalidTreeSize(usize),
+    ///A Merkle proof is not well formed.
+    MalformedProof(String),
+}
+
+impl fmt::Display for CryptoError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            CryptoError::FieldOverflow(value) => write!(f, "Value {} outside of the finite field", value),
+            CryptoError::InvalidInput(msg) => write!(f, "Invalid input: {}", msg),
+            CryptoError::InvalidKey(msg) => write!(f, "Invalid key: {}", msg),
+            CryptoError::IndexOutOfRange { index, len } => {
+                write!(f, "Index {} out of range for length {}", index, len)
+            }
+            CryptoError::InvalidTreeSize(size) => {
+                write!(f, "{} leaves are not a power of the branching factor", size)
+            }
+            CryptoError::MalformedProof(msg) => write!(f, "Malformed Merkle proof: {}", msg),
+        }
+    }
+}
+
+impl Error for CryptoError {}
@@ -3,6 +3,7 @@
 //! A Cryptography crate, providing functionallity for hashing and signing data
 //! and creating hash and Merkle trees.
 
+pub mod error;
 pub mod merkle_tree;
 pub mod poseidon;
 
@@ -11,6 +12,8 @@ use std::marker::PhantomData;
 use num_bigint::BigInt;
 use serde::{Serialize, Deserialize};
 
+use error::CryptoError;
+
 ///A trait representing the basic functionallity of a hash function.
 pub trait HashFunction{
 
@@ -18,14 +21,14 @@ pub trait HashFunction{
     fn new() ->Self;
 
     ///Hashes an vector of strings.
-    fn hash(&self,inputs: &Vec<String>) -> BigInt;
+    fn hash(&self,inputs: &Vec<String>) -> Result<BigInt, CryptoError>;
 
     ///Hashes an array of numbers.
-    fn hash_big_int(&self, inputs: &[BigInt]) -> BigInt;
+    fn hash_big_int(&self, inputs: &[BigInt]) -> Result<BigInt, CryptoError>;
 
     ///Hashes a signle string.
     /// Based on the hashing of an array of Strings.
-    fn hash_str(&self,input: &str) -> BigInt{
+    fn hash_str(&self,input: &str) -> Result<BigInt, CryptoError>{
         self.hash(&vec![input.to_owned()])
      }
 }
@@ -35,7 +38,7 @@ pub trait SignatureFunction: Sized{
 
     ///Signs a message with the provided private key.
     fn sign(private_key: BigInt, message: BigInt)
-    -> Signature<Self>;
+    -> Result<Signature<Self>, CryptoError>;
 
     ///Verifies the signature.
     fn verify(sig: &Signature<Self>, msg: &BigInt) -> bool;
@@ -51,6 +54,14 @@ pub struct Signature<S: SignatureFunction>{
     _signature_function: PhantomData<fn()->S>
 }
 
+impl<S: SignatureFunction> Signature<S>{
+
+    ///Instatiates a signature from its components, e.g. after decoding it.
+    pub fn new(r8: [BigInt;2], s: BigInt, public_key_signer: [BigInt;2]) -> Self{
+        Signature { r8, s, public_key_signer, _signature_function: PhantomData::<fn()->S> }
+    }
+}
+
 ///Implements the Clone trait for a Signature.
 impl<S: SignatureFunction> Clone for Signature<S>{
     fn clone(&self) -> Self {
@@ -1,15 +1,19 @@
 //! A hash tree implementation.
 //! 
-//! Provides an implementation for a hash tree. 
-//! In case the BRANCHING_FACTOR=2 it also provides the ability to create a Merkle proof.
+//! Provides an implementation for a hash tree and its Merkle proofs.
+//! In case the BRANCHING_FACTOR=2 it also provides the ability to create Merkle multiproofs.
+//! For large and mostly empty trees a sparse Merkle tree is provided,
+//!  producing the same roots and Merkle proofs as the dense binary hash tree.
+//! For append-only sets, like issuance logs, an incremental Merkle tree stores only the frontier.
 
-use std::{marker::PhantomData, error::Error, vec};
+use std::{collections::{BTreeMap, BTreeSet}, marker::PhantomData, vec};
 
 use num_bigint::BigInt;
-use num_traits::Pow;
 use serde::{Serialize, Deserialize};
+#[cfg(feature = "parallel")]
+use rayon::prelude::*;
 
-use crate::HashFunction;
+use crate::{HashFunction, error::CryptoError};
 
 /// A struct representing a hash tree.
 /// 
@@ -23,12 +27,13 @@ pub struct HashTree<H: HashFunction, const BRANCHING_FACTOR: usize > {
 }
 
 /// A struct representing a Merkle proof.
-/// Only availabe for BRANCHING_FACTOR=2.
+/// 
+/// The lemma holds the hash of the leave, the siblings level by level and the root.
 /// 
 /// Serializable with serde.
 #[derive(Debug,Clone,Serialize,Deserialize)]
 pub struct  MerkleProof<H: HashFunction, const BRANCHING_FACTOR: usize > {
-    pub path: Vec<usize>, //0 or 1
+    pub path: Vec<usize>, //0 to BRANCHING_FACTOR-1
     pub lemma: Vec<BigInt>,
     #[serde(bound(serialize = "PhantomData<fn()->H>: Serialize", deserialize = "PhantomData<fn()->H>: Deserialize<'de>"))]
     _hash_fn: PhantomData<fn()->H>,
@@ -41,6 +46,79 @@ impl<H: HashFunction, const BRANCHING_FACTOR: usize > MerkleProof<H,BRANCHING_FA
         lemma: Vec<BigInt>) -> Self{
             Self { path, lemma, _hash_fn: PhantomData::<fn()->H> }
         }
+
+    ///Retrieves the index of the proven leave.
+    pub fn index(&self) -> usize {
+        self.path.iter().rev().fold(0, |index, position| index * BRANCHING_FACTOR + position)
+    }
+
+    ///Retrieves the root the proof leads to.
+    pub fn root(&self) -> Option<&BigInt> {
+        self.lemma.last()
+    }
+
+    ///Checks if the proof is valid.
+    pub fn verify(&self)
+    ->Result<bool,CryptoError>{
+        let hash_fn = H::new();
+
+        let expected = self.path.len() * (BRANCHING_FACTOR - 1) + 2;
+        if self.lemma.len() != expected {
+            Err(CryptoError::MalformedProof(format!("Expected {} lemma elements, found {}",
+                expected, self.lemma.len())))?
+        }
+
+        let mut current_hash = self.lemma[0].clone();
+
+        for (i, position) in self.path.iter().enumerate() {
+            if *position >= BRANCHING_FACTOR {
+                Err(CryptoError::MalformedProof("Path index out of bounds".to_owned()))?
+            }
+
+            let first = 1 + i * (BRANCHING_FACTOR - 1);
+            let mut children = self.lemma[first..first + BRANCHING_FACTOR - 1].to_vec();
+            children.insert(*position, current_hash);
+            current_hash = hash_fn.hash_big_int(&children)?;
+        }
+
+        Ok(current_hash == self.lemma[self.lemma.len() -1])
+    }
+}
+
+/// A struct representing a Merkle proof for several leaves at once.
+/// Only availabe for BRANCHING_FACTOR=2.
+/// 
+/// Siblings shared by the paths of the leaves or computable from the revealed leaves are omitted.
+/// 
+/// Serializable with serde.
+#[derive(Debug,Serialize,Deserialize)]
+pub struct MerkleMultiProof<H: HashFunction, const BRANCHING_FACTOR: usize > {
+    pub depth: u32,
+    ///Indices of the leaves in ascending order.
+    pub indices: Vec<usize>,
+    ///Hashes of the leaves in the order of the indices.
+    pub leaves: Vec<BigInt>,
+    ///Missing siblings, level by level from the leaves and in ascending order on each level.
+    pub siblings: Vec<BigInt>,
+    pub root: BigInt,
+    #[serde(bound(serialize = "PhantomData<fn()->H>: Serialize", deserialize = "PhantomData<fn()->H>: Deserialize<'de>"))]
+    _hash_fn: PhantomData<fn()->H>,
+}
+
+impl<H: HashFunction, const BRANCHING_FACTOR: usize > MerkleMultiProof<H,BRANCHING_FACTOR> {
+
+    ///Instatiates a new MerkleMultiProof.
+    pub fn new(depth: u32, indices: Vec<usize>, leaves: Vec<BigInt>,
+        siblings: Vec<BigInt>, root: BigInt) -> Self{
+            Self { depth, indices, leaves, siblings, root, _hash_fn: PhantomData::<fn()->H> }
+        }
+}
+
+//Implements the Clone trait
+impl<H: HashFunction, const BRANCHING_FACTOR: usize> Clone for MerkleMultiProof<H,BRANCHING_FACTOR> {
+    fn clone(&self) -> Self {
+        Self::new(self.depth, self.indices.clone(), self.leaves.clone(), self.siblings.clone(), self.root.clone())
+    }
 }
 
 ///Implements generic hash tree functions.
@@ -51,13 +129,15 @@ impl<H: HashFunction, const BRANCHING_FACTOR: usize> HashTree<H, BRANCHING_FACTO
     /// # Arguments
     /// * 'input' - A vector of strings, which become the leaves by hashing.
     /// 
-    /// # Panics
+    /// # Errors
     /// 
-    /// The function whill panic, should the input size not be a power of the BRANCHING_FACTOR.
-    pub fn new(input: &Vec<String>) -> Self {
-        let depth = (input.len() as f32).log(BRANCHING_FACTOR as f32).ceil() as u32;
-
-        assert!((BRANCHING_FACTOR as f32).pow(depth as f32) == (input.len() as f32));
+    /// The function fails, should the input size not be a power of the BRANCHING_FACTOR
+    ///  or a leave not be hashable.
+    pub fn new(input: &Vec<String>) -> Result<Self, CryptoError> {
+        if BRANCHING_FACTOR < 2 {
+            Err(CryptoError::InvalidInput(format!("Branching factor {} below 2", BRANCHING_FACTOR)))?;
+        }
+        let depth = exact_log(input.len(), BRANCHING_FACTOR).ok_or(CryptoError::InvalidTreeSize(input.len()))?;
 
         let data = Vec::<BigInt>::new();
         let mut leaves = Vec::<String>::new();
@@ -66,9 +146,9 @@ impl<H: HashFunction, const BRANCHING_FACTOR: usize> HashTree<H, BRANCHING_FACTO
 
 
         let mut tree = HashTree { depth,leaves, data, _hasher: PhantomData::<fn()->H> };
-        tree.generate_tree();        
+        tree.generate_tree()?;        
         
-        tree
+        Ok(tree)
     }
 
     /// Retrieves the root of the hash tree.
@@ -78,43 +158,109 @@ impl<H: HashFunction, const BRANCHING_FACTOR: usize> HashTree<H, BRANCHING_FACTO
 
     /// Generates the hash tree from the leaves.
     /// 
-    /// While the leaves can be modified, the number of leaves is assumed to be constant.
-    fn generate_tree(&mut self){
-        let hasher = H::new();
-        
-        let size = ( ((BRANCHING_FACTOR as f32).pow((self.depth + 1) as f32) - 1.0)/ ((BRANCHING_FACTOR -1 ) as f32) ) as usize;
-        self.data = Vec::new();
+    /// Should the number of leaves have changed, the depth is recomputed.
+    /// With the feature 'parallel', the leaves and the nodes of each level are hashed in parallel.
+    fn generate_tree(&mut self) -> Result<(), CryptoError>{
+        self.depth = exact_log(self.leaves.len(), BRANCHING_FACTOR).ok_or(CryptoError::InvalidTreeSize(self.leaves.len()))?;
+        self.data = hash_leaves::<H>(&self.leaves)?;
 
-        let mut hashes: Vec<BigInt> = self.leaves.clone().into_iter().map(|x| hasher.hash_str(&x)).collect();
+        let mut start = 0;
+        let mut width = self.leaves.len();
+        while width > 1 {
+            let mut hashes = hash_chunks::<H>(&self.data[start..start + width], BRANCHING_FACTOR)?;
+            self.data.append(&mut hashes);
+            start += width;
+            width /= BRANCHING_FACTOR;
+        }
 
-        self.data.append(&mut hashes);
+        Ok(())
+    }
 
-        for j in (BRANCHING_FACTOR-1..size).step_by(BRANCHING_FACTOR) {
-            self.data.push(hasher.hash_big_int(&self.data[j + 1 -BRANCHING_FACTOR..=j]));
+    ///Checks that the leaves and nodes still form a tree of the stored depth.
+    fn check_size(&self) -> Result<(), CryptoError> {
+        let nodes: usize = (0..=self.depth).map(|level| self.leaves.len() / BRANCHING_FACTOR.pow(level)).sum();
+        if exact_log(self.leaves.len(), BRANCHING_FACTOR) != Some(self.depth) || self.data.len() != nodes {
+            Err(CryptoError::InvalidTreeSize(self.leaves.len()))?;
         }
+        Ok(())
     }
 
     ///Updates the leave at 'index' and regenerates the hash tree.
-    pub fn update(&mut self, index: usize, new_leave: String) {
-        let hasher = H::new();
+    pub fn update(&mut self, index: usize, new_leave: String) -> Result<(), CryptoError> {
+        self.update_batch(index, &vec![new_leave])
+    }
 
-        self.leaves[index] = new_leave.clone();
-        self.data[index] = hasher.hash(&vec![new_leave]);
+    ///Updates the leaves starting at 'index' and regenerates the hash tree.
+    /// 
+    /// Nodes shared by the paths are only hashed once.
+    pub fn update_batch(&mut self, index: usize, new_leaves: &Vec<String>) -> Result<(), CryptoError> {
+        let hasher = H::new();
 
-        let mut i = (index/BRANCHING_FACTOR)*BRANCHING_FACTOR;
-        let mut s = 0 as usize;
-        for k in 0..self.depth {
+        self.check_size()?;
+        let d = new_leaves.len();
+        if d == 0 {
+            return Ok(());
+        }
+        let last = index.saturating_add(d - 1);
+        if last >= self.leaves.len() {
+            Err(CryptoError::IndexOutOfRange { index: last, len: self.leaves.len() })?;
+        }
 
-            let i_n = i/BRANCHING_FACTOR;
-            let s_n = s + (BRANCHING_FACTOR as f32).pow((self.depth -k) as f32) as usize;
+        for (k, leave) in new_leaves.iter().enumerate() {
+            self.data[index+k] = hasher.hash_str(leave)?;
+            self.leaves[index+k] = leave.clone();
+        }
 
-            self.data[s_n + i_n] = hasher.hash_big_int(&self.data[s+i..s+i+BRANCHING_FACTOR]);
-            
+        let mut first = index;
+        let mut last = last;
+        let mut s = 0;
+        let mut width = self.leaves.len();
+        for _ in 0..self.depth {
+            let s_n = s + width;
+            first /= BRANCHING_FACTOR;
+            last /= BRANCHING_FACTOR;
+
+            for j in first..=last {
+                let children = s + j * BRANCHING_FACTOR;
+                self.data[s_n + j] = hasher.hash_big_int(&self.data[children..children + BRANCHING_FACTOR])?;
+            }
 
-            i = (i_n/BRANCHING_FACTOR)*BRANCHING_FACTOR;
             s = s_n;
+            width /= BRANCHING_FACTOR;
         }
-        
+
+        Ok(())
+    }
+
+    ///Generates a Merkle proof for the leave at 'index'.
+    /// 
+    /// Each level of the proof carries the position among the children
+    ///  and the BRANCHING_FACTOR-1 siblings in their order.
+    pub fn generate_proof(&self,index: usize)
+    -> Result<MerkleProof<H,BRANCHING_FACTOR>,CryptoError>{
+        self.check_size()?;
+        if self.leaves.len() <= index {
+            Err(CryptoError::IndexOutOfRange { index, len: self.leaves.len() })?;
+        }
+
+        let mut path = Vec::with_capacity(self.depth as usize);
+        let mut lemma  = vec![self.data[index].clone()];
+        let mut offset: usize = 0;
+        let mut pos = index;
+        let mut width = self.leaves.len();
+        for _ in 0 .. self.depth as usize {
+            let position = pos % BRANCHING_FACTOR;
+            let first = offset + pos - position;
+            path.push(position);
+            lemma.extend((0..BRANCHING_FACTOR).filter(|j| *j != position).map(|j| self.data[first + j].clone()));
+
+            pos /= BRANCHING_FACTOR;
+            offset += width;
+            width /= BRANCHING_FACTOR;
+        }
+        lemma.push(self.get_root().clone());
+
+        Ok(MerkleProof::new(path,lemma))
     }
 
 
@@ -156,136 +302,569 @@ impl<H: HashFunction, const BRANCHING_FACTOR: usize> ToString for HashTree<H,BRA
 ///Implements the Merkle tree functionality.
 impl<H: HashFunction> HashTree<H,2>  {
     
-    ///Generates a Merkle proof for the leave at 'index'.
-    pub fn generate_proof(&self,index: usize)
-    -> Result<MerkleProof<H,2>,Box<dyn Error>>{
-        if self.leaves.len() <= index {
-            Err("Index out of bounds")?;
-        } 
-        let mut path = vec![0 as usize;self.depth as usize];
+    ///Generates a Merkle multiproof for the leaves at 'indices'.
+    pub fn generate_multi_proof(&self, indices: &[usize])
+    -> Result<MerkleMultiProof<H,2>,CryptoError>{
+        let offsets: Vec<usize> = (0..=self.depth).scan(0, |offset, level| {
+            let current = *offset;
+            *offset += self.leaves.len() >> level;
+            Some(current)
+        }).collect();
+
+        multi_proof(self.depth, indices, |level, pos| &self.data[offsets[level] + pos])
+    }
 
-        for i in 0 .. self.depth as usize{
-            path[i] = ((index >> i) & 1) as usize;
+
+}
+
+///Implements the Merkle multiproof functionality.
+impl<H: HashFunction>  MerkleMultiProof<H,2>{
+
+    ///Checks if the proof is valid.
+    pub fn verify(&self)
+    ->Result<bool,CryptoError>{
+        let hash_fn = H::new();
+
+        if self.indices.is_empty() || self.indices.len() != self.leaves.len() {
+            Err(CryptoError::MalformedProof(format!("{} indices for {} leaves",
+                self.indices.len(), self.leaves.len())))?
+        }
+        if self.depth >= usize::BITS {
+            Err(CryptoError::MalformedProof(format!("Depth {} exceeds the addressable leaves", self.depth)))?
+        }
+        if self.indices.windows(2).any(|w| w[0] >= w[1]) || self.indices[self.indices.len() - 1] >> self.depth != 0 {
+            Err(CryptoError::MalformedProof("Indices not ascending or out of range".to_owned()))?
         }
 
-        let mut lemma  = vec![self.data[index].clone()];
-        let mut offset: usize = 0; 
-        let mut pos = index;
-        let mut width = self.leaves.len();
-        for i in 0 .. self.depth as usize {
-            if path[i] == 1{
-                lemma.push(self.data[offset + pos -1].clone());
-            } else {
-                lemma.push(self.data[offset + pos +1].clone());
+        let mut known: BTreeMap<usize, BigInt> = self.indices.iter().cloned().zip(self.leaves.iter().cloned()).collect();
+        let mut siblings = self.siblings.iter();
+        for _ in 0..self.depth {
+            let mut parents = BTreeMap::new();
+            for (&pos, hash) in known.iter() {
+                if parents.contains_key(&(pos >> 1)) {
+                    continue;
+                }
+                let sibling = match known.get(&(pos ^ 1)) {
+                    Some(sibling) => sibling,
+                    None => siblings.next().ok_or_else(|| CryptoError::MalformedProof("Missing siblings".to_owned()))?,
+                };
+                let pair = if pos & 1 == 0 { [hash.clone(), sibling.clone()] } else { [sibling.clone(), hash.clone()] };
+                parents.insert(pos >> 1, hash_fn.hash_big_int(&pair)?);
             }
-            pos >>= 1;
-            offset += width;
-            width >>= 1;
+            known = parents;
         }
-        lemma.push(self.get_root().clone());
 
-        Ok(MerkleProof::<H,2>::new(path,lemma))
-        
+        if siblings.next().is_some() {
+            Err(CryptoError::MalformedProof("Unused siblings".to_owned()))?
+        }
+        Ok(known.get(&0) == Some(&self.root))
     }
+}
 
-    
+///Collects the siblings of a binary tree not computable from the leaves at 'indices'.
+/// 
+/// The node at 'pos' on 'level' is retrieved by 'node', with the leaves being on level 0.
+fn multi_proof<'a, H: HashFunction>(depth: u32, indices: &[usize], node: impl Fn(usize, usize) -> &'a BigInt)
+-> Result<MerkleMultiProof<H,2>, CryptoError> {
+    let mut indices = indices.to_vec();
+    indices.sort_unstable();
+    indices.dedup();
+    match indices.last() {
+        None => Err(CryptoError::InvalidInput("No leaves to prove".to_owned()))?,
+        Some(&index) if index >> depth != 0 => Err(CryptoError::IndexOutOfRange { index, len: 1 << depth })?,
+        _ => (),
+    }
 
+    let leaves = indices.iter().map(|&index| node(0, index).clone()).collect();
+    let mut siblings = Vec::new();
+    let mut known: BTreeSet<usize> = indices.iter().cloned().collect();
+    for level in 0..depth as usize {
+        for &pos in known.iter() {
+            if pos & 1 == 1 && known.contains(&(pos ^ 1)) {
+                continue;
+            }
+            if !known.contains(&(pos ^ 1)) {
+                siblings.push(node(level, pos ^ 1).clone());
+            }
+        }
+        known = known.iter().map(|pos| pos >> 1).collect();
+    }
+
+    Ok(MerkleMultiProof::new(depth, indices, leaves, siblings, node(depth as usize, 0).clone()))
+}
+
+/// A struct representing a sparse binary Merkle tree.
+/// 
+/// Equivalent to a 'HashTree<H,2>' with 2^depth leaves, all initialized with the default leave.
+/// Only the leaves differing from the default leave and the nodes on their paths are stored,
+///  the remaining nodes are taken from the precomputed hashes of the empty subtrees.
+/// 
+/// Serializable with serde.
+#[derive(Debug, Serialize, Deserialize)]
+pub struct SparseMerkleTree<H: HashFunction> {
+    depth: u32,
+    default_leave: String,
+    ///Leaves differing from the default leave.
+    leaves: BTreeMap<usize, String>,
+    ///Populated nodes per level, starting with the hashes of the leaves.
+    nodes: Vec<BTreeMap<usize, BigInt>>,
+    ///Hashes of the empty subtrees per level.
+    empty: Vec<BigInt>,
+    _hasher: PhantomData<fn() -> H>,
+}
+
+impl<H: HashFunction> SparseMerkleTree<H> {
+
+    ///Instatiates a new sparse Merkle tree.
+    /// 
+    /// # Arguments
+    /// * 'depth' - The depth of the tree, resulting in 2^depth leaves.
+    /// * 'default_leave' - The value of all leaves, which have not been updated.
+    /// 
+    /// # Errors
+    /// 
+    /// The function fails, should the number of leaves not be representable
+    ///  or the default leave not be hashable.
+    pub fn new(depth: u32, default_leave: &str) -> Result<Self, CryptoError> {
+        if depth >= usize::BITS {
+            Err(CryptoError::InvalidInput(format!("Depth {} exceeds the addressable leaves", depth)))?;
+        }
 
-    ///Updates the leaves starting at 'index' and regenerates the hash tree.
-    pub fn update_batch(&mut self, index: usize, new_leaves: &Vec<String>) {
         let hasher = H::new();
+        let mut empty = vec![hasher.hash_str(default_leave)?];
+        for i in 0..depth as usize {
+            let hash = hasher.hash_big_int(&[empty[i].clone(), empty[i].clone()])?;
+            empty.push(hash);
+        }
 
-        let d = new_leaves.len();
+        Ok(SparseMerkleTree {
+            depth,
+            default_leave: default_leave.to_owned(),
+            leaves: BTreeMap::new(),
+            nodes: vec![BTreeMap::new(); depth as usize + 1],
+            empty,
+            _hasher: PhantomData::<fn()->H>,
+        })
+    }
+
+    ///Retrieves the depth of the tree.
+    pub fn depth(&self) -> u32 {
+        self.depth
+    }
+
+    ///Retrieves the number of leaves, including the default leaves.
+    pub fn leave_count(&self) -> usize {
+        1 << self.depth
+    }
+
+    /// Retrieves the root of the tree.
+    pub fn get_root(&self) -> &BigInt {
+        self.node(self.depth as usize, 0)
+    }
 
-        for k in 0..d {
-            self.leaves[index+k] = new_leaves[k].clone();
-            self.data[index+k] = hasher.hash_str(&new_leaves[k]);
+    ///Retrieves the leave at 'index'.
+    pub fn get_leave(&self, index: usize) -> Result<&str, CryptoError> {
+        self.check_index(index)?;
+        Ok(self.leaves.get(&index).map(|x| x.as_str()).unwrap_or(&self.default_leave))
+    }
+
+    ///Iterates over the leaves differing from the default leave in ascending order.
+    pub fn populated_leaves(&self) -> impl Iterator<Item = (usize, &str)> {
+        self.leaves.iter().map(|(i, x)| (*i, x.as_str()))
+    }
+
+    ///Retrieves the node at 'index' on 'level', with the leaves being on level 0.
+    fn node(&self, level: usize, index: usize) -> &BigInt {
+        self.nodes[level].get(&index).unwrap_or(&self.empty[level])
+    }
+
+    ///Stores the node, dropping it should it equal the empty subtree.
+    fn set_node(&mut self, level: usize, index: usize, hash: BigInt) {
+        if hash == self.empty[level] {
+            self.nodes[level].remove(&index);
+        } else {
+            self.nodes[level].insert(index, hash);
+        }
+    }
+
+    fn check_index(&self, index: usize) -> Result<(), CryptoError> {
+        if index >= self.leave_count() {
+            Err(CryptoError::IndexOutOfRange { index, len: self.leave_count() })?;
+        }
+        Ok(())
+    }
+
+    ///Stores the leave without updating the nodes above it.
+    fn set_leave(&mut self, hasher: &H, index: usize, new_leave: String) -> Result<(), CryptoError> {
+        let hash = hasher.hash_str(&new_leave)?;
+        if new_leave == self.default_leave {
+            self.leaves.remove(&index);
+        } else {
+            self.leaves.insert(index, new_leave);
+        }
+        self.set_node(0, index, hash);
+        Ok(())
+    }
+
+    ///Updates the leave at 'index' and the path to the root.
+    pub fn update(&mut self, index: usize, new_leave: String) -> Result<(), CryptoError> {
+        self.check_index(index)?;
+
+        let hasher = H::new();
+        self.set_leave(&hasher, index, new_leave)?;
+
+        let mut pos = index;
+        for level in 0..self.depth as usize {
+            let left = pos & !1;
+            let hash = hasher.hash_big_int(&[self.node(level, left).clone(), self.node(level, left + 1).clone()])?;
+            pos >>= 1;
+            self.set_node(level + 1, pos, hash);
+        }
+
+        Ok(())
+    }
+
+    ///Updates the leaves starting at 'index' and the paths to the root.
+    /// 
+    /// Nodes shared by the paths are only hashed once.
+    pub fn update_batch(&mut self, index: usize, new_leaves: &Vec<String>) -> Result<(), CryptoError> {
+        if new_leaves.is_empty() {
+            return Ok(());
         }
+        self.check_index(index + new_leaves.len() - 1)?;
 
-        let mut i = (index/2)*2;
-        let mut e = index + d - 1 ;
-        e = (e/2)*2+1;
-        let mut s = 0 as usize;
+        let hasher = H::new();
+        for (k, leave) in new_leaves.iter().enumerate() {
+            self.set_leave(&hasher, index + k, leave.clone())?;
+        }
 
+        self.update_paths((index..index + new_leaves.len()).collect())
+    }
+
+    ///Updates the leaves at arbitrary positions and the paths to the root.
+    /// 
+    /// Nodes shared by the paths are only hashed once.
+    /// Should an index occur multiple times, the last leave is kept.
+    /// 
+    /// # Errors
+    /// 
+    /// The function fails without modifying the tree, should an index be out of range.
+    pub fn update_leaves(&mut self, new_leaves: &[(usize, String)]) -> Result<(), CryptoError> {
+        for (index, _) in new_leaves {
+            self.check_index(*index)?;
+        }
 
-        for k in 0..self.depth {
+        let hasher = H::new();
+        for (index, leave) in new_leaves {
+            self.set_leave(&hasher, *index, leave.clone())?;
+        }
 
-            let i_n = i/2;
-            let e_n = e/2;
-            let s_n = s + (2 as f32).pow((self.depth -k) as f32) as usize;
+        self.update_paths(new_leaves.iter().map(|(index, _)| *index).collect())
+    }
 
-            for j in i_n..=e_n {
-                let l = j-i_n;
-                self.data[s_n +j] = hasher.hash_big_int(&self.data[s+i+2*l..=s+i+2*l+1]);
+    ///Recomputes the nodes above the changed leaves level by level.
+    /// With the feature 'parallel', the nodes of each level are hashed in parallel.
+    fn update_paths(&mut self, mut changed: BTreeSet<usize>) -> Result<(), CryptoError> {
+        for level in 0..self.depth as usize {
+            let parents: BTreeSet<usize> = changed.iter().map(|i| i >> 1).collect();
+            let children: Vec<BigInt> = parents.iter()
+                .flat_map(|parent| [self.node(level, 2 * parent).clone(), self.node(level, 2 * parent + 1).clone()])
+                .collect();
+            let hashes = hash_chunks::<H>(&children, 2)?;
+            for (&parent, hash) in parents.iter().zip(hashes) {
+                self.set_node(level + 1, parent, hash);
             }
+            changed = parents;
+        }
 
-            i = (i_n/2)*2;
-            e = (e_n/2)*2+1;
-            s = s_n;
+        Ok(())
+    }
+
+    ///Generates a Merkle proof for the leave at 'index'.
+    /// 
+    /// The proof is identical to the one of the equivalent dense 'HashTree<H,2>'.
+    pub fn generate_proof(&self, index: usize) -> Result<MerkleProof<H,2>, CryptoError> {
+        self.check_index(index)?;
+
+        let mut path = Vec::with_capacity(self.depth as usize);
+        let mut lemma = vec![self.node(0, index).clone()];
+        let mut pos = index;
+        for level in 0..self.depth as usize {
+            path.push(pos & 1);
+            lemma.push(self.node(level, pos ^ 1).clone());
+            pos >>= 1;
         }
+        lemma.push(self.get_root().clone());
+
+        Ok(MerkleProof::<H,2>::new(path, lemma))
+    }
 
+    ///Generates a Merkle multiproof for the leaves at 'indices'.
+    /// 
+    /// The proof is identical to the one of the equivalent dense 'HashTree<H,2>'.
+    pub fn generate_multi_proof(&self, indices: &[usize]) -> Result<MerkleMultiProof<H,2>, CryptoError> {
+        multi_proof(self.depth, indices, |level, pos| self.node(level, pos))
     }
 }
 
-///Implements the Merkle proof functionality.
-impl<H: HashFunction>  MerkleProof<H,2>{
-    
-    ///Checks if the proof is valid.
-    pub fn verify(&self)
-    ->Result<bool,Box<dyn Error>>{
-        let hash_fn = H::new();
+//Implements the Clone trait
+impl<H: HashFunction> Clone for SparseMerkleTree<H> {
+    fn clone(&self) -> Self {
+        Self { depth: self.depth, default_leave: self.default_leave.clone(), leaves: self.leaves.clone(),
+            nodes: self.nodes.clone(), empty: self.empty.clone(), _hasher: PhantomData::<fn()->H> }
+    }
+}
 
-        let mut current_hash = self.lemma[0].clone();
+/// A struct representing an append-only binary Merkle tree, like the commitment trees of Zcash and Tornado Cash.
+/// 
+/// Equivalent to a 'SparseMerkleTree<H>' whose leaves are set from left to right.
+/// Only the frontier, the rightmost node per level, is stored,
+///  as well as the Merkle paths of the tracked leaves, which are updated on every append.
+/// 
+/// Serializable with serde.
+#[derive(Debug, Serialize, Deserialize)]
+pub struct IncrementalMerkleTree<H: HashFunction> {
+    depth: u32,
+    ///Index of the next leave.
+    next_index: usize,
+    ///Last left node per level, completed or not.
+    frontier: Vec<BigInt>,
+    ///Hashes of the empty subtrees per level.
+    empty: Vec<BigInt>,
+    root: BigInt,
+    ///Hashes and Merkle paths of the tracked leaves.
+    tracked: BTreeMap<usize, (BigInt, Vec<BigInt>)>,
+    _hasher: PhantomData<fn() -> H>,
+}
+
+impl<H: HashFunction> IncrementalMerkleTree<H> {
+
+    ///Instatiates a new incremental Merkle tree without leaves.
+    /// 
+    /// # Arguments
+    /// * 'depth' - The depth of the tree, resulting in a capacity of 2^depth leaves.
+    /// * 'default_leave' - The value of the leaves not yet appended.
+    /// 
+    /// # Errors
+    /// 
+    /// The function fails, should the number of leaves not be representable
+    ///  or the default leave not be hashable.
+    pub fn new(depth: u32, default_leave: &str) -> Result<Self, CryptoError> {
+        if depth >= usize::BITS {
+            Err(CryptoError::InvalidInput(format!("Depth {} exceeds the addressable leaves", depth)))?;
+        }
+
+        let hasher = H::new();
+        let mut empty = vec![hasher.hash_str(default_leave)?];
+        for i in 0..depth as usize {
+            let hash = hasher.hash_big_int(&[empty[i].clone(), empty[i].clone()])?;
+            empty.push(hash);
+        }
+
+        Ok(IncrementalMerkleTree {
+            depth,
+            next_index: 0,
+            frontier: empty[..depth as usize].to_vec(),
+            root: empty[depth as usize].clone(),
+            empty,
+            tracked: BTreeMap::new(),
+            _hasher: PhantomData::<fn()->H>,
+        })
+    }
+
+    ///Retrieves the depth of the tree.
+    pub fn depth(&self) -> u32 {
+        self.depth
+    }
+
+    ///Retrieves the maximum number of leaves.
+    pub fn capacity(&self) -> usize {
+        1 << self.depth
+    }
+
+    ///Retrieves the number of appended leaves.
+    pub fn appended_count(&self) -> usize {
+        self.next_index
+    }
+
+    /// Retrieves the root of the tree.
+    pub fn root(&self) -> &BigInt {
+        &self.root
+    }
+
+    ///Appends the leave and updates the Merkle paths of the tracked leaves.
+    /// 
+    /// Returns the index of the leave.
+    /// 
+    /// # Errors
+    /// 
+    /// The function fails, should the tree be full or the leave not be hashable.
+    pub fn append(&mut self, leave: &str) -> Result<usize, CryptoError> {
+        let index = self.next_index;
+        if index >= self.capacity() {
+            Err(CryptoError::IndexOutOfRange { index, len: self.capacity() })?;
+        }
 
-        for i in 0..self.path.len() {
-
-            if self.path[i] == 0 {
-                current_hash = hash_fn.hash_big_int(&[
-                    current_hash,
-                    self.lemma[i+1].clone()
-                ]);
-            }else if self.path[i]  == 1{
-                current_hash = hash_fn.hash_big_int(&[
-                    self.lemma[i+1].clone(),
-                    current_hash
-                ]);
-            }else {
-                Err("Path index out of bounds")?
+        let hasher = H::new();
+        let mut hash = hasher.hash_str(leave)?;
+        for level in 0..self.depth as usize {
+            let pos = index >> level;
+            //the new node is the sibling on this level of the tracked leaves in the neighbouring subtree
+            for (tracked, (_, path)) in self.tracked.iter_mut() {
+                if (tracked >> level) ^ 1 == pos {
+                    path[level] = hash.clone();
+                }
             }
+
+            hash = if pos & 1 == 0 {
+                self.frontier[level] = hash.clone();
+                hasher.hash_big_int(&[hash, self.empty[level].clone()])?
+            } else {
+                hasher.hash_big_int(&[self.frontier[level].clone(), hash])?
+            };
         }
-        
-        Ok(current_hash == self.lemma[self.lemma.len() -1])
+
+        self.root = hash;
+        self.next_index += 1;
+        Ok(index)
+    }
+
+    ///Appends the leave and tracks it for generating Merkle proofs.
+    /// 
+    /// Returns the index of the leave.
+    pub fn append_tracked(&mut self, leave: &str) -> Result<usize, CryptoError> {
+        let index = self.append(leave)?;
+
+        let path = (0..self.depth as usize).map(|level| {
+            if (index >> level) & 1 == 1 {
+                self.frontier[level].clone()
+            } else {
+                self.empty[level].clone()
+            }
+        }).collect();
+        self.tracked.insert(index, (H::new().hash_str(leave)?, path));
+        Ok(index)
+    }
+
+    ///Stops tracking the leave at 'index', returns false should it not be tracked.
+    pub fn untrack(&mut self, index: usize) -> bool {
+        self.tracked.remove(&index).is_some()
+    }
+
+    ///Iterates over the indices of the tracked leaves in ascending order.
+    pub fn tracked(&self) -> impl Iterator<Item = usize> + '_ {
+        self.tracked.keys().copied()
+    }
+
+    ///Generates a Merkle proof against the current root for the tracked leave at 'index'.
+    /// 
+    /// The proof is identical to the one of the equivalent 'SparseMerkleTree<H>'.
+    pub fn generate_proof(&self, index: usize) -> Result<MerkleProof<H,2>, CryptoError> {
+        let (hash, path) = match self.tracked.get(&index) {
+            Some(witness) => witness,
+            None => Err(CryptoError::InvalidInput(format!("Leave {} not tracked", index)))?,
+        };
+
+        let mut lemma = vec![hash.clone()];
+        lemma.extend(path.iter().cloned());
+        lemma.push(self.root.clone());
+        let path = (0..self.depth as usize).map(|level| (index >> level) & 1).collect();
+
+        Ok(MerkleProof::<H,2>::new(path, lemma))
+    }
+}
+
+//Implements the Clone trait
+impl<H: HashFunction> Clone for IncrementalMerkleTree<H> {
+    fn clone(&self) -> Self {
+        Self { depth: self.depth, next_index: self.next_index, frontier: self.frontier.clone(),
+            empty: self.empty.clone(), root: self.root.clone(), tracked: self.tracked.clone(),
+            _hasher: PhantomData::<fn()->H> }
     }
 }
 
 /// Helper function for preparing the inputs for a hash tree.
 /// 
 /// Fills the 'values' vector up to the next bigger power of the branching factor.
-pub fn fill_vec(values: &mut Vec<String>, branching_factor: usize){
-    let depth = (values.len() as f32).log(branching_factor as f32).ceil();
-    let leave_count =  (branching_factor as f32).pow(depth) as usize;
+/// 
+/// # Errors
+/// 
+/// The function fails, should the branching factor be below 2
+///  or the next power not be representable.
+pub fn fill_vec(values: &mut Vec<String>, branching_factor: usize) -> Result<(), CryptoError>{
+    if branching_factor < 2 {
+        Err(CryptoError::InvalidInput(format!("Branching factor {} below 2", branching_factor)))?;
+    }
+
+    let mut leave_count: usize = 1;
+    while leave_count < values.len() {
+        leave_count = leave_count.checked_mul(branching_factor).ok_or(CryptoError::InvalidTreeSize(values.len()))?;
+    }
+
+    values.resize(leave_count, "".to_owned());
+    Ok(())
+}
 
-    for _i in values.len() .. leave_count{
-        values.push("".to_owned());
+///Hashes the leaves.
+#[cfg(not(feature = "parallel"))]
+fn hash_leaves<H: HashFunction>(leaves: &[String]) -> Result<Vec<BigInt>, CryptoError> {
+    let hasher = H::new();
+    leaves.iter().map(|x| hasher.hash_str(x)).collect()
+}
+
+///Hashes the leaves in parallel.
+#[cfg(feature = "parallel")]
+fn hash_leaves<H: HashFunction>(leaves: &[String]) -> Result<Vec<BigInt>, CryptoError> {
+    leaves.par_iter().map_init(H::new, |hasher, x| hasher.hash_str(x)).collect()
+}
+
+///Hashes the consecutive chunks of 'size' nodes.
+#[cfg(not(feature = "parallel"))]
+fn hash_chunks<H: HashFunction>(nodes: &[BigInt], size: usize) -> Result<Vec<BigInt>, CryptoError> {
+    let hasher = H::new();
+    nodes.chunks(size).map(|x| hasher.hash_big_int(x)).collect()
+}
+
+///Hashes the consecutive chunks of 'size' nodes in parallel.
+#[cfg(feature = "parallel")]
+fn hash_chunks<H: HashFunction>(nodes: &[BigInt], size: usize) -> Result<Vec<BigInt>, CryptoError> {
+    nodes.par_chunks(size).map_init(H::new, |hasher, x| hasher.hash_big_int(x)).collect()
+}
+
+///Computes the exponent of 'size' to the base 'branching_factor', should 'size' be an exact power.
+fn exact_log(size: usize, branching_factor: usize) -> Option<u32> {
+    if branching_factor < 2 || size == 0 {
+        return None;
     }
+
+    let mut depth = 0;
+    let mut rest = size;
+    while rest % branching_factor == 0 {
+        rest /= branching_factor;
+        depth += 1;
+    }
+    (rest == 1).then_some(depth)
 }
 
 #[cfg(test)]
 mod test{
-    use crate::poseidon::PoseidonHasher;
+    use num_bigint::BigInt;
+    use proptest::prelude::*;
+
+    use crate::{poseidon::PoseidonHasher, error::CryptoError, HashFunction};
 
-    use super::HashTree;
+    use super::{fill_vec, HashTree, IncrementalMerkleTree, MerkleMultiProof, SparseMerkleTree};
 
     ///TEST: basic hash tree functionality
     #[test]
     fn hash_tree(){
         let mut tree = HashTree::<PoseidonHasher,2>::new(
             &vec!["a".to_owned(),"b".to_owned(),"c".to_owned(),"d".to_owned()]
-        );
+        ).unwrap();
         assert!(tree.get_root().to_string() ==
          "8535275740347089689449340416732452215544475238884505322559258662396662865912");
 
-        tree.update(0, "b".to_owned());
+        tree.update(0, "b".to_owned()).unwrap();
         assert!(tree.get_root().to_string() ==
          "3044625888948158814383343899956680036611526934629800520116421530768212335650");
 
@@ -296,27 +875,47 @@ mod test{
     fn merkle_proof(){
         let tree = HashTree::<PoseidonHasher,2>::new(
             &vec!["a".to_owned(),"b".to_owned(),"c".to_owned(),"d".to_owned()]
-        );
+        ).unwrap();
 
         let merkle_proof = tree.generate_proof(3).unwrap();
         assert!(merkle_proof.verify().unwrap());
     }
 
+    ///TEST: invalid sizes and indices
+    #[test]
+    fn hash_tree_errors(){
+        let tree = HashTree::<PoseidonHasher,2>::new(
+            &vec!["a".to_owned(),"b".to_owned(),"c".to_owned()]
+        );
+        assert_eq!(tree.err(), Some(CryptoError::InvalidTreeSize(3)));
+
+        let mut tree = HashTree::<PoseidonHasher,2>::new(
+            &vec!["a".to_owned(),"b".to_owned(),"c".to_owned(),"d".to_owned()]
+        ).unwrap();
+        assert_eq!(tree.update(4, "e".to_owned()), Err(CryptoError::IndexOutOfRange { index: 4, len: 4 }));
+        assert!(tree.update_batch(3, &vec!["e".to_owned(),"f".to_owned()]).is_err());
+        assert!(tree.generate_proof(4).is_err());
+
+        let mut merkle_proof = tree.generate_proof(3).unwrap();
+        merkle_proof.lemma.pop();
+        assert!(merkle_proof.verify().is_err());
+    }
+
     ///TEST: updates
     #[test]
     fn merkle_updates_simple(){
         let mut tree = HashTree::<PoseidonHasher,2>::new(
             &vec!["a".to_owned(),"b".to_owned(),"c".to_owned(),"d".to_owned()]
-        );
+        ).unwrap();
         println!("{}", tree.to_string());
 
-        tree.update(1, "c".to_owned());
+        tree.update(1, "c".to_owned()).unwrap();
         println!("{}", tree.to_string());
 
-        tree.update_batch(1, &vec!["c".to_owned(),"c".to_owned(),"d".to_owned()]);
+        tree.update_batch(1, &vec!["c".to_owned(),"c".to_owned(),"d".to_owned()]).unwrap();
         println!("{}", tree.to_string());
 
-        tree.generate_tree();
+        tree.generate_tree().unwrap();
         println!("{}", tree.to_string());
     }
 
@@ -325,16 +924,16 @@ mod test{
     fn merkle_updates_large(){
         let mut tree = HashTree::<PoseidonHasher,2>::new(
             &(0..1024).into_iter().map(|x| x.to_string()).collect()
-        );
+        ).unwrap();
         println!("{}", tree.get_root());
 
-        tree.update(351, "1234".to_owned());
+        tree.update(351, "1234".to_owned()).unwrap();
         println!("{}", tree.get_root());
 
-        tree.update_batch(351, &vec!["1234".to_owned(),"352".to_owned(),"353".to_owned()]);
+        tree.update_batch(351, &vec!["1234".to_owned(),"352".to_owned(),"353".to_owned()]).unwrap();
         println!("{}", tree.get_root());
 
-        tree.generate_tree();
+        tree.generate_tree().unwrap();
         println!("{}", tree.get_root());
     }
 
@@ -343,25 +942,397 @@ mod test{
     fn merkle_updates_branching(){
         let mut tree = HashTree::<PoseidonHasher,3>::new(
             &(0..9).into_iter().map(|x| x.to_string()).collect()
-        );
+        ).unwrap();
         println!("{}", tree.to_string());
 
-        tree.update(7, "7".to_owned());
+        tree.update(7, "7".to_owned()).unwrap();
         println!("{}", tree.to_string());
 
-        tree.generate_tree();
+        tree.generate_tree().unwrap();
         println!("{}\n\n", tree.to_string());
 
         
         let mut tree = HashTree::<PoseidonHasher,8>::new(
             &(0..512).into_iter().map(|x| x.to_string()).collect()
-        );
+        ).unwrap();
         println!("{}", tree.get_root());
 
-        tree.update(64, "64".to_owned());
+        tree.update(64, "64".to_owned()).unwrap();
         println!("{}", tree.get_root());
 
-        tree.generate_tree();
+        tree.generate_tree().unwrap();
         println!("{}", tree.get_root());
     }
-}
\ No newline at end of file
+
+    ///TEST: sparse Merkle tree matches the dense Merkle tree
+    #[test]
+    fn sparse_merkle_tree(){
+        let mut dense = HashTree::<PoseidonHasher,2>::new(&vec!["0".to_owned(); 16]).unwrap();
+        let mut sparse = SparseMerkleTree::<PoseidonHasher>::new(4, "0").unwrap();
+        assert_eq!(sparse.get_root(), dense.get_root());
+
+        dense.update(5, "32".to_owned()).unwrap();
+        sparse.update(5, "32".to_owned()).unwrap();
+        assert_eq!(sparse.get_root(), dense.get_root());
+
+        let leaves = vec!["1".to_owned(), "0".to_owned(), "7".to_owned()];
+        dense.update_batch(9, &leaves).unwrap();
+        sparse.update_batch(9, &leaves).unwrap();
+        assert_eq!(sparse.get_root(), dense.get_root());
+        assert_eq!(sparse.get_leave(11).unwrap(), "7");
+        assert_eq!(sparse.get_leave(10).unwrap(), "0");
+
+        let leaves = vec![(14, "3".to_owned()), (2, "4".to_owned()), (14, "5".to_owned())];
+        dense.update(2, "4".to_owned()).unwrap();
+        dense.update(14, "5".to_owned()).unwrap();
+        sparse.update_leaves(&leaves).unwrap();
+        assert_eq!(sparse.get_root(), dense.get_root());
+        assert!(sparse.update_leaves(&vec![(3, "1".to_owned()), (16, "1".to_owned())]).is_err());
+        assert_eq!(sparse.get_leave(3).unwrap(), "0");
+        dense.update_batch(2, &vec!["0".to_owned()]).unwrap();
+        dense.update(14, "0".to_owned()).unwrap();
+        sparse.update_leaves(&vec![(2, "0".to_owned()), (14, "0".to_owned())]).unwrap();
+        assert_eq!(sparse.populated_leaves().map(|(i, _)| i).collect::<Vec<_>>(), vec![5, 9, 11]);
+
+        for index in [0, 5, 10, 15] {
+            let proof = sparse.generate_proof(index).unwrap();
+            let dense_proof = dense.generate_proof(index).unwrap();
+            assert_eq!(proof.path, dense_proof.path);
+            assert_eq!(proof.lemma, dense_proof.lemma);
+            assert!(proof.verify().unwrap());
+        }
+
+        //resetting a leave prunes its path
+        sparse.update(5, "0".to_owned()).unwrap();
+        sparse.update_batch(9, &vec!["0".to_owned(); 3]).unwrap();
+        let empty = SparseMerkleTree::<PoseidonHasher>::new(4, "0").unwrap();
+        assert_eq!(sparse.get_root(), empty.get_root());
+        assert!(sparse.nodes.iter().all(|level| level.is_empty()));
+
+        assert_eq!(sparse.update(16, "1".to_owned()), Err(CryptoError::IndexOutOfRange { index: 16, len: 16 }));
+        assert!(sparse.update_batch(15, &vec!["1".to_owned(); 2]).is_err());
+        assert!(sparse.generate_proof(16).is_err());
+        assert!(SparseMerkleTree::<PoseidonHasher>::new(usize::BITS, "0").is_err());
+    }
+
+    ///TEST: deep sparse Merkle tree
+    #[test]
+    fn sparse_merkle_tree_deep(){
+        let mut tree = SparseMerkleTree::<PoseidonHasher>::new(32, "0").unwrap();
+        tree.update(3_000_000_000, "1".to_owned()).unwrap();
+
+        let proof = tree.generate_proof(3_000_000_000).unwrap();
+        assert_eq!(proof.lemma.len(), 34);
+        assert!(proof.verify().unwrap());
+        assert!(tree.nodes.iter().all(|level| level.len() == 1));
+    }
+
+    ///TEST: incremental Merkle tree matches the sparse Merkle tree
+    #[test]
+    fn incremental_merkle_tree(){
+        let mut incremental = IncrementalMerkleTree::<PoseidonHasher>::new(4, "0").unwrap();
+        let mut sparse = SparseMerkleTree::<PoseidonHasher>::new(4, "0").unwrap();
+        assert_eq!(incremental.root(), sparse.get_root());
+
+        for i in 0..16 {
+            let leave = (i * 7).to_string();
+            let index = if i == 0 || i == 5 || i == 15 {
+                incremental.append_tracked(&leave).unwrap()
+            } else {
+                incremental.append(&leave).unwrap()
+            };
+            assert_eq!(index, i);
+            sparse.update(i, leave).unwrap();
+            assert_eq!(incremental.root(), sparse.get_root());
+
+            for tracked in incremental.tracked() {
+                let proof = incremental.generate_proof(tracked).unwrap();
+                let sparse_proof = sparse.generate_proof(tracked).unwrap();
+                assert_eq!(proof.path, sparse_proof.path);
+                assert_eq!(proof.lemma, sparse_proof.lemma);
+                assert!(proof.verify().unwrap());
+            }
+        }
+        assert_eq!(incremental.tracked().collect::<Vec<_>>(), vec![0, 5, 15]);
+        assert_eq!(incremental.appended_count(), 16);
+
+        assert_eq!(incremental.append("1"), Err(CryptoError::IndexOutOfRange { index: 16, len: 16 }));
+        assert!(incremental.untrack(5));
+        assert!(!incremental.untrack(5));
+        assert!(incremental.generate_proof(5).is_err());
+        assert!(incremental.generate_proof(3).is_err());
+    }
+
+    ///TEST: deep incremental Merkle tree
+    #[test]
+    fn incremental_merkle_tree_deep(){
+        let mut incremental = IncrementalMerkleTree::<PoseidonHasher>::new(32, "0").unwrap();
+        let mut sparse = SparseMerkleTree::<PoseidonHasher>::new(32, "0").unwrap();
+
+        incremental.append_tracked("1").unwrap();
+        for i in 1..5 {
+            incremental.append(&i.to_string()).unwrap();
+        }
+        sparse.update_batch(0, &vec!["1".to_owned(), "1".to_owned(), "2".to_owned(), "3".to_owned(), "4".to_owned()]).unwrap();
+        assert_eq!(incremental.root(), sparse.get_root());
+
+        let proof = incremental.generate_proof(0).unwrap();
+        assert_eq!(proof.lemma.len(), 34);
+        assert!(proof.verify().unwrap());
+        assert_eq!(incremental.frontier.len(), 32);
+    }
+
+    ///TEST: Merkle multiproofs
+    #[test]
+    fn merkle_multi_proof(){
+        let tree = HashTree::<PoseidonHasher,2>::new(
+            &(0..16).map(|x| x.to_string()).collect()
+        ).unwrap();
+
+        let proof = tree.generate_multi_proof(&[9, 2, 3, 1, 2]).unwrap();
+        assert_eq!(proof.indices, vec![1, 2, 3, 9]);
+        assert!(proof.verify().unwrap());
+        //0 and 8 on level 0, 5 on level 1, 1 and 3 on level 2
+        assert_eq!(proof.siblings.len(), 5);
+        for (index, leave) in proof.indices.iter().zip(proof.leaves.iter()) {
+            assert_eq!(&tree.generate_proof(*index).unwrap().lemma[0], leave);
+        }
+
+        let json = serde_json::to_string(&proof).unwrap();
+        let proof: MerkleMultiProof<PoseidonHasher,2> = serde_json::from_str(&json).unwrap();
+        assert!(proof.verify().unwrap());
+
+        let all = tree.generate_multi_proof(&(0..16).collect::<Vec<_>>()).unwrap();
+        assert!(all.siblings.is_empty());
+        assert!(all.verify().unwrap());
+
+        let mut sparse = SparseMerkleTree::<PoseidonHasher>::new(4, "0").unwrap();
+        sparse.update_batch(0, &(0..16).map(|x| x.to_string()).collect()).unwrap();
+        let sparse_proof = sparse.generate_multi_proof(&[1, 2, 3, 9]).unwrap();
+        assert_eq!(sparse_proof.siblings, proof.siblings);
+        assert_eq!(sparse_proof.root, proof.root);
+
+        let mut tampered = proof.clone();
+        tampered.leaves.swap(0, 1);
+        assert!(!tampered.verify().unwrap());
+        let mut tampered = proof.clone();
+        tampered.siblings.pop();
+        assert!(tampered.verify().is_err());
+        let mut tampered = proof.clone();
+        tampered.siblings.push(proof.root.clone());
+        assert!(tampered.verify().is_err());
+        let mut tampered = proof.clone();
+        tampered.indices = vec![1, 2, 3, 16];
+        assert!(tampered.verify().is_err());
+
+        assert!(tree.generate_multi_proof(&[]).is_err());
+        assert!(tree.generate_multi_proof(&[3, 16]).is_err());
+    }
+
+    ///TEST: k-ary Merkle proofs
+    #[test]
+    fn merkle_proof_branching(){
+        let tree = HashTree::<PoseidonHasher,6>::new(
+            &(0..36).map(|x| x.to_string()).collect()
+        ).unwrap();
+        for index in [0, 5, 6, 23, 35] {
+            let proof = tree.generate_proof(index).unwrap();
+            assert_eq!(proof.path.len(), 2);
+            assert_eq!(proof.lemma.len(), 12);
+            assert_eq!(proof.index(), index);
+            assert_eq!(proof.root(), Some(tree.get_root()));
+            assert!(proof.verify().unwrap());
+        }
+
+        let tree = HashTree::<PoseidonHasher,3>::new(
+            &(0..27).map(|x| x.to_string()).collect()
+        ).unwrap();
+        let mut proof = tree.generate_proof(14).unwrap();
+        assert_eq!(proof.path, vec![2, 1, 1]);
+        assert!(proof.verify().unwrap());
+
+        proof.lemma.swap(1, 2);
+        assert!(!proof.verify().unwrap());
+        proof.path[0] = 3;
+        assert!(proof.verify().is_err());
+        proof.lemma.pop();
+        assert!(proof.verify().is_err());
+        assert!(tree.generate_proof(27).is_err());
+    }
+
+    ///Order sensitive hash function accepting any number of inputs,
+    ///  for checking the tree arithmetic beyond the arity of Poseidon.
+    struct TestHasher;
+
+    impl HashFunction for TestHasher {
+        fn new() -> Self {
+            TestHasher
+        }
+
+        fn hash(&self, inputs: &Vec<String>) -> Result<BigInt, CryptoError> {
+            let inputs: Vec<BigInt> = inputs.iter()
+                .map(|x| x.bytes().fold(BigInt::from(7), |acc, b| (acc * 257 + b) % 1_000_000_007u64))
+                .collect();
+            self.hash_big_int(&inputs)
+        }
+
+        fn hash_big_int(&self, inputs: &[BigInt]) -> Result<BigInt, CryptoError> {
+            Ok(inputs.iter().fold(BigInt::from(inputs.len()), |acc, x| (acc * 1_000_003 + x) % 1_000_000_007u64))
+        }
+    }
+
+    ///Checks that single updates, batch updates and rebuilding result in the same tree.
+    fn check_updates<const B: usize>(depth: u32, start: usize, values: &[String]) -> Result<(), TestCaseError> {
+        let len = B.pow(depth);
+        let mut leaves: Vec<String> = (0..len).map(|x| x.to_string()).collect();
+        let start = start % len;
+        let values = &values[..values.len().min(len - start)];
+
+        let mut single = HashTree::<TestHasher,B>::new(&leaves).unwrap();
+        let mut batch = single.clone();
+        for (k, value) in values.iter().enumerate() {
+            single.update(start + k, value.clone()).unwrap();
+        }
+        batch.update_batch(start, &values.to_vec()).unwrap();
+        leaves.splice(start..start + values.len(), values.iter().cloned());
+        let built = HashTree::<TestHasher,B>::new(&leaves).unwrap();
+        let mut rebuilt = single.clone();
+        rebuilt.generate_tree().unwrap();
+
+        prop_assert_eq!(single.get_root(), built.get_root());
+        prop_assert_eq!(&batch.data, &built.data);
+        prop_assert_eq!(&rebuilt.data, &built.data);
+        prop_assert!(built.generate_proof(start).unwrap().verify().unwrap());
+        Ok(())
+    }
+
+    macro_rules! dispatch {
+        ($b:expr, $($args:expr),*) => {
+            match $b {
+                2 => check_updates::<2>($($args),*), 3 => check_updates::<3>($($args),*),
+                4 => check_updates::<4>($($args),*), 5 => check_updates::<5>($($args),*),
+                6 => check_updates::<6>($($args),*), 7 => check_updates::<7>($($args),*),
+                8 => check_updates::<8>($($args),*), 9 => check_updates::<9>($($args),*),
+                10 => check_updates::<10>($($args),*), 11 => check_updates::<11>($($args),*),
+                12 => check_updates::<12>($($args),*), 13 => check_updates::<13>($($args),*),
+                14 => check_updates::<14>($($args),*), 15 => check_updates::<15>($($args),*),
+                16 => check_updates::<16>($($args),*),
+                _ => unreachable!(),
+            }
+        };
+    }
+
+    ///Branching factors and depths of trees with at most 2^12 leaves.
+    fn tree_shape() -> impl Strategy<Value = (usize, u32)> {
+        (2usize..=16).prop_flat_map(|b| {
+            let max_depth = (1..).take_while(|d| b.pow(*d) <= 1 << 12).last().unwrap_or(0);
+            (Just(b), 0..=max_depth)
+        })
+    }
+
+    proptest! {
+        ///TEST: updates match rebuilding for branching factors 2 to 16
+        #[test]
+        fn hash_tree_updates((b, depth) in tree_shape(), start in any::<usize>(),
+            values in proptest::collection::vec("[a-z0-9]{0,4}", 1..40)) {
+            dispatch!(b, depth, start, &values)?;
+        }
+
+        ///TEST: sizes are only accepted as exact powers of the branching factor
+        #[test]
+        fn hash_tree_sizes(len in 0usize..300, b in 2usize..=16) {
+            let mut values: Vec<String> = (0..len).map(|x| x.to_string()).collect();
+            let power = (0..).map(|d| b.pow(d)).find(|p| *p >= len).unwrap();
+            fill_vec(&mut values, b).unwrap();
+            prop_assert_eq!(values.len(), power);
+
+            let input: Vec<String> = vec!["".to_owned(); len];
+            let tree = HashTree::<TestHasher,3>::new(&input);
+            let is_power = (0..).map(|d| 3usize.pow(d)).take_while(|p| *p <= len).any(|p| p == len);
+            prop_assert_eq!(tree.is_ok(), is_power);
+            if !is_power {
+                prop_assert_eq!(tree.err(), Some(CryptoError::InvalidTreeSize(len)));
+            }
+        }
+    }
+
+    proptest! {
+        #![proptest_config(ProptestConfig::with_cases(6))]
+
+        ///TEST: updates match rebuilding for binary trees of depths 13 to 19, between 'tree_shape' and 'hash_tree_depth_20'
+        #[test]
+        fn hash_tree_updates_deep(depth in 13u32..=19, start in any::<usize>(),
+            values in proptest::collection::vec("[a-z0-9]{0,4}", 1..40)) {
+            check_updates::<2>(depth, start, &values)?;
+        }
+    }
+
+    ///TEST: parallel hashing matches hashing in sequence
+    #[cfg(feature = "parallel")]
+    #[test]
+    fn hash_parallel(){
+        let leaves: Vec<String> = (0..1000).map(|x| x.to_string()).collect();
+        let hasher = TestHasher::new();
+        let hashed: Vec<BigInt> = leaves.iter().fold(Vec::new(), |mut acc, x| {
+            acc.push(hasher.hash_str(x).unwrap());
+            acc
+        });
+        assert_eq!(super::hash_leaves::<TestHasher>(&leaves).unwrap(), hashed);
+
+        for size in [2, 3, 7, 16] {
+            let chunks: Vec<BigInt> = hashed.chunks(size).fold(Vec::new(), |mut acc, x| {
+                acc.push(hasher.hash_big_int(x).unwrap());
+                acc
+            });
+            assert_eq!(super::hash_chunks::<TestHasher>(&hashed, size).unwrap(), chunks);
+        }
+    }
+
+    ///TEST: tree of depth 20
+    #[test]
+    fn hash_tree_depth_20(){
+        check_updates::<2>(20, 700_000, &vec!["a".to_owned(); 1000]).unwrap();
+    }
+
+    ///TEST: invalid branching factors and tampered trees
+    #[test]
+    fn hash_tree_arithmetic_errors(){
+        assert!(HashTree::<TestHasher,1>::new(&vec!["a".to_owned()]).is_err());
+        assert!(HashTree::<TestHasher,0>::new(&vec!["a".to_owned()]).is_err());
+        assert!(fill_vec(&mut vec!["a".to_owned()], 1).is_err());
+        let mut values = vec!["".to_owned()];
+        fill_vec(&mut values, 2).unwrap();
+        assert_eq!(values.len(), 1);
+
+        let mut tree = HashTree::<TestHasher,4>::new(&vec!["a".to_owned(); 16]).unwrap();
+        tree.leaves.push("b".to_owned());
+        assert_eq!(tree.update(0, "c".to_owned()), Err(CryptoError::InvalidTreeSize(17)));
+        assert!(tree.generate_proof(0).is_err());
+        assert_eq!(tree.generate_tree(), Err(CryptoError::InvalidTreeSize(17)));
+
+        let mut tree = HashTree::<TestHasher,4>::new(&vec!["a".to_owned(); 16]).unwrap();
+        assert_eq!(tree.update_batch(usize::MAX, &vec!["a".to_owned(); 2]),
+            Err(CryptoError::IndexOutOfRange { index: usize::MAX, len: 16 }));
+    }
+
+    ///TEST: construction matches leave by leave updates, also when hashing in parallel
+    #[test]
+    fn hash_tree_construction(){
+        let leaves: Vec<String> = (0..216).map(|x| x.to_string()).collect();
+        let built = HashTree::<PoseidonHasher,6>::new(&leaves).unwrap();
+        let mut updated = HashTree::<PoseidonHasher,6>::new(&vec!["".to_owned(); 216]).unwrap();
+        for (index, leave) in leaves.iter().enumerate() {
+            updated.update(index, leave.clone()).unwrap();
+        }
+        assert_eq!(built.data, updated.data);
+
+        let mut batch = SparseMerkleTree::<PoseidonHasher>::new(8, "0").unwrap();
+        let mut single = batch.clone();
+        batch.update_batch(0, &leaves).unwrap();
+        for (index, leave) in leaves.iter().enumerate() {
+            single.update(index, leave.clone()).unwrap();
+        }
+        assert_eq!(batch.get_root(), single.get_root());
+        assert_eq!(batch.nodes, single.nodes);
+    }
+}
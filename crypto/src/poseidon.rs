@@ -13,7 +13,7 @@ use poseidon_rs::{Fr, Poseidon};
 use babyjubjub_rs::{PrivateKey, Signature as babySignature, Point, Fr as babyjubjubFr, new_key};
 use serde::{Serialize, Deserialize};
 
-use crate::{HashFunction, Signature, SignatureFunction};
+use crate::{HashFunction, Signature, SignatureFunction, error::CryptoError};
 
 
 /// A struct representing the instance of a poseidon hash function.
@@ -34,6 +34,34 @@ impl PoseidonHasher {
     ///Constant for the maximum input length
     /// of the poseidon hash function.
     const POSEIDON_MAX_LENGTH: usize = 6;   
+
+    ///Hashes a string, which is not a number, in chunks of POSEIDON_MAX_LENGTH bytes.
+    fn hash_text(&self, x: &str) -> Result<BigInt, CryptoError> {
+        if x == ""{
+            return Ok(BigInt::from(0));
+        }
+
+        let v: Vec<u16> = x.encode_utf16().collect();
+        let mut input_array = Vec::<u8>::new();
+        for i in 0..v.len(){
+            input_array.push((v[i] & 255) as u8);
+            input_array.push((v[i] >> 8) as u8);
+        }
+        let mut first = BigInt::from(input_array[0]);
+        let upper_bound = input_array.len().div_ceil(PoseidonHasher::POSEIDON_MAX_LENGTH);
+        for i in 1 ..upper_bound+1 {   
+            let mut input = vec![first];
+            for j in 0 .. PoseidonHasher::POSEIDON_MAX_LENGTH -1 {
+                let index = i*PoseidonHasher::POSEIDON_MAX_LENGTH + j;
+                if input_array.len() > index {
+                    input.push(BigInt::from(input_array[index]));
+                }
+            }
+            first = self.hash_big_int(input.as_slice())?;
+        }
+
+        Ok(first)
+    }
 }
 
 ///Implementing the HashFunction trait for the PoseidonHasher struct.
@@ -46,111 +74,118 @@ impl HashFunction for PoseidonHasher{
 
     ///Hashes an array of numbers.
     /// Array length is limited by POSEIDON_MAX_LENGTH.
-    fn hash_big_int(&self, inputs: &[BigInt]) -> BigInt {
-        let inputs_converted: Vec<Fr> = inputs.into_iter().map(|x| Fr::from_str(&x.to_string()).unwrap()).collect();
+    /// Fails, should a number be outside of the finite field.
+    fn hash_big_int(&self, inputs: &[BigInt]) -> Result<BigInt, CryptoError> {
+        let mut inputs_converted = Vec::<Fr>::with_capacity(inputs.len());
+        for x in inputs {
+            match Fr::from_str(&x.to_string()) {
+                Some(y) => inputs_converted.push(y),
+                None => Err(CryptoError::FieldOverflow(x.to_string()))?,
+            }
+        }
         
-        let repr = self.0.hash(inputs_converted).unwrap().into_repr();
+        let repr = match self.0.hash(inputs_converted) {
+            Ok(res) => res.into_repr(),
+            Err(err) => Err(CryptoError::InvalidInput(err))?,
+        };
         if repr.is_zero(){
-            return BigInt::from(0);
+            return Ok(BigInt::from(0));
         }
 
         let mut buf = Vec::<u8>::new();
-        repr.write_le(&mut buf).unwrap();
-        BigInt::from_bytes_le(num_bigint::Sign::Plus, &buf)
+        repr.write_le(&mut buf).map_err(|err| CryptoError::InvalidInput(err.to_string()))?;
+        Ok(BigInt::from_bytes_le(num_bigint::Sign::Plus, &buf))
     }
 
     ///Hashes an vector of strings.
     /// Vector length is limited by POSEIDON_MAX_LENGTH.
-    fn hash(&self,inputs: &Vec<String>) -> BigInt {
-        let inputs_converted: Vec<BigInt> = inputs.into_iter().map(
-            |x| {
-                let res = match BigInt::from_str(&x){
-                    Ok(y) => y,
-                    Err(_) => {
-                        if x == ""{
-                            return BigInt::from(0);
-                        }
-
-                        let v: Vec<u16> = x.encode_utf16().collect();
-                        let mut input_array = Vec::<u8>::new();
-                        for i in 0..v.len(){
-                            input_array.push((v[i] & 255) as u8);
-                            input_array.push((v[i] >> 8) as u8);
-                        }
-                        let mut first = BigInt::from(input_array[0]);
-                        let upper_bound = (input_array.len() as f32/PoseidonHasher::POSEIDON_MAX_LENGTH as f32).ceil() as usize;
-                        for i in 1 ..upper_bound+1 {   
-                            let mut input = vec![first];
-                            for j in 0 .. PoseidonHasher::POSEIDON_MAX_LENGTH -1 {
-                                let index = i*PoseidonHasher::POSEIDON_MAX_LENGTH + j;
-                                if input_array.len() > index {
-                                    input.push(BigInt::from(input_array[index]));
-                                }
-                            }
-                            first = self.hash_big_int(input.as_slice());
-                        }
-
-                        first
-                    }
-                };
-
-                res
-            }
-        ).collect();
+    fn hash(&self,inputs: &Vec<String>) -> Result<BigInt, CryptoError> {
+        let mut inputs_converted = Vec::<BigInt>::with_capacity(inputs.len());
+        for x in inputs {
+            let res = match BigInt::from_str(&x){
+                Ok(y) => y,
+                Err(_) => self.hash_text(x)?,
+            };
+            inputs_converted.push(res);
+        }
 
         self.hash_big_int(inputs_converted.as_slice())
     }
 }
 
+///Converts a field element of Baby Jubjub to a number.
+fn to_big_int(element: &babyjubjubFr) -> Result<BigInt, CryptoError> {
+    BigInt::from_str_radix(&to_hex(element),16).map_err(|err| CryptoError::InvalidInput(err.to_string()))
+}
+
+///Converts a number to a field element of Baby Jubjub.
+fn to_field_element(value: &BigInt) -> Option<babyjubjubFr> {
+    babyjubjubFr::from_str(&value.to_string())
+}
+
+///Imports the private key from its 32 byte big endian representation.
+fn import_private_key(secret_key: &BigInt) -> Result<PrivateKey, CryptoError> {
+    let mut private_key_raw = secret_key.to_bytes_le().1;
+    if private_key_raw.len() > 32 {
+        Err(CryptoError::InvalidKey("imported key can not be bigger than 32 bytes".to_owned()))?;
+    }
+    for _i in private_key_raw.len() .. 32 {
+        private_key_raw.push(0);
+    }
+    private_key_raw.reverse();
+
+    PrivateKey::import(private_key_raw).map_err(CryptoError::InvalidKey)
+}
+
 
 ///Implementing the SignatureFunction trait for PoseidonSignature.
 /// Utilizes the babyjubjubrs crate.
 impl SignatureFunction for PoseidonSignature {
 
     //Signs a message with the provided private key.
-    fn sign(private_key: BigInt, message: BigInt) -> Signature<PoseidonSignature> {
-
-        let mut private_key_raw = private_key.to_bytes_le().1;
-        assert!(private_key_raw.len() <= 32);
-        for _i in private_key_raw.len() .. 32 {
-            private_key_raw.push(0);
-        }
-        private_key_raw.reverse();
+    fn sign(private_key: BigInt, message: BigInt) -> Result<Signature<PoseidonSignature>, CryptoError> {
 
-        assert!(private_key_raw.len() == 32);
+        let private_key = import_private_key(&private_key)?;
 
-
-        let private_key = PrivateKey::import(private_key_raw).expect("imported key can not be bigger than 32 bytes");
-
-        let sig: babySignature = private_key.sign(message).expect("Message outside of finite field");
+        if to_field_element(&message).is_none() {
+            Err(CryptoError::FieldOverflow(message.to_string()))?;
+        }
+        let sig: babySignature = match private_key.sign(message.clone()) {
+            Ok(sig) => sig,
+            Err(_) => Err(CryptoError::FieldOverflow(message.to_string()))?,
+        };
 
         let public_key = private_key.public();
         
         
-        Signature { r8: [
-                    BigInt::from_str_radix(&to_hex(&sig.r_b8.x),16).unwrap(),
-                    BigInt::from_str_radix(&to_hex(&sig.r_b8.y),16).unwrap()
+        Ok(Signature { r8: [
+                    to_big_int(&sig.r_b8.x)?,
+                    to_big_int(&sig.r_b8.y)?
                     ],
                      s: sig.s,
                     public_key_signer: [
-                        BigInt::from_str_radix(&to_hex(&public_key.x),16).unwrap(),
-                        BigInt::from_str_radix(&to_hex(&public_key.y),16).unwrap()
+                        to_big_int(&public_key.x)?,
+                        to_big_int(&public_key.y)?
                     ],
-                _signature_function: std::marker::PhantomData::<fn()->PoseidonSignature>}
+                _signature_function: std::marker::PhantomData::<fn()->PoseidonSignature>})
     }
 
     ///Verifies the signature.
+    /// Signatures with coordinates outside of the finite field are invalid.
     fn verify(sig: &Signature<Self>, msg: &BigInt) -> bool {
         
-        let public_key = Point{ x: babyjubjubFr::from_str(&sig.public_key_signer[0].to_string()).unwrap(), 
-                                   y: babyjubjubFr::from_str(&sig.public_key_signer[1].to_string()).unwrap(), };
+        let (public_key, r_b8) = match (
+            to_field_element(&sig.public_key_signer[0]), to_field_element(&sig.public_key_signer[1]),
+            to_field_element(&sig.r8[0]), to_field_element(&sig.r8[1])
+        ) {
+            (Some(pk_x), Some(pk_y), Some(r_x), Some(r_y)) =>
+                (Point{ x: pk_x, y: pk_y }, Point{ x: r_x, y: r_y }),
+            _ => return false,
+        };
         
         let baby_jubjub_signature = babySignature { 
-            r_b8: Point{
-                 x: babyjubjubFr::from_str(&sig.r8[0].to_string()).unwrap(), 
-                 y: babyjubjubFr::from_str(&sig.r8[1].to_string()).unwrap() 
-                },
-                 s: sig.s.clone() };
+            r_b8,
+            s: sig.s.clone() };
 
         babyjubjub_rs::verify(public_key, baby_jubjub_signature, msg.clone())
     }
@@ -164,23 +199,14 @@ impl PoseidonSignature {
         private_key.scalar_key()
     }
 
-    pub fn get_public_keys(secret_key: BigInt) -> [BigInt;2]{      
+    pub fn get_public_keys(secret_key: BigInt) -> Result<[BigInt;2], CryptoError>{      
 
-        let mut private_key_raw = secret_key.to_bytes_le().1;
-        assert!(private_key_raw.len() <= 32);
-        for _i in private_key_raw.len() .. 32 {
-            private_key_raw.push(0);
-        }
-        private_key_raw.reverse();
-
-        assert!(private_key_raw.len() == 32);
-
-        let private_key = PrivateKey::import(private_key_raw).expect("imported key can not be bigger than 32 bytes");
+        let private_key = import_private_key(&secret_key)?;
         let public_key = private_key.public();
-        [
-                        BigInt::from_str_radix(&to_hex(&public_key.x),16).unwrap(),
-                        BigInt::from_str_radix(&to_hex(&public_key.y),16).unwrap()
-                    ]
+        Ok([
+            to_big_int(&public_key.x)?,
+            to_big_int(&public_key.y)?
+        ])
     }
 }
 
@@ -198,7 +224,7 @@ mod test{
             BigInt::from_str("2010143491207902444122668013146870263468969134090678646686512037244361350365").unwrap()
         ];
         let hasher = PoseidonHasher::new();
-        let result = hasher.hash_big_int(&input);
+        let result = hasher.hash_big_int(&input).unwrap();
 
         assert_eq!(result,BigInt::from_str("12353927035604053351139001901051657562744637204994815165268679511342104426088").unwrap());
 
@@ -240,9 +266,23 @@ mod test{
 
         let msg = BigInt::from(42);
 
-        let sig: Signature<PoseidonSignature> = PoseidonSignature::sign(private_key.clone(), msg.clone());
+        let sig: Signature<PoseidonSignature> = PoseidonSignature::sign(private_key.clone(), msg.clone()).unwrap();
 
         assert!(PoseidonSignature::verify(&sig, &msg));
     }
 
+    ///TEST: inputs outside of the finite field
+    #[test]
+    fn test_poseidon_field_overflow(){
+        let hasher = PoseidonHasher::new();
+        let modulus = BigInt::from_str("21888242871839275222246405745257275088548364400416034343698204186575808495617").unwrap();
+
+        assert!(matches!(hasher.hash_big_int(&[modulus.clone()]), Err(CryptoError::FieldOverflow(_))));
+        assert!(matches!(hasher.hash(&vec![modulus.to_string()]), Err(CryptoError::FieldOverflow(_))));
+
+        let private_key = BigInt::from(42);
+        assert!(matches!(PoseidonSignature::sign(private_key, modulus), Err(CryptoError::FieldOverflow(_))));
+        assert!(matches!(PoseidonSignature::get_public_keys(BigInt::from(2).pow(256u32)), Err(CryptoError::InvalidKey(_))));
+    }
+
 }
\ No newline at end of file
@@ -1,6 +1,6 @@
 use heimdall::{presentation::*, credential::Credential, revocation::RevocationRegistry,
-     zkp::{attribute::proof_attribute_presentation, delegation::proof_delegation_presentation,
-    polygon::proof_polygon_presentation, range::proof_range_presentation}
+     resource::{FileSystemLoader, VerifiedLoader},
+     zkp::{attribute, delegation, pinned_loader, polygon, range}
     };
 
 use crypto::{poseidon::*};
@@ -12,8 +12,27 @@ use criterion::{
 use num_bigint::BigInt;
 use std::{str::FromStr, time::{Duration, Instant}};
 
-//benchmarks the attribute presentation generation 
-fn attribute_presentation(c: &mut Criterion){
+//loads the ressources of the circuits, pinning the digests of the proving keys
+// listed in the file 'HEIMDALL_RESOURCE_SUMS', e.g. generated by 'sha256sum *.wasm *.zkey'
+fn loader() -> VerifiedLoader<FileSystemLoader> {
+    //on android the ressources are located in the working directory
+    let loader = if cfg!(target_os = "android") {
+        FileSystemLoader::new(".")
+    } else {
+        FileSystemLoader::default()
+    };
+    let sums = std::env::var("HEIMDALL_RESOURCE_SUMS")
+        .map(|path| std::fs::read_to_string(path).unwrap())
+        .unwrap_or_default();
+    pinned_loader(loader, &sums).unwrap()
+}
+
+//credential, revocation registry and holder key shared by the attribute presentation benchmarks
+fn attribute_fixture() -> (
+    Credential<PoseidonHasher, PoseidonSignature>,
+    RevocationRegistry<PoseidonHasher, PoseidonSignature>,
+    BigInt,
+) {
     let issuer_sk =
         BigInt::from_str("2951225162891973271265230278305932248884420185229553697262927872")
             .unwrap();
@@ -21,65 +40,85 @@ fn attribute_presentation(c: &mut Criterion){
         BigInt::from_str("5728529159811704811523142037611732735149121473808289891486793728")
             .unwrap();
 
-    let credential = black_box(
-        Credential::<PoseidonHasher, PoseidonSignature>::new(
-            &vec![
-                "John".to_owned(),
-                "Jones".to_owned(),
-                "male".to_owned(),
-                "843995700".to_owned(),
-                "blue".to_owned(),
-                "180".to_owned(),
-                "115703781".to_owned(),
-                "499422598".to_owned(),
-            ],
-            255 as u64,
-            &[
-                BigInt::from_str(
-                    "11568348142699582059879762896692005650111252224863899748681544124434641871979",
-                )
-                .unwrap(),
-                BigInt::from_str(
-                    "3313301605305461355814038303705256811688733498785606352476634260778286273969",
-                )
-                .unwrap(),
-            ],
-            365,
-            "Identity",
-            false,
-            "revocRegistry",
-            issuer_sk.clone(),
-        )
-    );
+    let credential = Credential::<PoseidonHasher, PoseidonSignature>::new(
+        &vec![
+            "John".to_owned(),
+            "Jones".to_owned(),
+            "male".to_owned(),
+            "843995700".to_owned(),
+            "blue".to_owned(),
+            "180".to_owned(),
+            "115703781".to_owned(),
+            "499422598".to_owned(),
+        ],
+        255 as u64,
+        &[
+            BigInt::from_str(
+                "11568348142699582059879762896692005650111252224863899748681544124434641871979",
+            )
+            .unwrap(),
+            BigInt::from_str(
+                "3313301605305461355814038303705256811688733498785606352476634260778286273969",
+            )
+            .unwrap(),
+        ],
+        365,
+        "Identity",
+        false,
+        "revocRegistry",
+        issuer_sk.clone(),
+    )
+    .unwrap();
 
-    let mut circuit = None;
-    let mut zkey = None;
-    if cfg!(target_os = "android") {
-        circuit = Some(
-            std::fs::read("presentation_attribute.dat").unwrap()
-        );
-        zkey = Some(
-            std::fs::read("presentation_attribute.zkey").unwrap()
-        );
-
-    }
-    
     let revoc_reg =
-    RevocationRegistry::<PoseidonHasher, PoseidonSignature>::new(Some(issuer_sk.clone()));
+    RevocationRegistry::<PoseidonHasher, PoseidonSignature>::new(Some(issuer_sk)).unwrap();
+
+    (credential, revoc_reg, holder_sk)
+}
+
+//creates the attribute presentation measured by the attribute presentation benchmarks
+fn attribute_fixture_presentation(
+    credential: &Credential<PoseidonHasher, PoseidonSignature>,
+    revoc_reg: &RevocationRegistry<PoseidonHasher, PoseidonSignature>,
+    holder_sk: &BigInt,
+) -> AttributePresentation<PoseidonHasher, PoseidonSignature> {
+    AttributePresentation::<PoseidonHasher, PoseidonSignature>::new(
+        credential,
+        BigInt::from_str("1678460108000").unwrap(),
+        revoc_reg,
+        Some(holder_sk.clone()),
+        None,
+        BigInt::from(1234),
+        vec![0, 1, 2, 3, 4, 5, 6, 7],
+    )
+    .unwrap()
+}
+
+//benchmarks the attribute presentation generation 
+fn attribute_presentation(c: &mut Criterion){
+    let (credential, revoc_reg, holder_sk) = black_box(attribute_fixture());
+
+    let loader = loader();
 
     c.bench_function("attribute presentation generation", 
         |b| b.iter(||{
-            let mut attr_pres = AttributePresentation::<PoseidonHasher, PoseidonSignature>::new(
-                &credential,
-                BigInt::from_str("1678460108000").unwrap(),
-                &revoc_reg,
-                Some(holder_sk.clone()),
-                None,
-                BigInt::from(1234),
-                vec![0, 1, 2, 3, 4, 5, 6, 7],
-            )
-            .unwrap();
-            attr_pres.generate(circuit.as_ref(), zkey.as_ref()).unwrap();
+            let mut attr_pres = attribute_fixture_presentation(&credential, &revoc_reg, &holder_sk);
+            attr_pres.generate(&loader).unwrap();
+        })
+    );
+}
+
+//benchmarks the attribute presentation generation reusing the loaded circuit 
+fn attribute_presentation_cached(c: &mut Criterion){
+    let (credential, revoc_reg, holder_sk) = black_box(attribute_fixture());
+
+    let loader = loader();
+    let prover = attribute::CIRCUIT.prover(&loader).unwrap();
+
+    c.bench_function("attribute presentation generation cached", 
+        |b| b.iter(||{
+            let mut attr_pres = attribute_fixture_presentation(&credential, &revoc_reg, &holder_sk);
+            attr_pres.generate_with_prover(&prover).unwrap();
         })
     );
 }
@@ -123,24 +162,15 @@ fn attribute_presentation_fragmented(c: &mut Criterion){
             "revocRegistry",
             issuer_sk.clone(),
         )
+        .unwrap()
     );
     let mut duration = start.elapsed().as_millis();
     println!("//Credential creation: {}ms", duration);
 
-    let mut circuit = None;
-    let mut zkey = None;
-    if cfg!(target_os = "android") {
-        circuit = Some(
-            std::fs::read("presentation_attribute.dat").unwrap()
-        );
-        zkey = Some(
-            std::fs::read("presentation_attribute.zkey").unwrap()
-        );
-
-    }
+    let loader = loader();
     
     let revoc_reg =
-    RevocationRegistry::<PoseidonHasher, PoseidonSignature>::new(Some(issuer_sk.clone()));
+    RevocationRegistry::<PoseidonHasher, PoseidonSignature>::new(Some(issuer_sk.clone())).unwrap();
 
     start = Instant::now();
     let mut attr_pres = AttributePresentation::<PoseidonHasher, PoseidonSignature>::new(
@@ -172,7 +202,7 @@ fn attribute_presentation_fragmented(c: &mut Criterion){
 
     start = Instant::now();
     attr_pres.presentation.ark_circom_full_proof =
-        match proof_attribute_presentation(&input_json, circuit.as_ref(), zkey.as_ref()) {
+        match attribute::CIRCUIT.prove(&input_json, &loader) {
             Ok(proof) => Some(proof),
             Err(err) => panic!("Proving process failed"),
         };
@@ -224,22 +254,13 @@ fn range_presentation(c: &mut Criterion){
             "revocRegistry",
             issuer_sk.clone(),
         )
+        .unwrap()
     );
     
     let revoc_reg =
-    RevocationRegistry::<PoseidonHasher, PoseidonSignature>::new(Some(issuer_sk.clone()));
-
-    let mut circuit = None;
-    let mut zkey = None;
-    if cfg!(target_os = "android") {
-        circuit = Some(
-            std::fs::read("presentation_range.dat").unwrap()
-        );
-        zkey = Some(
-            std::fs::read("presentation_range.zkey").unwrap()
-        );
+    RevocationRegistry::<PoseidonHasher, PoseidonSignature>::new(Some(issuer_sk.clone())).unwrap();
 
-    }
+    let loader = loader();
 
     c.bench_function("range presentation generation", 
         |b| b.iter(||{
@@ -256,7 +277,7 @@ fn range_presentation(c: &mut Criterion){
             )
             .unwrap();
     
-            range_pres.generate(circuit.as_ref(), zkey.as_ref()).unwrap();
+            range_pres.generate(&loader).unwrap();
         })
     );
 }
@@ -300,24 +321,15 @@ fn range_presentation_fragmented(c: &mut Criterion){
             "revocRegistry",
             issuer_sk.clone(),
         )
+        .unwrap()
     );
     let mut duration = start.elapsed().as_millis();
     println!("//Credential creation: {}ms", duration);
 
-    let mut circuit = None;
-    let mut zkey = None;
-    if cfg!(target_os = "android") {
-        circuit = Some(
-            std::fs::read("presentation_range.dat").unwrap()
-        );
-        zkey = Some(
-            std::fs::read("presentation_range.zkey").unwrap()
-        );
-
-    }
+    let loader = loader();
     
     let revoc_reg =
-    RevocationRegistry::<PoseidonHasher, PoseidonSignature>::new(Some(issuer_sk.clone()));
+    RevocationRegistry::<PoseidonHasher, PoseidonSignature>::new(Some(issuer_sk.clone())).unwrap();
 
     start = Instant::now();
     let mut range_pres = RangePresentation::<PoseidonHasher, PoseidonSignature>::new(
@@ -351,7 +363,7 @@ fn range_presentation_fragmented(c: &mut Criterion){
 
     start = Instant::now();
     range_pres.presentation.ark_circom_full_proof =
-        match proof_range_presentation(&input_json, circuit.as_ref(), zkey.as_ref()) {
+        match range::CIRCUIT.prove(&input_json, &loader) {
             Ok(proof) => Some(proof),
             Err(err) => panic!("Proving process failed"),
         };
@@ -403,23 +415,14 @@ fn polygon_presentation(c: &mut Criterion){
             "revocRegistry",
             issuer_sk.clone(),
         )
+        .unwrap()
     );
     
     let revoc_reg =
-    RevocationRegistry::<PoseidonHasher, PoseidonSignature>::new(Some(issuer_sk.clone()));
+    RevocationRegistry::<PoseidonHasher, PoseidonSignature>::new(Some(issuer_sk.clone())).unwrap();
 
 
-    let mut circuit = None;
-    let mut zkey = None;
-    if cfg!(target_os = "android") {
-        circuit = Some(
-            std::fs::read("presentation_polygon.dat").unwrap()
-        );
-        zkey = Some(
-            std::fs::read("presentation_polygon.zkey").unwrap()
-        );
-
-    }
+    let loader = loader();
 
     c.bench_function("polygon presentation generation", 
         |b| b.iter(||{
@@ -446,7 +449,7 @@ fn polygon_presentation(c: &mut Criterion){
             )
             .unwrap();
     
-            polygon_pres.generate(circuit.as_ref(), zkey.as_ref()).unwrap();
+            polygon_pres.generate(&loader).unwrap();
         })
     );
 }
@@ -490,24 +493,15 @@ fn polygon_presentation_fragmented(c: &mut Criterion){
             "revocRegistry",
             issuer_sk.clone(),
         )
+        .unwrap()
     );
     let mut duration = start.elapsed().as_millis();
     println!("//Credential creation: {}ms", duration);
 
-    let mut circuit = None;
-    let mut zkey = None;
-    if cfg!(target_os = "android") {
-        circuit = Some(
-            std::fs::read("presentation_polygon.dat").unwrap()
-        );
-        zkey = Some(
-            std::fs::read("presentation_polygon.zkey").unwrap()
-        );
-
-    }
+    let loader = loader();
     
     let revoc_reg =
-    RevocationRegistry::<PoseidonHasher, PoseidonSignature>::new(Some(issuer_sk.clone()));
+    RevocationRegistry::<PoseidonHasher, PoseidonSignature>::new(Some(issuer_sk.clone())).unwrap();
 
     start = Instant::now();
     let mut polygon_pres = PolygonPresentation::<PoseidonHasher, PoseidonSignature>::new(
@@ -551,7 +545,7 @@ fn polygon_presentation_fragmented(c: &mut Criterion){
 
     start = Instant::now();
     polygon_pres.presentation.ark_circom_full_proof =
-        match proof_polygon_presentation(&input_json, circuit.as_ref(), zkey.as_ref()) {
+        match polygon::CIRCUIT.prove(&input_json, &loader) {
             Ok(proof) => Some(proof),
             Err(err) => panic!("Proving process failed"),
         };
@@ -601,23 +595,14 @@ fn delegation_presentation(c: &mut Criterion){
             "revocRegistry",
             issuer_sk.clone(),
         )
+        .unwrap()
     );
     
     let revoc_reg =
-    RevocationRegistry::<PoseidonHasher, PoseidonSignature>::new(Some(issuer_sk.clone()));
-
+    RevocationRegistry::<PoseidonHasher, PoseidonSignature>::new(Some(issuer_sk.clone())).unwrap();
 
-    let mut circuit = None;
-    let mut zkey = None;
-    if cfg!(target_os = "android") {
-        circuit = Some(
-            std::fs::read("presentation_delegation.dat").unwrap()
-        );
-        zkey = Some(
-            std::fs::read("presentation_delegation.zkey").unwrap()
-        );
 
-    }
+    let loader = loader();
 
     c.bench_function("delegation presentation generation", 
         |b| b.iter(||{
@@ -631,7 +616,7 @@ fn delegation_presentation(c: &mut Criterion){
             )
             .unwrap();
     
-            del_pres.generate(circuit.as_ref(), zkey.as_ref()).unwrap();
+            del_pres.generate(&loader).unwrap();
         })
     );
 }
@@ -675,24 +660,15 @@ fn delegation_presentation_fragmented(c: &mut Criterion){
             "revocRegistry",
             issuer_sk.clone(),
         )
+        .unwrap()
     );
     let mut duration = start.elapsed().as_millis();
     println!("//Credential creation: {}ms", duration);
 
-    let mut circuit = None;
-    let mut zkey = None;
-    if cfg!(target_os = "android") {
-        circuit = Some(
-            std::fs::read("presentation_delegation.dat").unwrap()
-        );
-        zkey = Some(
-            std::fs::read("presentation_delegation.zkey").unwrap()
-        );
-
-    }
+    let loader = loader();
     
     let revoc_reg =
-    RevocationRegistry::<PoseidonHasher, PoseidonSignature>::new(Some(issuer_sk.clone()));
+    RevocationRegistry::<PoseidonHasher, PoseidonSignature>::new(Some(issuer_sk.clone())).unwrap();
 
     start = Instant::now();
     let mut delegation_pres = DelegationPresentation::<PoseidonHasher, PoseidonSignature>::new(
@@ -723,7 +699,7 @@ fn delegation_presentation_fragmented(c: &mut Criterion){
 
     start = Instant::now();
     delegation_pres.attribute_presentation.presentation.ark_circom_full_proof =
-        match proof_delegation_presentation(&input_json, circuit.as_ref(), zkey.as_ref()) {
+        match delegation::CIRCUIT.prove(&input_json, &loader) {
             Ok(proof) => Some(proof),
             Err(err) => panic!("Proving process failed"),
         };
@@ -740,7 +716,7 @@ fn delegation_presentation_fragmented(c: &mut Criterion){
 criterion_group!(
     name = benches;
     config = Criterion::default().sample_size(10).measurement_time(Duration::from_millis(1000));
-    targets = attribute_presentation, attribute_presentation_fragmented,
+    targets = attribute_presentation, attribute_presentation_cached, attribute_presentation_fragmented,
      range_presentation, range_presentation_fragmented,
      polygon_presentation, polygon_presentation_fragmented,
      delegation_presentation, delegation_presentation_fragmented);
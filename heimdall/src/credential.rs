@@ -3,7 +3,9 @@ use std::{marker::PhantomData, time::{SystemTime, UNIX_EPOCH}};
 use serde::{Serialize, Deserialize};
 use num_bigint::BigInt;
 
-use crypto::{HashFunction, Signature,merkle_tree::{HashTree, self}, SignatureFunction};
+use crypto::{HashFunction, Signature,merkle_tree::{HashTree, MerkleProof, self}, SignatureFunction};
+
+use crate::{error::HeimdallError, revocation::{RevocationRegistry, REVOC_CAPACITY}};
 
 ///Number of leaves reserved for meta attributes.
 pub const META_SIZE: usize = 8;
@@ -24,9 +26,15 @@ pub struct Credential<H: HashFunction, S: SignatureFunction>{
 impl<H: HashFunction, S: SignatureFunction> Credential<H,S>{
 
     ///Generates a new Heimdall credential.
+    /// 
+    /// Fails, should the id exceed the capacity of a revocation registry.
+    /// Use 'issue' to obtain an unused id from the registry.
     pub fn new(attributes: &Vec<String>, id: u64, pk_holder: &[BigInt;2],
     expiration: u128, credential_type: &str,delegatable: bool, registry: &str, sk_issuer: BigInt)
-     -> Credential<H,S>{
+     -> Result<Credential<H,S>, HeimdallError>{
+        if id >= REVOC_CAPACITY as u64 {
+            Err(HeimdallError::IndexOutOfRange { index: id as usize, len: REVOC_CAPACITY })?;
+        }
         
         let mut full_attributes = Vec::<String>::new();
 
@@ -42,14 +50,59 @@ impl<H: HashFunction, S: SignatureFunction> Credential<H,S>{
 
         full_attributes.append(&mut attributes.clone());
 
-        merkle_tree::fill_vec(&mut full_attributes, 6);
-        let tree = HashTree::<H,6>::new(&full_attributes);
+        merkle_tree::fill_vec(&mut full_attributes, 6)?;
+        let tree = HashTree::<H,6>::new(&full_attributes)?;
         let root = tree.get_root().to_owned();
         
-        let signature = S::sign(sk_issuer, root.clone());
+        let signature = S::sign(sk_issuer, root.clone())?;
 
-        Credential { attributes: full_attributes, root, signature,
-        _hash_fn:PhantomData::<fn()->H>, _sig_fn: PhantomData::<fn()->S> }
+        Ok(Credential { attributes: full_attributes, root, signature,
+        _hash_fn:PhantomData::<fn()->H>, _sig_fn: PhantomData::<fn()->S> })
+    }
+
+    ///Generates a new Heimdall credential with an id allocated from the revocation registry.
+    /// 
+    /// # Arguments
+    /// * 'revocation_registry' - The registry named 'registry', assigning the id.
+    ///                           The id is released again, should the generation fail.
+    pub fn issue(attributes: &Vec<String>, revocation_registry: &mut RevocationRegistry<H,S>, pk_holder: &[BigInt;2],
+    expiration: u128, credential_type: &str,delegatable: bool, registry: &str, sk_issuer: BigInt)
+     -> Result<Credential<H,S>, HeimdallError>{
+        let id = revocation_registry.allocate_id()?;
+
+        let result = Self::new(attributes, id as u64, pk_holder, expiration,
+            credential_type, delegatable, registry, sk_issuer);
+        if result.is_err() {
+            revocation_registry.release_id(id)?;
+        }
+        result
+    }
+
+    ///Generates a Merkle proof for the attribute at 'index', including the meta attributes,
+    ///  for disclosing it outside of a ZKP.
+    pub fn generate_attribute_proof(&self, index: usize) -> Result<MerkleProof<H,6>, HeimdallError>{
+        let tree = HashTree::<H,6>::new(&self.attributes)?;
+        Ok(tree.generate_proof(index)?)
+    }
+
+    ///Checks that 'attribute' is the attribute at 'index' of a credential signed by the issuer.
+    /// 
+    /// # Arguments
+    /// * 'proof' - The Merkle proof generated by the holder with 'generate_attribute_proof'.
+    /// * 'signature' - The signature of the credential root.
+    pub fn verify_attribute_proof(proof: &MerkleProof<H,6>, attribute: &str, index: usize,
+    signature: &Signature<S>, pk_issuer: &[BigInt;2]) -> Result<bool, HeimdallError>{
+        let root = match proof.root() {
+            Some(root) => root,
+            None => return Ok(false),
+        };
+        if &signature.public_key_signer != pk_issuer || !S::verify(signature, root) {
+            Err(HeimdallError::SignatureInvalid("Credential root not signed by the issuer.".to_owned()))?;
+        }
+
+        Ok(proof.index() == index
+            && proof.lemma[0] == H::new().hash_str(attribute)?
+            && proof.verify()?)
     }
 }
 
@@ -72,7 +125,9 @@ mod test{
     use crypto::{poseidon::{PoseidonHasher, PoseidonSignature}, SignatureFunction};
     use num_bigint::BigInt;
 
-    use super::Credential;
+    use crate::{error::HeimdallError, revocation::{RevocationRegistry, REVOC_CAPACITY}};
+
+    use super::{Credential, META_SIZE};
 
 
 
@@ -100,10 +155,54 @@ mod test{
         false,
         "revocRegistry",
         issuer_sk.clone()
-        );
+        ).unwrap();
         
         assert!(PoseidonSignature::verify(&cred.signature, &cred.root));
     }
 
-    
-}
\ No newline at end of file
+    ///TEST: credential ids allocated from the revocation registry
+    #[test]
+    fn credential_issue(){
+        let issuer_sk = BigInt::from_str("2951225162891973271265230278305932248884420185229553697262927872").unwrap();
+        let pk_holder = [BigInt::from_str("11568348142699582059879762896692005650111252224863899748681544124434641871979").unwrap(),
+        BigInt::from_str("3313301605305461355814038303705256811688733498785606352476634260778286273969").unwrap()];
+        let mut registry = RevocationRegistry::<PoseidonHasher,PoseidonSignature>::new(None).unwrap();
+
+        let issue = |registry: &mut RevocationRegistry<PoseidonHasher,PoseidonSignature>|
+            Credential::<PoseidonHasher,PoseidonSignature>::issue(&vec!["John".to_owned()],
+                registry, &pk_holder, 365, "Identity", false, "revocRegistry", issuer_sk.clone());
+        let first = issue(&mut registry).unwrap();
+        let second = issue(&mut registry).unwrap();
+        assert_ne!(first.attributes[0], second.attributes[0]);
+        assert!(registry.is_allocated(usize::from_str(&second.attributes[0]).unwrap()));
+
+        assert!(matches!(
+            Credential::<PoseidonHasher,PoseidonSignature>::new(&vec!["John".to_owned()], REVOC_CAPACITY as u64,
+                &pk_holder, 365, "Identity", false, "revocRegistry", issuer_sk.clone()),
+            Err(HeimdallError::IndexOutOfRange { .. })
+        ));
+    }
+
+    ///TEST: attribute disclosure with Merkle proofs
+    #[test]
+    fn credential_attribute_proof(){
+        let issuer_sk = BigInt::from_str("2951225162891973271265230278305932248884420185229553697262927872").unwrap();
+        let issuer_pk = PoseidonSignature::get_public_keys(issuer_sk.clone()).unwrap();
+        let cred = Credential::<PoseidonHasher,PoseidonSignature>::new(&vec!["John".to_owned(), "Jones".to_owned()],
+            7, &issuer_pk, 365, "Identity", false, "revocRegistry", issuer_sk.clone()).unwrap();
+
+        let proof = cred.generate_attribute_proof(META_SIZE + 1).unwrap();
+        assert_eq!(proof.root(), Some(&cred.root));
+        assert!(Credential::<PoseidonHasher,PoseidonSignature>::verify_attribute_proof(
+            &proof, "Jones", META_SIZE + 1, &cred.signature, &issuer_pk).unwrap());
+        assert!(!Credential::<PoseidonHasher,PoseidonSignature>::verify_attribute_proof(
+            &proof, "John", META_SIZE + 1, &cred.signature, &issuer_pk).unwrap());
+        assert!(!Credential::<PoseidonHasher,PoseidonSignature>::verify_attribute_proof(
+            &proof, "Jones", META_SIZE, &cred.signature, &issuer_pk).unwrap());
+
+        let other_pk = PoseidonSignature::get_public_keys(BigInt::from(42)).unwrap();
+        assert!(Credential::<PoseidonHasher,PoseidonSignature>::verify_attribute_proof(
+            &proof, "Jones", META_SIZE + 1, &cred.signature, &other_pk).is_err());
+        assert!(cred.generate_attribute_proof(cred.attributes.len()).is_err());
+    }
+}
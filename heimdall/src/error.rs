@@ -0,0 +1,101 @@
+use std::{error::Error, fmt};
+
+use ark_circom_service::ProofError;
+use crypto::error::CryptoError;
+use witness_utils::WitnessError;
+
+///Errors raised while issuing, presenting or revoking credentials.
+#[derive(Debug)]
+pub enum HeimdallError {
+    ///A circuit resource could not be loaded.
+    MissingResource(String),
+    ///A circuit resource does not match its expected digest.
+    UntrustedResource(String),
+    ///The provided input is malformed.
+    InvalidInput(String),
+    ///An index exceeds the number of available elements.
+    IndexOutOfRange { index: usize, len: usize },
+    ///A signature does not verify under the expected key.
+    SignatureInvalid(String),
+    ///The id already has the requested revocation status.
+    RevocationUnchanged { id: usize, revoked: bool },
+    ///An event of the revocation log cannot be replayed.
+    InvalidEventLog { index: usize, msg: String },
+    ///No revocation registry is known under the name.
+    UnknownRegistry(String),
+    ///All ids of the revocation registry are assigned.
+    RegistryFull { capacity: usize },
+    ///Persisted data is malformed or inconsistent.
+    InvalidEncoding(String),
+    ///The generated presentation does not verify.
+    PresentationInvalid,
+    ///A signed timestamp is too old or lies in the future.
+    TimestampOutOfRange { timestamp: u128, now: u128 },
+    ///The system clock precedes the unix epoch.
+    ClockUnavailable(String),
+    Crypto(CryptoError),
+    Proof(ProofError),
+    Witness(WitnessError),
+}
+
+impl fmt::Display for HeimdallError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            HeimdallError::MissingResource(name) => write!(f, "Missing resource {}", name),
+            HeimdallError::UntrustedResource(msg) => write!(f, "Untrusted resource: {}", msg),
+            HeimdallError::InvalidInput(msg) => write!(f, "Invalid input: {}", msg),
+            HeimdallError::IndexOutOfRange { index, len } => {
+                write!(f, "Index {} out of range for length {}", index, len)
+            }
+            HeimdallError::SignatureInvalid(msg) => write!(f, "Invalid signature: {}", msg),
+            HeimdallError::RevocationUnchanged { id, revoked } => {
+                write!(f, "Id {} is {} revoked", id, if *revoked { "already" } else { "not" })
+            }
+            HeimdallError::InvalidEventLog { index, msg } => {
+                write!(f, "Invalid revocation event {}: {}", index, msg)
+            }
+            HeimdallError::UnknownRegistry(name) => write!(f, "Unknown revocation registry {}", name),
+            HeimdallError::RegistryFull { capacity } => {
+                write!(f, "All {} ids of the revocation registry are assigned", capacity)
+            }
+            HeimdallError::InvalidEncoding(msg) => write!(f, "Invalid encoding: {}", msg),
+            HeimdallError::PresentationInvalid => write!(f, "Presentation invalid"),
+            HeimdallError::TimestampOutOfRange { timestamp, now } => {
+                write!(f, "Timestamp {} outside the accepted window at {}", timestamp, now)
+            }
+            HeimdallError::ClockUnavailable(msg) => write!(f, "Clock unavailable: {}", msg),
+            HeimdallError::Crypto(err) => write!(f, "{}", err),
+            HeimdallError::Proof(err) => write!(f, "{}", err),
+            HeimdallError::Witness(err) => write!(f, "{}", err),
+        }
+    }
+}
+
+impl Error for HeimdallError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            HeimdallError::Crypto(err) => Some(err),
+            HeimdallError::Proof(err) => Some(err),
+            HeimdallError::Witness(err) => Some(err),
+            _ => None,
+        }
+    }
+}
+
+impl From<CryptoError> for HeimdallError {
+    fn from(err: CryptoError) -> Self {
+        HeimdallError::Crypto(err)
+    }
+}
+
+impl From<ProofError> for HeimdallError {
+    fn from(err: ProofError) -> Self {
+        HeimdallError::Proof(err)
+    }
+}
+
+impl From<WitnessError> for HeimdallError {
+    fn from(err: WitnessError) -> Self {
+        HeimdallError::Witness(err)
+    }
+}
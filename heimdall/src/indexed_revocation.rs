@@ -0,0 +1,260 @@
+//! Indexed Merkle tree alternative to the bit-vector revocation registry.
+//!
+//! Proves ids not to be revoked with non-membership witnesses, without revealing a block of ids.
+//! Only available out of circuit: the presentation circuits verify bit-vector witnesses,
+//!  so presentations can't be generated from the indexed registry.
+//! Meant for verifiers checking the status of credentials presented in the clear.
+
+use std::collections::BTreeMap;
+
+use crypto::{HashFunction, merkle_tree::{MerkleProof, SparseMerkleTree}, SignatureFunction};
+use num_bigint::BigInt;
+use serde::{Serialize, Deserialize};
+
+use crate::{error::HeimdallError, revocation::{RevocationScheme, RevocationWitness, SignedRevocationRoot}};
+
+///Depth of the indexed Merkle tree, limiting the number of revocations.
+pub const INDEXED_TREE_DEPTH: u32 = 32;
+
+///A struct representing a leaf of the indexed Merkle tree.
+/// 
+/// The leaves form a list sorted by value, each leaf pointing to the next larger value.
+/// Values are the revoked ids shifted by one, the value 0 being the head of the list.
+#[derive(Debug,Clone,PartialEq,Eq,Serialize,Deserialize)]
+pub struct IndexedLeaf{
+    pub value: usize,
+    ///Position of the leaf with the next larger value.
+    pub next_index: usize,
+    ///Next larger value, 0 for the end of the list.
+    pub next_value: usize,
+}
+
+impl IndexedLeaf{
+
+    ///Computes the value stored in the tree for the leaf.
+    fn hash<H: HashFunction>(&self) -> Result<BigInt, HeimdallError>{
+        Ok(H::new().hash_big_int(&[
+            BigInt::from(self.value), BigInt::from(self.next_index), BigInt::from(self.next_value)
+        ])?)
+    }
+
+    ///Checks wether the value lies strictly between the leaf and its successor.
+    fn covers(&self, value: usize) -> bool{
+        self.value < value && (self.next_value == 0 || value < self.next_value)
+    }
+}
+
+///A struct representing the witness of the indexed registry.
+/// 
+/// Proves that the id is not revoked through the leaf with the next smaller value,
+///  whose successor is larger than the id.
+/// Reveals nothing about the position of the id among all ids.
+#[derive(Debug,Clone,Serialize,Deserialize)]
+pub struct NonMembershipWitness<H: HashFunction>{
+    pub low_leaf: IndexedLeaf,
+    #[serde(bound(serialize = "MerkleProof<H,2>: Serialize", deserialize = "MerkleProof<H,2>: Deserialize<'de>"))]
+    pub proof: MerkleProof<H,2>,
+}
+
+impl<H: HashFunction> RevocationWitness for NonMembershipWitness<H>{
+    fn verify_unrevoked(&self, id: usize, root: &BigInt) -> Result<bool, HeimdallError>{
+        let leaf = self.low_leaf.hash::<H>()?.to_string();
+
+        let Some(value) = id.checked_add(1) else { return Ok(false) };
+
+        Ok(self.low_leaf.covers(value)
+            && self.proof.lemma.first() == Some(&H::new().hash_str(&leaf)?)
+            && self.proof.lemma.last() == Some(root)
+            && self.proof.verify()?)
+    }
+}
+
+///A struct representing a revocation registry based on an indexed Merkle tree.
+/// 
+/// Holds only the revoked ids, which can't be reinstated.
+/// Proves ids not to be revoked with non-membership witnesses,
+///  which do not reveal a block of ids like the bit-vector registry.
+#[derive(Serialize,Deserialize)]
+pub struct IndexedRevocationRegistry<H: HashFunction,S: SignatureFunction>{
+    #[serde(bound(serialize = "SparseMerkleTree<H>: Serialize", deserialize = "SparseMerkleTree<H>: Deserialize<'de>"))]
+    tree: SparseMerkleTree<H>,
+    ///Leaves in the order of insertion, matching their position in the tree.
+    leaves: Vec<IndexedLeaf>,
+    ///Positions of the leaves by value.
+    positions: BTreeMap<usize, usize>,
+    pub signed_root: Option<SignedRevocationRoot<S>>,
+}
+
+impl<H:HashFunction, S: SignatureFunction> IndexedRevocationRegistry<H,S>{
+
+    ///Creates a registry without revoked ids.
+    /// If present, signs the root with the secret key.
+    pub fn new(secret_key: Option<BigInt>) -> Result<Self, HeimdallError>{
+        let mut registry = IndexedRevocationRegistry {
+            tree: SparseMerkleTree::<H>::new(INDEXED_TREE_DEPTH, "0")?,
+            leaves: Vec::new(),
+            positions: BTreeMap::new(),
+            signed_root: None,
+        };
+        registry.insert_leaf(IndexedLeaf { value: 0, next_index: 0, next_value: 0 })?;
+        registry.sign(secret_key)?;
+        Ok(registry)
+    }
+
+    ///Revokes the id by inserting it into the sorted list.
+    /// If present, signs the new root with the secret key.
+    /// 
+    /// Fails, should the id already be revoked.
+    pub fn revoke(&mut self, id: usize, sk: Option<BigInt>) -> Result<(), HeimdallError>{
+        let value = id.checked_add(1)
+            .ok_or(HeimdallError::IndexOutOfRange { index: id, len: usize::MAX })?;
+        if self.positions.contains_key(&value) {
+            Err(HeimdallError::RevocationUnchanged { id, revoked: true })?;
+        }
+        if self.leaves.len() >= self.tree.leave_count() {
+            Err(HeimdallError::RegistryFull { capacity: self.tree.leave_count() - 1 })?;
+        }
+
+        let low_index = self.low_leaf(value);
+        let low_leaf = self.leaves[low_index].clone();
+        let new_leaf = IndexedLeaf { value, next_index: low_leaf.next_index, next_value: low_leaf.next_value };
+        let new_index = self.leaves.len();
+
+        let updated_low = IndexedLeaf { next_index: new_index, next_value: value, ..low_leaf };
+        self.tree.update_leaves(&[
+            (low_index, updated_low.hash::<H>()?.to_string()),
+            (new_index, new_leaf.hash::<H>()?.to_string()),
+        ])?;
+        self.leaves[low_index] = updated_low;
+        self.leaves.push(new_leaf);
+        self.positions.insert(value, new_index);
+
+        self.sign(sk)
+    }
+
+    ///Retrieves the number of revoked ids.
+    pub fn revoked_count(&self) -> usize{
+        self.leaves.len() - 1
+    }
+
+    ///Checks that the registry carries a fresh root signed by the issuer matching its tree.
+    pub fn verify_signed_root(&self, pk_issuer: &[BigInt;2], max_age: u128)
+    -> Result<(),HeimdallError>{
+        match &self.signed_root {
+            Some(signed_root) if &signed_root.root == self.tree.get_root() => signed_root.verify::<H>(pk_issuer, max_age),
+            Some(_) => Err(HeimdallError::SignatureInvalid("Signed root does not match the revocation registry.".to_owned())),
+            None => Err(HeimdallError::SignatureInvalid("Revocation registry not signed.".to_owned())),
+        }
+    }
+
+    ///Position of the leaf with the largest value smaller than 'value'.
+    fn low_leaf(&self, value: usize) -> usize{
+        //the head of the list with value 0 is always present
+        *self.positions.range(..value).next_back().expect("head of the list").1
+    }
+
+    fn insert_leaf(&mut self, leaf: IndexedLeaf) -> Result<(), HeimdallError>{
+        let index = self.leaves.len();
+        self.tree.update(index, leaf.hash::<H>()?.to_string())?;
+        self.positions.insert(leaf.value, index);
+        self.leaves.push(leaf);
+        Ok(())
+    }
+
+    fn sign(&mut self, sk: Option<BigInt>) -> Result<(), HeimdallError>{
+        self.signed_root = match sk{
+            Some(sk) => Some(SignedRevocationRoot::new::<H>(self.tree.get_root().clone(), sk)?),
+            None => None,
+        };
+        Ok(())
+    }
+}
+
+impl<H:HashFunction, S: SignatureFunction> RevocationScheme<H> for IndexedRevocationRegistry<H,S>{
+    type Witness = NonMembershipWitness<H>;
+
+    fn root(&self) -> &BigInt{
+        self.tree.get_root()
+    }
+
+    fn is_revoked(&self, id: usize) -> Result<bool, HeimdallError>{
+        Ok(id.checked_add(1).is_some_and(|value| self.positions.contains_key(&value)))
+    }
+
+    ///Generates the non-membership witness for the id.
+    /// Fails, should the id be revoked or exceed the ids representable in the tree.
+    fn witness_for(&self, id: usize) -> Result<Self::Witness, HeimdallError>{
+        let value = id.checked_add(1)
+            .ok_or(HeimdallError::IndexOutOfRange { index: id, len: usize::MAX })?;
+        if self.positions.contains_key(&value) {
+            Err(HeimdallError::RevocationUnchanged { id, revoked: true })?;
+        }
+        let low_index = self.low_leaf(value);
+        Ok(NonMembershipWitness {
+            low_leaf: self.leaves[low_index].clone(),
+            proof: self.tree.generate_proof(low_index)?,
+        })
+    }
+}
+
+#[cfg(test)]
+mod test{
+    use std::str::FromStr;
+
+    use crypto::poseidon::{PoseidonHasher, PoseidonSignature};
+
+    use super::*;
+
+    ///Checks the witnesses of the registry for revoked and unrevoked ids.
+    fn check_scheme<R: RevocationScheme<PoseidonHasher>>(scheme: &R, revoked: &[usize], unrevoked: &[usize]){
+        for id in revoked {
+            assert!(RevocationScheme::is_revoked(scheme, *id).unwrap());
+        }
+        for id in unrevoked {
+            assert!(!RevocationScheme::is_revoked(scheme, *id).unwrap());
+            let witness = scheme.witness_for(*id).unwrap();
+            assert!(witness.verify_unrevoked(*id, scheme.root()).unwrap());
+            assert!(!witness.verify_unrevoked(*id, &BigInt::from(1)).unwrap());
+        }
+    }
+
+    ///TEST: indexed registry as revocation scheme
+    #[test]
+    pub fn revocation_scheme_indexed() {
+        let issuer_sk = BigInt::from_str("2951225162891973271265230278305932248884420185229553697262927872").unwrap();
+        let issuer_pk = PoseidonSignature::get_public_keys(issuer_sk.clone()).unwrap();
+        let mut rev_reg =
+         IndexedRevocationRegistry::<PoseidonHasher,PoseidonSignature>::new(None).unwrap();
+        check_scheme(&rev_reg, &[], &[0, 1, 1000]);
+
+        for id in [500, 0, 7, 9000] {
+            rev_reg.revoke(id, None).unwrap();
+        }
+        rev_reg.revoke(8, Some(issuer_sk.clone())).unwrap();
+        assert_eq!(rev_reg.revoked_count(), 5);
+        assert!(rev_reg.verify_signed_root(&issuer_pk, 60*1000).is_ok());
+        assert!(matches!(rev_reg.revoke(7, None), Err(HeimdallError::RevocationUnchanged { id: 7, revoked: true })));
+        assert!(rev_reg.witness_for(7).is_err());
+        check_scheme(&rev_reg, &[0, 7, 8, 500, 9000], &[1, 6, 9, 499, 501, 8999, 9001, usize::MAX - 1]);
+
+        //the witness of one gap does not cover ids of another gap
+        let witness = rev_reg.witness_for(6).unwrap();
+        assert!(!witness.verify_unrevoked(7, rev_reg.root()).unwrap());
+        assert!(!witness.verify_unrevoked(600, rev_reg.root()).unwrap());
+
+        //the largest id can't be shifted into the list
+        assert!(!rev_reg.witness_for(9001).unwrap().verify_unrevoked(usize::MAX, rev_reg.root()).unwrap());
+        assert!(matches!(rev_reg.witness_for(usize::MAX), Err(HeimdallError::IndexOutOfRange { .. })));
+
+        //the sorted list is consistent
+        let mut index = 0;
+        let mut values = Vec::new();
+        loop {
+            let leaf = &rev_reg.leaves[index];
+            values.push(leaf.value);
+            if leaf.next_value == 0 { break; }
+            index = leaf.next_index;
+        }
+        assert_eq!(values, vec![0, 1, 8, 9, 501, 9001]);
+    }
+}
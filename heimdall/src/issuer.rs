@@ -0,0 +1,197 @@
+use std::collections::HashMap;
+
+use crypto::HashFunction;
+use num_bigint::BigInt;
+use serde::{Deserialize, Serialize};
+
+///A struct representing an issuer trusted by the verifier.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TrustedIssuer {
+    pub name: String,
+    pub public_key: [BigInt; 2],
+
+    ///Start of the validity window in milliseconds since the unix epoch.
+    pub valid_from: u128,
+    ///End of the validity window in milliseconds since the unix epoch.
+    /// Unbounded if not present.
+    pub valid_until: Option<u128>,
+}
+
+///The outcome of checking the issuer of a presentation against the trusted issuers.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub enum IssuerVerification {
+    ///The credential was signed by the named trusted issuer.
+    Trusted { issuer: String },
+    ///The presentation does not contain a proof.
+    MissingProof,
+    ///No issuer is trusted for the credential type.
+    UnknownCredentialType { meta_type: String },
+    ///The credential was signed by the named issuer, whose validity window starts after the time of the check.
+    NotYetValid { issuer: String },
+    ///The credential was signed by the named issuer, whose validity window ends before the time of the check.
+    Expired { issuer: String },
+    ///The time of the check could not be determined from the clock of the verifier.
+    ClockUnavailable,
+    ///The credential was not signed by any issuer trusted for the credential type.
+    Untrusted,
+}
+
+impl IssuerVerification {
+    ///Checks wether a trusted issuer matched.
+    pub fn is_trusted(&self) -> bool {
+        matches!(self, IssuerVerification::Trusted { .. })
+    }
+}
+
+///A struct representing the issuers trusted by the verifier, grouped by credential type.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct TrustedIssuers {
+    issuers: HashMap<String, Vec<TrustedIssuer>>,
+}
+
+impl TrustedIssuers {
+    ///Creates an empty trust list.
+    pub fn new() -> Self {
+        TrustedIssuers {
+            issuers: HashMap::new(),
+        }
+    }
+
+    ///Trusts the issuer for credentials of the type 'meta_type'.
+    pub fn insert(&mut self, meta_type: &str, issuer: TrustedIssuer) {
+        self.issuers
+            .entry(meta_type.to_owned())
+            .or_insert_with(Vec::new)
+            .push(issuer);
+    }
+
+    ///Retrieves the issuers trusted for credentials of the type 'meta_type'.
+    pub fn get(&self, meta_type: &str) -> &[TrustedIssuer] {
+        match self.issuers.get(meta_type) {
+            Some(issuers) => issuers,
+            None => &[],
+        }
+    }
+
+    ///Checks the link back of a presentation against the trusted issuers.
+    ///
+    /// The link back is the public hash of the challenge and the public key of the issuer,
+    ///  computed in the circuit from the key that signed the credential.
+    ///
+    /// # Arguments
+    /// * 'time' - The time of the check in milliseconds since the unix epoch.
+    pub fn check<H: HashFunction>(
+        &self,
+        meta_type: &str,
+        challenge: &BigInt,
+        link_back: &BigInt,
+        time: u128,
+    ) -> IssuerVerification {
+        let issuers = self.get(meta_type);
+        if issuers.is_empty() {
+            return IssuerVerification::UnknownCredentialType {
+                meta_type: meta_type.to_owned(),
+            };
+        }
+
+        let hash_fn = H::new();
+        let mut result = IssuerVerification::Untrusted;
+        for issuer in issuers {
+            let expected = hash_fn.hash(&vec![
+                challenge.to_string(),
+                issuer.public_key[0].to_string(),
+                issuer.public_key[1].to_string(),
+            ]);
+            if expected.as_ref() != Ok(link_back) {
+                continue;
+            }
+
+            if time < issuer.valid_from {
+                result = IssuerVerification::NotYetValid {
+                    issuer: issuer.name.clone(),
+                };
+            } else if issuer.valid_until.map_or(false, |until| time > until) {
+                result = IssuerVerification::Expired {
+                    issuer: issuer.name.clone(),
+                };
+            } else {
+                return IssuerVerification::Trusted {
+                    issuer: issuer.name.clone(),
+                };
+            }
+        }
+
+        result
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::str::FromStr;
+
+    use crypto::poseidon::{PoseidonHasher, PoseidonSignature};
+
+    use super::*;
+
+    ///TEST: issuer trust list
+    #[test]
+    fn trusted_issuers() {
+        let issuer_sk =
+            BigInt::from_str("2951225162891973271265230278305932248884420185229553697262927872")
+                .unwrap();
+        let issuer_pk = PoseidonSignature::get_public_keys(issuer_sk).unwrap();
+        let challenge = BigInt::from(1234);
+
+        let link_back = PoseidonHasher::new().hash(&vec![
+            challenge.to_string(),
+            issuer_pk[0].to_string(),
+            issuer_pk[1].to_string(),
+        ])
+        .unwrap();
+
+        let mut trusted_issuers = TrustedIssuers::new();
+        trusted_issuers.insert(
+            "Identity",
+            TrustedIssuer {
+                name: "issuer".to_owned(),
+                public_key: issuer_pk,
+                valid_from: 1000,
+                valid_until: Some(2000),
+            },
+        );
+
+        assert_eq!(
+            trusted_issuers.check::<PoseidonHasher>("Identity", &challenge, &link_back, 1500),
+            IssuerVerification::Trusted {
+                issuer: "issuer".to_owned()
+            }
+        );
+        assert_eq!(
+            trusted_issuers.check::<PoseidonHasher>("Identity", &challenge, &link_back, 500),
+            IssuerVerification::NotYetValid {
+                issuer: "issuer".to_owned()
+            }
+        );
+        assert_eq!(
+            trusted_issuers.check::<PoseidonHasher>("Identity", &challenge, &link_back, 2500),
+            IssuerVerification::Expired {
+                issuer: "issuer".to_owned()
+            }
+        );
+        assert_eq!(
+            trusted_issuers.check::<PoseidonHasher>(
+                "Identity",
+                &BigInt::from(4321),
+                &link_back,
+                1500
+            ),
+            IssuerVerification::Untrusted
+        );
+        assert_eq!(
+            trusted_issuers.check::<PoseidonHasher>("Passport", &challenge, &link_back, 1500),
+            IssuerVerification::UnknownCredentialType {
+                meta_type: "Passport".to_owned()
+            }
+        );
+    }
+}
@@ -1,4 +1,11 @@
 pub mod credential;
+pub mod error;
+pub mod indexed_revocation;
+pub mod issuer;
 pub mod presentation;
+pub mod report;
+pub mod resource;
 pub mod revocation;
+pub mod revocation_manager;
+pub mod status_list;
 pub mod zkp;
@@ -1,20 +1,21 @@
-use std::{error::Error, str::FromStr};
+use std::str::FromStr;
 
 use num_bigint::BigInt;
 use num_traits::One;
 use serde::{Deserialize, Serialize};
 use serde_big_array::BigArray;
 
-use ark_circom_service::{ArkCircomFullProof, Bn254};
+use ark_circom_service::{ArkCircomFullProof, Bn254, VerifierKeyStore};
 use crypto::{merkle_tree::MerkleProof, HashFunction, Signature, SignatureFunction};
 
 use crate::{
-    credential::Credential,
-    revocation::{RevocationRegistry, MAX_LEAF_SIZE},
-    zkp::{
-        attribute::proof_attribute_presentation, delegation::proof_delegation_presentation,
-        polygon::proof_polygon_presentation, range::proof_range_presentation,
-    },
+    credential::{Credential, META_SIZE},
+    error::HeimdallError,
+    issuer::{IssuerVerification, TrustedIssuers},
+    report::VerificationReport,
+    resource::ResourceLoader,
+    revocation::{now, BitVectorWitness, RevocationScheme, SignedRevocationRoot},
+    zkp::{attribute, delegation, polygon, range, Circuit, CircuitProver},
 };
 
 /// Maximum number of points for a polygon as specified in the circuit.
@@ -76,13 +77,23 @@ pub struct Presentation<H: HashFunction, S: SignatureFunction> {
 
 ///
 pub trait ContentPresentation: Serialize{
-    fn verify(&self) -> bool;
-    fn generate_input_json(&self) -> Result<String, Box<dyn Error>>;
-    fn generate(
-        &mut self,
-        circuit: Option<&Vec<u8>>,
-        zkey: Option<&Vec<u8>>,
-    ) -> Result<(), Box<dyn Error>>;
+    ///Verifies the presentation against the trusted verification keys.
+    /// Lists the outcome of every check in the report.
+    fn verify(&self, key_store: &VerifierKeyStore) -> VerificationReport;
+    ///Verifies the presentation like 'verify' and checks that the credential was signed by a trusted issuer.
+    /// The issuer is only checked once the proof and all other checks passed.
+    fn verify_with_issuers(&self, key_store: &VerifierKeyStore, trusted_issuers: &TrustedIssuers) -> VerificationReport;
+    ///Retrieves the descriptor of the circuit proving the presentation.
+    fn circuit(&self) -> &'static Circuit;
+    fn generate_input_json(&self) -> Result<String, HeimdallError>;
+    ///Generates the presentation, loading the ressources of the circuit.
+    /// Use 'generate_with_prover' for repeated presentations.
+    fn generate(&mut self, loader: &dyn ResourceLoader) -> Result<(), HeimdallError> {
+        let prover = self.circuit().prover(loader)?;
+        self.generate_with_prover(&prover)
+    }
+    ///Generates the presentation with the loaded ressources of the circuit.
+    fn generate_with_prover(&mut self, prover: &CircuitProver) -> Result<(), HeimdallError>;
     fn remove_private_data(&mut self);
 }
 
@@ -144,14 +155,23 @@ pub struct DelegationPresentation<H: HashFunction, S: SignatureFunction> {
 impl<H: HashFunction, S: SignatureFunction> Presentation<H, S> {
     ///Private constructor for the basic presentation.
     /// Used in the constructores of the subclasses.
-    fn new(
+    ///
+    /// Accepts any revocation scheme providing the bit-vector witness verified by the circuits,
+    ///  e.g. the registry of the issuer or a replica of the holder.
+    fn new<R: RevocationScheme<H, Witness = BitVectorWitness<H>>>(
         cred: &Credential<H, S>,
         expiration_date: BigInt,
-        revocation_registry: &RevocationRegistry<H, S>,
+        revocation_registry: &R,
         challenge: BigInt,
         sk_holder: Option<BigInt>,
         pk_issuer: Option<[BigInt; 2]>,
-    ) -> Self {
+    ) -> Result<Self, HeimdallError> {
+        if cred.attributes.len() < META_SIZE {
+            Err(HeimdallError::InvalidInput(format!(
+                "Credential with {} attributes lacks the meta attributes",
+                cred.attributes.len()
+            )))?
+        }
         let cred: Credential<H, S> = cred.clone();
 
         //
@@ -161,33 +181,30 @@ impl<H: HashFunction, S: SignatureFunction> Presentation<H, S> {
         //Generating circom inputs
         let mut values_zkp = Vec::<BigInt>::new();
         let hash_fn = H::new();
-        for i in 0..8 {
+        for i in 0..META_SIZE {
             let new_e = match BigInt::from_str(&cred.attributes[i]) {
                 Ok(y) => y,
                 Err(_) => {
                     if &cred.attributes[i] == "" {
                         BigInt::from(0)
                     } else {
-                        hash_fn.hash_str(&cred.attributes[i])
+                        hash_fn.hash_str(&cred.attributes[i])?
                     }
                 }
             };
             values_zkp.push(new_e);
         }
-        for i in 8..cred.attributes.len() {
-            values_zkp.push(hash_fn.hash_str(&cred.attributes[i]));
+        for i in META_SIZE..cred.attributes.len() {
+            values_zkp.push(hash_fn.hash_str(&cred.attributes[i])?);
         }
 
-        let revoc_tree_position = usize::from_str(&cred.attributes[0]).unwrap() / MAX_LEAF_SIZE;
-        let proof_revocation = revocation_registry
-            .tree
-            .generate_proof(revoc_tree_position)
-            .unwrap();
-        let revocation_leaf =
-            BigInt::from_str(&revocation_registry.tree.leaves[revoc_tree_position]).unwrap();
+        let id = usize::from_str(&cred.attributes[0]).map_err(|_| {
+            HeimdallError::InvalidInput(format!("Malformed credential id {}", cred.attributes[0]))
+        })?;
+        let witness = revocation_registry.witness_for(id)?;
         //signing challange
         let signature_challenge = match sk_holder {
-            Some(sk_holder) => Some(S::sign(sk_holder, challenge.clone())),
+            Some(sk_holder) => Some(S::sign(sk_holder, challenge.clone())?),
             None => None,
         };
 
@@ -196,15 +213,15 @@ impl<H: HashFunction, S: SignatureFunction> Presentation<H, S> {
                 challenge.to_string(),
                 pk_issuer[0].to_string(),
                 pk_issuer[1].to_string(),
-            ])),
+            ])?),
             None => None,
         };
 
         let private_inputs = PrivateInputs {
             values: values_zkp,
             signature_meta: cred.signature.clone(),
-            proof_revocation,
-            revocation_leaf,
+            proof_revocation: witness.proof,
+            revocation_leaf: witness.leaf,
             challenge: challenge.clone(),
             signature_challenge,
             expiration_date: expiration_date.clone(),
@@ -214,92 +231,190 @@ impl<H: HashFunction, S: SignatureFunction> Presentation<H, S> {
             meta_type: cred.attributes[1].clone(),
             meta_pk_issuer: pk_issuer,
             revocation_registry: cred.attributes[4].clone(),
-            revocation_root: revocation_registry.tree.get_root().clone(),
-            revoked: revocation_registry.is_revoked(revoc_tree_position).unwrap(),
+            revocation_root: revocation_registry.root().clone(),
+            revoked: revocation_registry.is_revoked(id)?,
             delegatable: cred.attributes[6] != "0",
             link_back,
             challenge,
             expiration_date,
         };
 
-        Presentation {
+        Ok(Presentation {
             credential: Some(cred),
             private_inputs: Some(private_inputs),
             public_signals: output,
             ark_circom_full_proof: None,
+        })
+    }
+
+    ///Retrieves the credential.
+    /// Fails, should the private data have already been removed.
+    fn credential(&self) -> Result<&Credential<H, S>, HeimdallError> {
+        self.credential.as_ref().ok_or_else(|| {
+            HeimdallError::InvalidInput("Credential has already been consumed!".to_owned())
+        })
+    }
+
+    ///Retrieves the public outputs of the ZKP.
+    /// Empty, should the presentation not contain a proof.
+    fn outputs(&self) -> &[BigInt] {
+        match &self.ark_circom_full_proof {
+            Some(proof) => &proof.outputs,
+            None => &[],
         }
     }
 
+    ///Retrieves the public output of the ZKP at 'index'.
+    fn output(&self, index: usize) -> Option<&BigInt> {
+        self.outputs().get(index)
+    }
+
     ///Verifies the meta data with the public signals of the ZKP.
-    pub fn verify_meta_data(
-        &self,
-        type_index: usize,
-        revocation_root_index: usize,
-        revocation_registry_hash_index: usize,
-        revoked_index: usize,
-        link_back_index: usize,
-        delegatable_index: usize,
-        challenge_index: usize,
-        expiration_date_index: usize,
-    ) -> bool {
-        match &self.ark_circom_full_proof {
-            Some(proof) => {
-                let hash_fn = H::new();
-
-                let mut res = true;
-
-                res = res
-                    && hash_fn.hash_str(&self.public_signals.meta_type)
-                        == proof.outputs[type_index];
-
-                res = res
-                    && self.public_signals.revocation_root == proof.outputs[revocation_root_index];
-
-                res = res
-                    && hash_fn.hash_str(&self.public_signals.revocation_registry)
-                        == proof.outputs[revocation_registry_hash_index];
-
-                res = res && self.public_signals.revoked == proof.outputs[revoked_index].is_one();
-                res = res
-                    && self.public_signals.delegatable == proof.outputs[delegatable_index].is_one();
-                res =
-                    res && self.public_signals.challenge == proof.outputs[challenge_index].clone();
-                res = res
-                    && self.public_signals.expiration_date
-                        == proof.outputs[expiration_date_index].clone();
-
-                if self.public_signals.meta_pk_issuer.is_some() {
-                    res = res
-                        && self.public_signals.link_back.as_ref().unwrap().clone()
-                            == proof.outputs[link_back_index];
-                }
-                res
+    pub fn verify_meta_data(&self, report: &mut VerificationReport, circuit: &Circuit) {
+        let hash_fn = H::new();
+        let layout = &circuit.layout;
+
+        report.check_result(
+            "type",
+            hash_fn.hash_str(&self.public_signals.meta_type),
+            self.output(layout.meta_type).cloned(),
+        );
+        report.check(
+            "revocation root",
+            self.public_signals.revocation_root.clone(),
+            self.output(layout.revocation_root).cloned(),
+        );
+        report.check_result(
+            "revocation registry",
+            hash_fn.hash_str(&self.public_signals.revocation_registry),
+            self.output(layout.revocation_registry).cloned(),
+        );
+        report.check(
+            "revoked",
+            self.public_signals.revoked,
+            self.output(layout.revoked).map(|x| x.is_one()),
+        );
+        report.check(
+            "delegatable",
+            self.public_signals.delegatable,
+            self.output(layout.delegatable).map(|x| x.is_one()),
+        );
+        report.check(
+            "challenge",
+            self.public_signals.challenge.clone(),
+            self.output(layout.challenge).cloned(),
+        );
+        report.check(
+            "expiration date",
+            self.public_signals.expiration_date.clone(),
+            self.output(layout.expiration_date).cloned(),
+        );
+
+        if self.public_signals.meta_pk_issuer.is_some() {
+            match &self.public_signals.link_back {
+                Some(link_back) => report.check(
+                    "link back",
+                    link_back.clone(),
+                    self.output(layout.link_back).cloned(),
+                ),
+                None => report.push("link back", false, None),
             }
-            None => false,
         }
     }
 
-    ///Verifies the zk-SNARKS.
-    pub fn verify_proof(&self) -> bool {
+    ///Verifies the zk-SNARKS against the trusted verification key of the circuit.
+    pub fn verify_proof(
+        &self,
+        report: &mut VerificationReport,
+        key_store: &VerifierKeyStore,
+        circuit: &Circuit,
+    ) {
         match &self.ark_circom_full_proof {
-            Some(proof) => {
-                let result = match proof.verify() {
-                    Ok(res) => res,
-                    Err(_) => false,
-                };
-                
-                result
-            }
-            None => false,
+            Some(proof) => match key_store.verify(circuit.name, proof) {
+                Ok(res) => report.push("proof", res, None),
+                Err(err) => report.push("proof", false, Some(err.to_string())),
+            },
+            None => report.push("proof", false, None),
         }
     }
 
+    ///Checks the issuer of the credential against the trusted issuers at the current time.
+    /// Relies on the link back computed in the circuit from the public key of the issuer,
+    ///  thus only checked once all previous checks of the report, including the proof, passed.
+    fn verify_issuer(
+        &self,
+        report: &mut VerificationReport,
+        trusted_issuers: &TrustedIssuers,
+        circuit: &Circuit,
+    ) {
+        if !report.is_valid() {
+            report.push("issuer", false, Some("Presentation not verified".to_owned()));
+            return;
+        }
+
+        let layout = &circuit.layout;
+        let issuer = match (now(), self.output(layout.challenge), self.output(layout.link_back)) {
+            (Err(_), _, _) => IssuerVerification::ClockUnavailable,
+            (Ok(now), Some(challenge), Some(link_back)) => trusted_issuers.check::<H>(
+                &self.public_signals.meta_type,
+                challenge,
+                link_back,
+                now,
+            ),
+            _ => IssuerVerification::MissingProof,
+        };
+        report.push("issuer", issuer.is_trusted(), Some(format!("{:?}", issuer)));
+        report.issuer = Some(issuer);
+    }
+
+    ///Checks that the revocation root of the ZKP is a fresh root signed by the issuer.
+    ///
+    /// # Arguments
+    /// * 'signed_root' - The root signed by the issuer, either published on its own
+    ///                     or taken from a signed registry snapshot.
+    ///
+    /// * 'max_age' - The maximum age of the signed root in milliseconds.
+    ///
+    /// # Errors
+    ///
+    /// The function fails, should the presentation lack a proof, the roots differ
+    ///  or the signed root not be fresh and signed by the issuer.
+    pub fn verify_revocation_root(
+        &self,
+        signed_root: &SignedRevocationRoot<S>,
+        pk_issuer: &[BigInt; 2],
+        max_age: u128,
+        circuit: &Circuit,
+    ) -> Result<(), HeimdallError> {
+        let root = self.output(circuit.layout.revocation_root).ok_or_else(|| {
+            HeimdallError::InvalidInput("Presentation lacks the revocation root of a proof.".to_owned())
+        })?;
+        if &signed_root.root != root || signed_root.root != self.public_signals.revocation_root {
+            Err(HeimdallError::SignatureInvalid(
+                "Signed root does not match the revocation root of the presentation.".to_owned(),
+            ))?
+        }
+        signed_root.verify::<H>(pk_issuer, max_age)
+    }
+
+    ///Creates a key store trusting the verification key of the generated proof.
+    /// Only meant for the self-check of the prover after generation.
+    fn generated_key_store(&self, circuit: &Circuit) -> VerifierKeyStore {
+        let mut key_store = VerifierKeyStore::new();
+        if let Some(proof) = &self.ark_circom_full_proof {
+            key_store.insert(circuit.name, proof.verification_key.clone());
+        }
+        key_store
+    }
+
     ///Generates a json of the  meta-inputs.
     ///Does not include the closing brace.
-    fn generate_input_json(&self) -> Result<String, Box<dyn Error>> {
+    fn generate_input_json(&self) -> Result<String, HeimdallError> {
         let private_inputs = match &self.private_inputs {
             Some(res) => res,
-            None => Err("Private inputs have already been consumed!")?,
+            None => Err(HeimdallError::InvalidInput(
+                "Private inputs have already been consumed!".to_owned(),
+            ))?,
         };
 
         let mut json = String::new();
@@ -340,15 +455,7 @@ impl<H: HashFunction, S: SignatureFunction> Presentation<H, S> {
         }
 
         json.push_str(&format!("\"issuerPK\":"));
-        append_json_vector(
-            &mut json,
-            &self
-                .credential
-                .as_ref()
-                .unwrap()
-                .signature
-                .public_key_signer,
-        );
+        append_json_vector(&mut json, &self.credential()?.signature.public_key_signer);
 
         //
         // public inputs
@@ -375,15 +482,15 @@ impl<H: HashFunction, S: SignatureFunction> Presentation<H, S> {
 
 impl<H: HashFunction, S: SignatureFunction> AttributePresentation<H, S> {
     ///Constructor for initializing a attribute presentation.
-    pub fn new(
+    pub fn new<R: RevocationScheme<H, Witness = BitVectorWitness<H>>>(
         cred: &Credential<H, S>,
         expiration_date: BigInt,
-        revocation_registry: &RevocationRegistry<H, S>,
+        revocation_registry: &R,
         sk_holder: Option<BigInt>, //mandatory for basic Attribute Presentation
         pk_issuer: Option<[BigInt; 2]>,
         challenge: BigInt,
         indizes: Vec<usize>,
-    ) -> Result<Self, Box<dyn Error>> {
+    ) -> Result<Self, HeimdallError> {
         let presentation = Presentation::<H, S>::new(
             cred,
             BigInt::from(expiration_date),
@@ -391,12 +498,18 @@ impl<H: HashFunction, S: SignatureFunction> AttributePresentation<H, S> {
             challenge,
             sk_holder,
             pk_issuer,
-        );
+        )?;
 
         let mut to_publish = vec![BigInt::from(0); cred.attributes.len()];
         let mut content = Vec::<String>::new();
 
         for i in indizes {
+            if i >= cred.attributes.len() {
+                Err(HeimdallError::IndexOutOfRange {
+                    index: i,
+                    len: cred.attributes.len(),
+                })?
+            }
             to_publish[i] = BigInt::from(1);
             content.push(cred.attributes[i].clone());
         }
@@ -411,29 +524,51 @@ impl<H: HashFunction, S: SignatureFunction> AttributePresentation<H, S> {
 
 impl<H: HashFunction, S: SignatureFunction> ContentPresentation for AttributePresentation<H, S> where AttributePresentation<H, S> :Serialize {
     ///Verifies the presentation.
-    fn verify(&self) -> bool {
-        let mut res = self.presentation.verify_proof();
+    fn verify(&self, key_store: &VerifierKeyStore) -> VerificationReport {
+        let mut report = VerificationReport::new();
 
-        res = res && self.presentation.verify_meta_data(0, 1, 2, 3, 4, 5, 6, 7);
+        self.presentation
+            .verify_proof(&mut report, key_store, &attribute::CIRCUIT);
+        self.presentation
+            .verify_meta_data(&mut report, &attribute::CIRCUIT);
 
         let hash_fn = H::new();
 
-        let proof = self.presentation.ark_circom_full_proof.as_ref().unwrap();
-
         let mut count: usize = 0;
-        for i in 8..self.to_publish.len() {
-            if self.to_publish[i].is_one() {
-                res = res && hash_fn.hash_str(&self.content[count]) == proof.outputs[8 + i];
-                count += 1;
+        for i in 0..self.to_publish.len() {
+            if !self.to_publish[i].is_one() {
+                continue;
+            }
+            if i >= META_SIZE {
+                let name = format!("attribute {}", i);
+                match self.content.get(count) {
+                    Some(content) => report.check_result(
+                        &name,
+                        hash_fn.hash_str(content),
+                        self.presentation
+                            .output(attribute::CIRCUIT.layout.content + i)
+                            .cloned(),
+                    ),
+                    None => report.push(&name, false, None),
+                }
             }
+            count += 1;
         }
 
-        res
+        report
+    }
+
+    ///Verifies the presentation and checks that the credential was signed by a trusted issuer.
+    fn verify_with_issuers(&self, key_store: &VerifierKeyStore, trusted_issuers: &TrustedIssuers) -> VerificationReport {
+        let mut report = self.verify(key_store);
+        self.presentation
+            .verify_issuer(&mut report, trusted_issuers, &attribute::CIRCUIT);
+        report
     }
 
     ///Generates a json of the content inputs.
     ///Does not include the closing brace.
-    fn generate_input_json(&self) -> Result<String, Box<dyn Error>> {
+    fn generate_input_json(&self) -> Result<String, HeimdallError> {
         match self.presentation.generate_input_json() {
             Ok(mut json) => {
                 json.push_str(&format!(",\"toPublish\":"));
@@ -447,13 +582,16 @@ impl<H: HashFunction, S: SignatureFunction> ContentPresentation for AttributePre
         }
     }
 
+    ///Retrieves the descriptor of the attribute presentation circuit.
+    fn circuit(&self) -> &'static Circuit {
+        &attribute::CIRCUIT
+    }
+
     ///Generates the presentation.
     /// Removes the private data afterwards.
-    fn generate(
-        &mut self,
-        circuit: Option<&Vec<u8>>,
-        zkey: Option<&Vec<u8>>,
-    ) -> Result<(), Box<dyn Error>> {
+    fn generate_with_prover(&mut self, prover: &CircuitProver) -> Result<(), HeimdallError> {
+        prover.ensure_circuit(self.circuit())?;
+
         let input_json = match self.generate_input_json() {
             Ok(mut input_json) => {
                 input_json.push('}');
@@ -464,13 +602,13 @@ impl<H: HashFunction, S: SignatureFunction> ContentPresentation for AttributePre
         };
 
         self.presentation.ark_circom_full_proof =
-            match proof_attribute_presentation(&input_json, circuit, zkey) {
+            match prover.prove(&input_json) {
                 Ok(proof) => Some(proof),
                 Err(err) => Err(err)?,
             };
 
-        if !self.verify() {
-            Err("Proof invalid!")?
+        if !self.verify(&self.presentation.generated_key_store(&attribute::CIRCUIT)).is_valid() {
+            Err(HeimdallError::PresentationInvalid)?
         }
 
         self.remove_private_data();
@@ -487,17 +625,24 @@ impl<H: HashFunction, S: SignatureFunction> ContentPresentation for AttributePre
 impl<H: HashFunction, S: SignatureFunction> RangePresentation<H, S> {
 
     ///Constructor for initializing a range presentation.
-    pub fn new(
+    pub fn new<R: RevocationScheme<H, Witness = BitVectorWitness<H>>>(
         cred: &Credential<H, S>,
         expiration_date: BigInt,
-        revocation_registry: &RevocationRegistry<H, S>,
+        revocation_registry: &R,
         sk_holder: Option<BigInt>,
         pk_issuer: Option<[BigInt; 2]>,
         challenge: BigInt,
         index: usize,
         lower_bound: BigInt,
         upper_bound: BigInt,
-    ) -> Result<Self, Box<dyn Error>> {
+    ) -> Result<Self, HeimdallError> {
+        if index >= cred.attributes.len() {
+            Err(HeimdallError::IndexOutOfRange {
+                index,
+                len: cred.attributes.len(),
+            })?
+        }
+
         let presentation = Presentation::<H, S>::new(
             cred,
             BigInt::from(expiration_date),
@@ -505,7 +650,7 @@ impl<H: HashFunction, S: SignatureFunction> RangePresentation<H, S> {
             challenge,
             sk_holder,
             pk_issuer,
-        );
+        )?;
 
         Ok(RangePresentation {
             presentation,
@@ -520,32 +665,55 @@ impl<H: HashFunction, S: SignatureFunction> RangePresentation<H, S> {
 impl<H: HashFunction, S: SignatureFunction> ContentPresentation for RangePresentation<H, S> where RangePresentation<H,S>: Serialize{
 
     ///Verifies the presentation.
-    fn verify(&self) -> bool {
-        let mut res = self.presentation.verify_proof();
-
-        res = res && self.presentation.verify_meta_data(0, 1, 2, 3, 4, 5, 6, 7);
-
-        let proof = self.presentation.ark_circom_full_proof.as_ref().unwrap();
-
-        res = res && self.lower_bound == proof.outputs[8];
-        res = res && self.upper_bound == proof.outputs[9];
-        res = res && self.in_bound == proof.outputs[10].is_one();
+    fn verify(&self, key_store: &VerifierKeyStore) -> VerificationReport {
+        let mut report = VerificationReport::new();
+
+        self.presentation
+            .verify_proof(&mut report, key_store, &range::CIRCUIT);
+        self.presentation
+            .verify_meta_data(&mut report, &range::CIRCUIT);
+
+        let content = range::CIRCUIT.layout.content;
+        report.check(
+            "lower bound",
+            self.lower_bound.clone(),
+            self.presentation.output(content).cloned(),
+        );
+        report.check(
+            "upper bound",
+            self.upper_bound.clone(),
+            self.presentation.output(content + 1).cloned(),
+        );
+        report.check(
+            "in bound",
+            self.in_bound,
+            self.presentation.output(content + 2).map(|x| x.is_one()),
+        );
 
-        let position = &proof.outputs[11..proof.outputs.len()]
-            .iter()
-            .position(|x| x.is_one());
+        let position = self
+            .presentation
+            .outputs()
+            .get(content + 3..)
+            .and_then(|outputs| outputs.iter().position(|x| x.is_one()));
+        report.check("index", self.index, position);
 
-        res = res && self.index == position.unwrap();
+        report
+    }
 
-        res
+    ///Verifies the presentation and checks that the credential was signed by a trusted issuer.
+    fn verify_with_issuers(&self, key_store: &VerifierKeyStore, trusted_issuers: &TrustedIssuers) -> VerificationReport {
+        let mut report = self.verify(key_store);
+        self.presentation
+            .verify_issuer(&mut report, trusted_issuers, &range::CIRCUIT);
+        report
     }
 
     ///Generates a json of the content inputs.
     ///Does not include the closing brace.
-    fn generate_input_json(&self) -> Result<String, Box<dyn Error>> {
+    fn generate_input_json(&self) -> Result<String, HeimdallError> {
         match self.presentation.generate_input_json() {
             Ok(mut json) => {
-                let cred = self.presentation.credential.as_ref().unwrap();
+                let cred = self.presentation.credential()?;
                 let mut index_vec = vec![BigInt::from(0); cred.attributes.len()];
                 index_vec[self.index] = BigInt::from(1);
                 json.push_str(&format!(",\"index\":"));
@@ -560,13 +728,16 @@ impl<H: HashFunction, S: SignatureFunction> ContentPresentation for RangePresent
         }
     }
 
+    ///Retrieves the descriptor of the range presentation circuit.
+    fn circuit(&self) -> &'static Circuit {
+        &range::CIRCUIT
+    }
+
     ///Generates the presentation.
     /// Removes the private data afterwards.
-    fn generate(
-        &mut self,
-        circuit: Option<&Vec<u8>>,
-        zkey: Option<&Vec<u8>>,
-    ) -> Result<(), Box<dyn Error>> {
+    fn generate_with_prover(&mut self, prover: &CircuitProver) -> Result<(), HeimdallError> {
+        prover.ensure_circuit(self.circuit())?;
+
         let input_json = match self.generate_input_json() {
             Ok(mut input_json) => {
                 input_json.push('}');
@@ -576,16 +747,19 @@ impl<H: HashFunction, S: SignatureFunction> ContentPresentation for RangePresent
         };
 
         self.presentation.ark_circom_full_proof =
-            match proof_range_presentation(&input_json, circuit, zkey) {
+            match prover.prove(&input_json) {
                 Ok(proof) => {
-                    self.in_bound = proof.outputs[10].is_one();
+                    self.in_bound = proof
+                        .outputs
+                        .get(range::CIRCUIT.layout.content + 2)
+                        .map_or(false, |x| x.is_one());
                     Some(proof)
                 }
                 Err(err) => Err(err)?,
             };
 
-        if !self.verify() {
-            Err("Proof invalid!")?
+        if !self.verify(&self.presentation.generated_key_store(&range::CIRCUIT)).is_valid() {
+            Err(HeimdallError::PresentationInvalid)?
         }
 
         self.remove_private_data();
@@ -602,17 +776,31 @@ impl<H: HashFunction, S: SignatureFunction> ContentPresentation for RangePresent
 impl<H: HashFunction, S: SignatureFunction> PolygonPresentation<H, S> {
 
     ///Constructor for initializing a polygon presentation.
-    pub fn new(
+    pub fn new<R: RevocationScheme<H, Witness = BitVectorWitness<H>>>(
         cred: &Credential<H, S>,
         expiration_date: BigInt,
-        revocation_registry: &RevocationRegistry<H, S>,
+        revocation_registry: &R,
         sk_holder: Option<BigInt>,
         pk_issuer: Option<[BigInt; 2]>,
         challenge: BigInt,
         index: usize,
         mut vert_x: Vec<BigInt>,
         mut vert_y: Vec<BigInt>,
-    ) -> Result<Self, Box<dyn Error>> {
+    ) -> Result<Self, HeimdallError> {
+        //the location spans the attributes at 'index' and 'index + 1'
+        if index + 1 >= cred.attributes.len() {
+            Err(HeimdallError::IndexOutOfRange {
+                index: index + 1,
+                len: cred.attributes.len(),
+            })?
+        }
+        if vert_x.is_empty() || vert_x.len() != vert_y.len() || vert_x.len() > MAX_POLYGON_SIZE {
+            Err(HeimdallError::InvalidInput(format!(
+                "Polygon requires between 1 and {} vertices of matching coordinates",
+                MAX_POLYGON_SIZE
+            )))?
+        }
+
         let presentation = Presentation::<H, S>::new(
             cred,
             BigInt::from(expiration_date),
@@ -620,10 +808,10 @@ impl<H: HashFunction, S: SignatureFunction> PolygonPresentation<H, S> {
             challenge,
             sk_holder,
             pk_issuer,
-        );
+        )?;
         let x_fill = vert_x[vert_x.len() - 1].clone();
         let y_fill = vert_y[vert_y.len() - 1].clone();
-        for _i in vert_x.len()..50 {
+        for _i in vert_x.len()..MAX_POLYGON_SIZE {
             vert_x.push(x_fill.clone());
             vert_y.push(y_fill.clone());
         }
@@ -631,8 +819,8 @@ impl<H: HashFunction, S: SignatureFunction> PolygonPresentation<H, S> {
         Ok(PolygonPresentation {
             presentation,
             index: index,
-            vert_x: vert_x.try_into().unwrap(),
-            vert_y: vert_y.try_into().unwrap(),
+            vert_x: vert_x.try_into().expect("polygon filled to its maximum size"),
+            vert_y: vert_y.try_into().expect("polygon filled to its maximum size"),
             location: Some([
                 cred.attributes[index].clone(),
                 cred.attributes[index + 1].clone(),
@@ -645,53 +833,74 @@ impl<H: HashFunction, S: SignatureFunction> PolygonPresentation<H, S> {
 impl<H: HashFunction, S: SignatureFunction> ContentPresentation for PolygonPresentation<H, S> where PolygonPresentation<H,S>: Serialize{
 
     ///Verifies the presentation.
-    fn verify(&self) -> bool {
-        let mut res = self.presentation.verify_proof();
-
-        res = res && self.presentation.verify_meta_data(0, 1, 2, 3, 4, 5, 6, 7);
-
-        let proof = self.presentation.ark_circom_full_proof.as_ref().unwrap();
-
-        for i in 0..50 {
-            res = res && self.vert_x[i] == proof.outputs[45 + i];
-            res = res && self.vert_y[i] == proof.outputs[45 + 50 + i];
-        }
+    fn verify(&self, key_store: &VerifierKeyStore) -> VerificationReport {
+        let mut report = VerificationReport::new();
+
+        self.presentation
+            .verify_proof(&mut report, key_store, &polygon::CIRCUIT);
+        self.presentation
+            .verify_meta_data(&mut report, &polygon::CIRCUIT);
+
+        let layout = match polygon::CIRCUIT.layout.polygon {
+            Some(layout) => layout,
+            None => {
+                report.push("layout", false, None);
+                return report;
+            }
+        };
+        let outputs = self.presentation.outputs();
+
+        report.check(
+            "vert x",
+            self.vert_x.to_vec(),
+            outputs
+                .get(layout.vert_x..layout.vert_x + MAX_POLYGON_SIZE)
+                .map(|x| x.to_vec()),
+        );
+        report.check(
+            "vert y",
+            self.vert_y.to_vec(),
+            outputs
+                .get(layout.vert_y..layout.vert_y + MAX_POLYGON_SIZE)
+                .map(|x| x.to_vec()),
+        );
+        report.check(
+            "in bound",
+            self.in_bound,
+            self.presentation.output(layout.in_bound).map(|x| x.is_one()),
+        );
 
-        res = res && self.in_bound == proof.outputs[8].is_one();
+        let position = outputs
+            .get(layout.index..layout.vert_x)
+            .and_then(|outputs| outputs.iter().position(|x| x.is_one()));
+        report.check("index", self.index, position);
 
-        let position = &proof.outputs[9..self
-            .presentation
-            .credential
-            .as_ref()
-            .unwrap()
-            .attributes
-            .len()]
-            .iter()
-            .position(|x| x.is_one());
+        report
+    }
 
-        res = res && position.is_some() && self.index == position.unwrap();
-        res
+    ///Verifies the presentation and checks that the credential was signed by a trusted issuer.
+    fn verify_with_issuers(&self, key_store: &VerifierKeyStore, trusted_issuers: &TrustedIssuers) -> VerificationReport {
+        let mut report = self.verify(key_store);
+        self.presentation
+            .verify_issuer(&mut report, trusted_issuers, &polygon::CIRCUIT);
+        report
     }
 
     ///Generates a json of the content inputs.
     ///Does not include the closing brace.
-    fn generate_input_json(&self) -> Result<String, Box<dyn Error>> {
+    fn generate_input_json(&self) -> Result<String, HeimdallError> {
         match self.presentation.generate_input_json() {
             Ok(mut json) => {
-                let mut index_vec = vec![
-                    BigInt::from(0);
-                    self.presentation
-                        .credential
-                        .as_ref()
-                        .unwrap()
-                        .attributes
-                        .len()
-                ];
+                let mut index_vec =
+                    vec![BigInt::from(0); self.presentation.credential()?.attributes.len()];
                 index_vec[self.index] = BigInt::from(1);
                 json.push_str(&format!(",\"index\":"));
                 append_json_vector(&mut json, &index_vec);
                 json.push_str(&format!("\"location\":"));
-                append_json_vector(&mut json, self.location.as_ref().unwrap());
+                let location = self.location.as_ref().ok_or_else(|| {
+                    HeimdallError::InvalidInput("Location has already been consumed!".to_owned())
+                })?;
+                append_json_vector(&mut json, location);
                 json.push_str(&format!("\"vertx\":"));
                 append_json_vector(&mut json, &self.vert_x);
                 json.push_str(&format!("\"verty\":"));
@@ -703,13 +912,16 @@ impl<H: HashFunction, S: SignatureFunction> ContentPresentation for PolygonPrese
         }
     }
 
+    ///Retrieves the descriptor of the polygon presentation circuit.
+    fn circuit(&self) -> &'static Circuit {
+        &polygon::CIRCUIT
+    }
+
     ///Generates the presentation.
     /// Removes the private data afterwards.
-    fn generate(
-        &mut self,
-        circuit: Option<&Vec<u8>>,
-        zkey: Option<&Vec<u8>>,
-    ) -> Result<(), Box<dyn Error>> {
+    fn generate_with_prover(&mut self, prover: &CircuitProver) -> Result<(), HeimdallError> {
+        prover.ensure_circuit(self.circuit())?;
+
         let input_json = match self.generate_input_json() {
             Ok(mut input_json) => {
                 input_json.push('}');
@@ -719,16 +931,20 @@ impl<H: HashFunction, S: SignatureFunction> ContentPresentation for PolygonPrese
         };
 
         self.presentation.ark_circom_full_proof =
-            match proof_polygon_presentation(&input_json, circuit, zkey) {
+            match prover.prove(&input_json) {
                 Ok(proof) => {
-                    self.in_bound = proof.outputs[8].is_one();
+                    self.in_bound = polygon::CIRCUIT
+                        .layout
+                        .polygon
+                        .and_then(|layout| proof.outputs.get(layout.in_bound))
+                        .is_some_and(|x| x.is_one());
                     Some(proof)
                 }
                 Err(err) => Err(err)?,
             };
 
-        if !self.verify() {
-            Err("Proof invalid!")?
+        if !self.verify(&self.presentation.generated_key_store(&polygon::CIRCUIT)).is_valid() {
+            Err(HeimdallError::PresentationInvalid)?
         }
 
         Ok(())
@@ -743,31 +959,31 @@ impl<H: HashFunction, S: SignatureFunction> ContentPresentation for PolygonPrese
 
 
 impl<H: HashFunction, S: SignatureFunction> DelegationPresentation<H, S> {
-    pub fn new(
+    pub fn new<R: RevocationScheme<H, Witness = BitVectorWitness<H>>>(
         cred: &Credential<H, S>,
         expiration_date: BigInt,
-        revocation_registry: &RevocationRegistry<H, S>,
+        revocation_registry: &R,
         pk_issuer: Option<[BigInt; 2]>,
         challenge: BigInt,
         indizes: Vec<usize>,
-    ) -> Result<Self, Box<dyn Error>> {
-        let hash_fn = H::new();
-        let link_forth = hash_fn.hash(&vec![
-            challenge.to_string(),
-            cred.attributes[2].to_string(),
-            cred.attributes[3].to_string(),
-        ]);
-
+    ) -> Result<Self, HeimdallError> {
         let attribute_presentation = AttributePresentation::new(
             cred,
             expiration_date,
             revocation_registry,
             None,
             pk_issuer,
-            challenge,
+            challenge.clone(),
             indizes,
         )?;
 
+        let hash_fn = H::new();
+        let link_forth = hash_fn.hash(&vec![
+            challenge.to_string(),
+            cred.attributes[2].to_string(),
+            cred.attributes[3].to_string(),
+        ])?;
+
         Ok(DelegationPresentation {
             attribute_presentation,
             link_forth,
@@ -780,38 +996,51 @@ impl<H: HashFunction, S: SignatureFunction> ContentPresentation for DelegationPr
     
 
     ///Verifies the presentation.
-    fn verify(&self) -> bool {
-        let mut res = self.attribute_presentation.presentation.verify_proof();
-
-        res = res
-            && self.link_forth
-                == self
-                    .attribute_presentation
-                    .presentation
-                    .ark_circom_full_proof
-                    .as_ref()
-                    .unwrap()
-                    .outputs[8];
-
-        res
+    fn verify(&self, key_store: &VerifierKeyStore) -> VerificationReport {
+        let mut report = VerificationReport::new();
+        let presentation = &self.attribute_presentation.presentation;
+
+        presentation.verify_proof(&mut report, key_store, &delegation::CIRCUIT);
+        presentation.verify_meta_data(&mut report, &delegation::CIRCUIT);
+        report.check(
+            "link forth",
+            self.link_forth.clone(),
+            presentation
+                .output(delegation::CIRCUIT.layout.content)
+                .cloned(),
+        );
+
+        report
+    }
+
+    ///Verifies the presentation and checks that the credential was signed by a trusted issuer.
+    fn verify_with_issuers(&self, key_store: &VerifierKeyStore, trusted_issuers: &TrustedIssuers) -> VerificationReport {
+        let mut report = self.verify(key_store);
+        self.attribute_presentation
+            .presentation
+            .verify_issuer(&mut report, trusted_issuers, &delegation::CIRCUIT);
+        report
     }
 
     ///Generates a json of the content inputs.
     ///Does not include the closing brace.
-    fn generate_input_json(&self) -> Result<String, Box<dyn Error>> {
+    fn generate_input_json(&self) -> Result<String, HeimdallError> {
         match self.attribute_presentation.generate_input_json() {
             Ok(json) => Ok(json),
             Err(er) => Err(er),
         }
     }
 
+    ///Retrieves the descriptor of the delegation presentation circuit.
+    fn circuit(&self) -> &'static Circuit {
+        &delegation::CIRCUIT
+    }
+
     ///Generates the presentation.
     /// Removes the private data afterwards.
-    fn generate(
-        &mut self,
-        circuit: Option<&Vec<u8>>,
-        zkey: Option<&Vec<u8>>,
-    ) -> Result<(), Box<dyn Error>> {
+    fn generate_with_prover(&mut self, prover: &CircuitProver) -> Result<(), HeimdallError> {
+        prover.ensure_circuit(self.circuit())?;
+
         let input_json = match self.generate_input_json() {
             Ok(mut input_json) => {
                 input_json.push('}');
@@ -822,14 +1051,18 @@ impl<H: HashFunction, S: SignatureFunction> ContentPresentation for DelegationPr
 
         self.attribute_presentation
             .presentation
-            .ark_circom_full_proof = match proof_delegation_presentation(&input_json, circuit, zkey)
+            .ark_circom_full_proof = match prover.prove(&input_json)
         {
             Ok(proof) => Some(proof),
             Err(err) => Err(err)?,
         };
 
-        if !self.verify() {
-            Err("Proof invalid!")?
+        let key_store = self
+            .attribute_presentation
+            .presentation
+            .generated_key_store(&delegation::CIRCUIT);
+        if !self.verify(&key_store).is_valid() {
+            Err(HeimdallError::PresentationInvalid)?
         }
 
         Ok(())
@@ -864,7 +1097,11 @@ mod test {
 
     use crypto::poseidon::{PoseidonHasher, PoseidonSignature};
 
-    use crate::revocation::RevocationRegistry;
+    use crate::{
+        issuer::TrustedIssuer,
+        revocation::{RevocationRegistry, RevocationRegistryReplica},
+        zkp::{load_verifier_key_store, test_prover_loader},
+    };
 
     use super::*;
 
@@ -906,10 +1143,11 @@ mod test {
             false,
             "revocRegistry",
             issuer_sk.clone(),
-        );
+        )
+        .unwrap();
 
         let revoc_reg =
-            RevocationRegistry::<PoseidonHasher, PoseidonSignature>::new(Some(issuer_sk.clone()));
+            RevocationRegistry::<PoseidonHasher, PoseidonSignature>::new(Some(issuer_sk.clone())).unwrap();
 
         let start = Instant::now();
         let mut attr_pres = AttributePresentation::<PoseidonHasher, PoseidonSignature>::new(
@@ -923,16 +1161,224 @@ mod test {
         )
         .unwrap();
 
-        attr_pres.generate(None, None).unwrap();
+        attr_pres.generate(&test_prover_loader()).unwrap();
         let duration = start.elapsed().as_millis();
         println!("Presentation took {} ms", duration);
 
-        assert!(attr_pres.verify());
+        let key_store = load_verifier_key_store(&test_prover_loader()).unwrap();
+        assert!(attr_pres.verify(&key_store).is_valid());
+
+        let mut trusted_issuers = TrustedIssuers::new();
+        let report = attr_pres.verify_with_issuers(&key_store, &trusted_issuers);
+        assert!(!report.is_valid());
+        assert!(matches!(report.issuer, Some(IssuerVerification::UnknownCredentialType { .. })));
+        trusted_issuers.insert(
+            "Identity",
+            TrustedIssuer {
+                name: "issuer".to_owned(),
+                public_key: PoseidonSignature::get_public_keys(issuer_sk.clone()).unwrap(),
+                valid_from: 0,
+                valid_until: None,
+            },
+        );
+        let report = attr_pres.verify_with_issuers(&key_store, &trusted_issuers);
+        assert!(report.is_valid());
+        assert!(report.issuer.unwrap().is_trusted());
+        //the issuer is not checked without a verified proof
+        let report = attr_pres.verify_with_issuers(&VerifierKeyStore::new(), &trusted_issuers);
+        assert!(report.issuer.is_none());
+        assert!(report.failures().iter().any(|check| check.name == "issuer"));
+
+        let issuer_pk = PoseidonSignature::get_public_keys(issuer_sk.clone()).unwrap();
+        let signed_root = revoc_reg.signed_root.as_ref().unwrap();
+        assert!(attr_pres
+            .presentation
+            .verify_revocation_root(signed_root, &issuer_pk, 60 * 60 * 1000, &attribute::CIRCUIT)
+            .is_ok());
+        let other_root = SignedRevocationRoot::new::<PoseidonHasher>(BigInt::from(0), issuer_sk.clone()).unwrap();
+        assert!(matches!(
+            attr_pres
+                .presentation
+                .verify_revocation_root(&other_root, &issuer_pk, 60 * 60 * 1000, &attribute::CIRCUIT),
+            Err(HeimdallError::SignatureInvalid(_))
+        ));
+        let other_pk = PoseidonSignature::get_public_keys(BigInt::from(42)).unwrap();
+        assert!(matches!(
+            attr_pres
+                .presentation
+                .verify_revocation_root(signed_root, &other_pk, 60 * 60 * 1000, &attribute::CIRCUIT),
+            Err(HeimdallError::SignatureInvalid(_))
+        ));
 
         let attribute_presentation_json = serde_json::to_string(&attr_pres).unwrap();
-        let attribute_presentation: AttributePresentation<PoseidonHasher, PoseidonSignature> =
+        let mut attribute_presentation: AttributePresentation<PoseidonHasher, PoseidonSignature> =
          serde_json::from_str(&attribute_presentation_json).unwrap();
-        assert!(attribute_presentation.verify())
+        assert!(attribute_presentation.verify(&key_store).is_valid());
+
+        //a proof shipping a key other than the pinned one is rejected
+        attribute_presentation
+            .presentation
+            .ark_circom_full_proof
+            .as_mut()
+            .unwrap()
+            .verification_key = key_store.get(range::CIRCUIT.name).unwrap().clone();
+        assert!(!attribute_presentation.verify(&key_store).is_valid());
+        assert!(!attr_pres.verify(&VerifierKeyStore::new()).is_valid());
+    }
+
+    ///TEST: verification of a presentation without a proof
+    #[test]
+    fn presentation_without_proof() {
+        let issuer_sk =
+            BigInt::from_str("2951225162891973271265230278305932248884420185229553697262927872")
+                .unwrap();
+
+        let cred = Credential::<PoseidonHasher, PoseidonSignature>::new(
+            &vec!["John".to_owned(), "Jones".to_owned()],
+            255 as u64,
+            &PoseidonSignature::get_public_keys(issuer_sk.clone()).unwrap(),
+            365,
+            "Identity",
+            false,
+            "revocRegistry",
+            issuer_sk.clone(),
+        )
+        .unwrap();
+
+        let revoc_reg =
+            RevocationRegistry::<PoseidonHasher, PoseidonSignature>::new(Some(issuer_sk.clone())).unwrap();
+
+        let attr_pres = AttributePresentation::<PoseidonHasher, PoseidonSignature>::new(
+            &cred,
+            BigInt::from_str("1678460108000").unwrap(),
+            &revoc_reg,
+            None,
+            None,
+            BigInt::from(1234),
+            vec![8, 9],
+        )
+        .unwrap();
+
+        let report = attr_pres.verify(&VerifierKeyStore::new());
+        assert!(!report.is_valid());
+        assert!(report
+            .failures()
+            .iter()
+            .any(|check| check.name == "proof" && check.actual.is_none()));
+        assert!(report
+            .failures()
+            .iter()
+            .any(|check| check.name == "attribute 8"));
+
+        //the issuer of an unproven presentation is not trusted, even if listed
+        let mut trusted_issuers = TrustedIssuers::new();
+        trusted_issuers.insert(
+            "Identity",
+            TrustedIssuer {
+                name: "issuer".to_owned(),
+                public_key: PoseidonSignature::get_public_keys(issuer_sk.clone()).unwrap(),
+                valid_from: 0,
+                valid_until: None,
+            },
+        );
+        let report = attr_pres.verify_with_issuers(&VerifierKeyStore::new(), &trusted_issuers);
+        assert!(report.issuer.is_none());
+        assert!(report
+            .failures()
+            .iter()
+            .any(|check| check.name == "issuer"));
+
+        //delegations check the meta signals as well
+        let del_pres = DelegationPresentation::<PoseidonHasher, PoseidonSignature>::new(
+            &cred,
+            BigInt::from_str("1678460108000").unwrap(),
+            &revoc_reg,
+            None,
+            BigInt::from(1234),
+            vec![8],
+        )
+        .unwrap();
+        let report = del_pres.verify(&VerifierKeyStore::new());
+        for name in ["type", "revocation root", "revocation registry", "revoked", "delegatable", "challenge", "expiration date", "link forth"] {
+            assert!(report.failures().iter().any(|check| check.name == name));
+        }
+
+        assert!(matches!(
+            AttributePresentation::<PoseidonHasher, PoseidonSignature>::new(
+                &cred,
+                BigInt::from_str("1678460108000").unwrap(),
+                &revoc_reg,
+                None,
+                None,
+                BigInt::from(1234),
+                vec![cred.attributes.len()],
+            ),
+            Err(HeimdallError::IndexOutOfRange { .. })
+        ));
+    }
+
+    ///TEST: revocation status of the credential id
+    #[test]
+    fn presentation_revocation_status() {
+        let issuer_sk =
+            BigInt::from_str("2951225162891973271265230278305932248884420185229553697262927872")
+                .unwrap();
+        let issuer_pk = PoseidonSignature::get_public_keys(issuer_sk.clone()).unwrap();
+
+        let cred = Credential::<PoseidonHasher, PoseidonSignature>::new(
+            &vec!["John".to_owned(), "Jones".to_owned()],
+            255 as u64,
+            &issuer_pk,
+            365,
+            "Identity",
+            false,
+            "revocRegistry",
+            issuer_sk.clone(),
+        )
+        .unwrap();
+        let present = |revoc_reg: &RevocationRegistry<PoseidonHasher, PoseidonSignature>| {
+            AttributePresentation::<PoseidonHasher, PoseidonSignature>::new(
+                &cred,
+                BigInt::from_str("1678460108000").unwrap(),
+                revoc_reg,
+                None,
+                None,
+                BigInt::from(1234),
+                vec![8],
+            )
+            .unwrap()
+        };
+
+        //id 255 shares the leaf 1 with other ids, but is not id 1
+        let mut revoc_reg =
+            RevocationRegistry::<PoseidonHasher, PoseidonSignature>::new(None).unwrap();
+        revoc_reg.revoke(1, "", None).unwrap();
+        revoc_reg.revoke(254, "", Some(issuer_sk.clone())).unwrap();
+        assert!(!present(&revoc_reg).presentation.public_signals.revoked);
+
+        revoc_reg.revoke(255, "", Some(issuer_sk.clone())).unwrap();
+        let attr_pres = present(&revoc_reg);
+        assert!(attr_pres.presentation.public_signals.revoked);
+        let private_inputs = attr_pres.presentation.private_inputs.as_ref().unwrap();
+        assert_eq!(private_inputs.proof_revocation.path[0], 1);
+        assert_eq!(private_inputs.proof_revocation.lemma.last(), Some(revoc_reg.root()));
+
+        //holders generate the presentation from their replica
+        let mut replica =
+            RevocationRegistryReplica::<PoseidonHasher, PoseidonSignature>::new(issuer_pk).unwrap();
+        replica.apply(&revoc_reg.delta(0, issuer_sk.clone()).unwrap()).unwrap();
+        let attr_pres = AttributePresentation::<PoseidonHasher, PoseidonSignature>::new(
+            &cred,
+            BigInt::from_str("1678460108000").unwrap(),
+            &replica,
+            None,
+            None,
+            BigInt::from(1234),
+            vec![8],
+        )
+        .unwrap();
+        assert!(attr_pres.presentation.public_signals.revoked);
+        assert_eq!(&attr_pres.presentation.public_signals.revocation_root, revoc_reg.root());
     }
 
     ///TEST: range presentation
@@ -972,10 +1418,11 @@ mod test {
             false,
             "revocRegistry",
             issuer_sk.clone(),
-        );
+        )
+        .unwrap();
 
         let revoc_reg =
-            RevocationRegistry::<PoseidonHasher, PoseidonSignature>::new(Some(issuer_sk.clone()));
+            RevocationRegistry::<PoseidonHasher, PoseidonSignature>::new(Some(issuer_sk.clone())).unwrap();
 
         let start = Instant::now();
         let mut range_pres = RangePresentation::<PoseidonHasher, PoseidonSignature>::new(
@@ -991,12 +1438,12 @@ mod test {
         )
         .unwrap();
 
-        range_pres.generate(None, None).unwrap();
+        range_pres.generate(&test_prover_loader()).unwrap();
 
         let duration = start.elapsed().as_millis();
         println!("Presentation took {} ms", duration);
 
-        assert!(range_pres.verify());
+        assert!(range_pres.verify(&load_verifier_key_store(&test_prover_loader()).unwrap()).is_valid());
     }
 
     ///TEST: polygon presentation
@@ -1036,10 +1483,11 @@ mod test {
             false,
             "revocRegistry",
             issuer_sk.clone(),
-        );
+        )
+        .unwrap();
 
         let revoc_reg =
-            RevocationRegistry::<PoseidonHasher, PoseidonSignature>::new(Some(issuer_sk.clone()));
+            RevocationRegistry::<PoseidonHasher, PoseidonSignature>::new(Some(issuer_sk.clone())).unwrap();
 
         let start = Instant::now();
         let mut polygon_pres = PolygonPresentation::<PoseidonHasher, PoseidonSignature>::new(
@@ -1065,11 +1513,26 @@ mod test {
         )
         .unwrap();
 
-        polygon_pres.generate(None, None).unwrap();
+        polygon_pres.generate(&test_prover_loader()).unwrap();
         let duration = start.elapsed().as_millis();
         println!("Presentation took {} ms", duration);
 
-        assert!(polygon_pres.verify());
+        assert!(polygon_pres.verify(&load_verifier_key_store(&test_prover_loader()).unwrap()).is_valid());
+    }
+
+    ///TEST: positions of the polygon outputs match the inputs of the circuit
+    #[test]
+    fn presentation_polygon_layout() {
+        let layout = polygon::CIRCUIT.layout.polygon.unwrap();
+        let input: serde_json::Value =
+            serde_json::from_str(&std::fs::read_to_string("input_polygon.json").unwrap()).unwrap();
+
+        assert_eq!(layout.in_bound, polygon::CIRCUIT.layout.content);
+        assert_eq!(layout.index, layout.in_bound + 1);
+        assert_eq!(layout.vert_x - layout.index, input["index"].as_array().unwrap().len());
+        assert_eq!(layout.vert_y - layout.vert_x, MAX_POLYGON_SIZE);
+        assert_eq!(input["vertx"].as_array().unwrap().len(), MAX_POLYGON_SIZE);
+        assert_eq!(input["verty"].as_array().unwrap().len(), MAX_POLYGON_SIZE);
     }
 
     ///TEST: delegation presentation
@@ -1106,10 +1569,11 @@ mod test {
             false,
             "revocRegistry",
             issuer_sk.clone(),
-        );
+        )
+        .unwrap();
 
         let revoc_reg =
-            RevocationRegistry::<PoseidonHasher, PoseidonSignature>::new(Some(issuer_sk.clone()));
+            RevocationRegistry::<PoseidonHasher, PoseidonSignature>::new(Some(issuer_sk.clone())).unwrap();
 
         let start = Instant::now();
         let mut del_pres = DelegationPresentation::<PoseidonHasher, PoseidonSignature>::new(
@@ -1122,7 +1586,7 @@ mod test {
         )
         .unwrap();
 
-        del_pres.generate(None, None).unwrap();
+        del_pres.generate(&test_prover_loader()).unwrap();
         let duration = start.elapsed().as_millis();
         println!("Presentation took {} ms", duration);
 
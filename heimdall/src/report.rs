@@ -0,0 +1,112 @@
+use std::fmt::{Debug, Display};
+
+use serde::{Deserialize, Serialize};
+
+use crate::issuer::IssuerVerification;
+
+///A struct representing the outcome of a single verification check.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct CheckResult {
+    pub name: String,
+    pub passed: bool,
+
+    ///The value expected by the verifier, if applicable.
+    pub expected: Option<String>,
+    ///The value found in the presentation, if present.
+    pub actual: Option<String>,
+}
+
+///A struct listing the individual checks of a presentation verification.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct VerificationReport {
+    pub checks: Vec<CheckResult>,
+
+    ///The outcome of the issuer check, present once the proof and all other checks passed.
+    #[serde(default)]
+    pub issuer: Option<IssuerVerification>,
+}
+
+impl VerificationReport {
+    ///Creates an empty report.
+    pub fn new() -> Self {
+        VerificationReport {
+            checks: Vec::new(),
+            issuer: None,
+        }
+    }
+
+    ///Records a check comparing the expected with the actual value.
+    /// Fails if the actual value is missing.
+    pub fn check<T: PartialEq + Debug>(&mut self, name: &str, expected: T, actual: Option<T>) {
+        self.checks.push(CheckResult {
+            name: name.to_owned(),
+            passed: actual.as_ref() == Some(&expected),
+            expected: Some(format!("{:?}", expected)),
+            actual: actual.map(|actual| format!("{:?}", actual)),
+        });
+    }
+
+    ///Records a check against an expected value that may have failed to compute.
+    /// Fails with the error as actual value in that case.
+    pub fn check_result<T: PartialEq + Debug, E: Display>(
+        &mut self,
+        name: &str,
+        expected: Result<T, E>,
+        actual: Option<T>,
+    ) {
+        match expected {
+            Ok(expected) => self.check(name, expected, actual),
+            Err(err) => self.push(name, false, Some(err.to_string())),
+        }
+    }
+
+    ///Records a check without comparable values.
+    pub fn push(&mut self, name: &str, passed: bool, actual: Option<String>) {
+        self.checks.push(CheckResult {
+            name: name.to_owned(),
+            passed,
+            expected: None,
+            actual,
+        });
+    }
+
+    ///Checks wether the report contains checks and all of them passed.
+    pub fn is_valid(&self) -> bool {
+        !self.checks.is_empty() && self.checks.iter().all(|check| check.passed)
+    }
+
+    ///Retrieves the failed checks.
+    pub fn failures(&self) -> Vec<&CheckResult> {
+        self.checks.iter().filter(|check| !check.passed).collect()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use num_bigint::BigInt;
+
+    use super::*;
+
+    ///TEST: verification report
+    #[test]
+    fn verification_report() {
+        let mut report = VerificationReport::new();
+        assert!(!report.is_valid());
+
+        report.check("challenge", BigInt::from(1234), Some(BigInt::from(1234)));
+        report.push("proof", true, None);
+        assert!(report.is_valid());
+
+        report.check("revoked", false, None);
+        assert!(!report.is_valid());
+
+        let failures = report.failures();
+        assert_eq!(failures.len(), 1);
+        assert_eq!(failures[0].name, "revoked");
+        assert_eq!(failures[0].expected, Some("false".to_owned()));
+        assert_eq!(failures[0].actual, None);
+
+        report.check_result("type", Err::<BigInt, _>("field overflow"), Some(BigInt::from(1)));
+        assert_eq!(report.failures()[1].actual, Some("field overflow".to_owned()));
+    }
+}
@@ -0,0 +1,286 @@
+use std::{collections::HashMap, path::PathBuf};
+
+use sha2::{Digest, Sha256};
+
+use crate::error::HeimdallError;
+
+///Default circuit location depending on the target.
+#[cfg(target_os = "android")]
+pub const DEFAULT_CIRCUITS_PATH: &str = "/data/data/com.cguthmann.vidar/circuits";
+#[cfg(not(target_os = "android"))]
+pub const DEFAULT_CIRCUITS_PATH: &str = "lib";
+
+///A trait for loading the ressources of the circuits, like the .dat and .zkey files.
+pub trait ResourceLoader {
+    ///Loads the ressource with the file name 'name'.
+    fn load(&self, name: &str) -> Result<Vec<u8>, HeimdallError>;
+
+    ///Retrieves wether the loader checks every ressource against a pinned digest itself.
+    /// The circuits check the ressources of other loaders against 'RESOURCE_DIGESTS'.
+    fn pins_digests(&self) -> bool {
+        false
+    }
+}
+
+///A struct loading ressources from a directory.
+#[derive(Debug, Clone)]
+pub struct FileSystemLoader {
+    root: PathBuf,
+}
+
+impl FileSystemLoader {
+    ///Loads the ressources from the directory 'root'.
+    pub fn new<P: Into<PathBuf>>(root: P) -> Self {
+        FileSystemLoader { root: root.into() }
+    }
+}
+
+///Loads the ressources from the default location for the system.
+/// Relative to the working directory, if not on android.
+impl Default for FileSystemLoader {
+    fn default() -> Self {
+        FileSystemLoader::new(DEFAULT_CIRCUITS_PATH)
+    }
+}
+
+impl ResourceLoader for FileSystemLoader {
+    fn load(&self, name: &str) -> Result<Vec<u8>, HeimdallError> {
+        let path = self.root.join(name);
+        std::fs::read(&path).map_err(|err| {
+            HeimdallError::MissingResource(format!("{}: {}", path.display(), err))
+        })
+    }
+}
+
+///A struct serving ressources compiled into the binary, e.g. with 'include_bytes!'.
+#[derive(Debug, Clone, Default)]
+pub struct EmbeddedLoader {
+    resources: HashMap<&'static str, &'static [u8]>,
+}
+
+impl EmbeddedLoader {
+    ///Creates a loader without ressources.
+    pub fn new() -> Self {
+        EmbeddedLoader {
+            resources: HashMap::new(),
+        }
+    }
+
+    ///Adds the embedded ressource, replacing a ressource of the same name.
+    pub fn with(mut self, name: &'static str, bytes: &'static [u8]) -> Self {
+        self.resources.insert(name, bytes);
+        self
+    }
+}
+
+impl ResourceLoader for EmbeddedLoader {
+    fn load(&self, name: &str) -> Result<Vec<u8>, HeimdallError> {
+        match self.resources.get(name) {
+            Some(bytes) => Ok(bytes.to_vec()),
+            None => Err(HeimdallError::MissingResource(name.to_owned())),
+        }
+    }
+}
+
+///A struct serving ressources held in memory, e.g. after downloading them.
+#[derive(Debug, Clone, Default)]
+pub struct MemoryLoader {
+    resources: HashMap<String, Vec<u8>>,
+}
+
+impl MemoryLoader {
+    ///Creates a loader without ressources.
+    pub fn new() -> Self {
+        MemoryLoader {
+            resources: HashMap::new(),
+        }
+    }
+
+    ///Adds the ressource, replacing a ressource of the same name.
+    pub fn insert(&mut self, name: &str, bytes: Vec<u8>) {
+        self.resources.insert(name.to_owned(), bytes);
+    }
+}
+
+impl ResourceLoader for MemoryLoader {
+    fn load(&self, name: &str) -> Result<Vec<u8>, HeimdallError> {
+        match self.resources.get(name) {
+            Some(bytes) => Ok(bytes.clone()),
+            None => Err(HeimdallError::MissingResource(name.to_owned())),
+        }
+    }
+}
+
+///A struct checking the ressources of another loader against their expected SHA-256 digests.
+///
+/// Fails for ressources without an expected digest,
+///  so no ressource is used unchecked.
+#[derive(Debug, Clone)]
+pub struct VerifiedLoader<L: ResourceLoader> {
+    loader: L,
+    digests: HashMap<String, [u8; 32]>,
+}
+
+impl<L: ResourceLoader> VerifiedLoader<L> {
+    ///Checks the ressources of 'loader'.
+    pub fn new(loader: L) -> Self {
+        VerifiedLoader {
+            loader,
+            digests: HashMap::new(),
+        }
+    }
+
+    ///Pins the expected SHA-256 digest of the ressource.
+    pub fn expect(&mut self, name: &str, digest: [u8; 32]) {
+        self.digests.insert(name.to_owned(), digest);
+    }
+
+    ///Pins the expected SHA-256 digest of the ressource, given in hex as printed by 'sha256sum'.
+    pub fn expect_hex(&mut self, name: &str, digest: &str) -> Result<(), HeimdallError> {
+        let invalid = || HeimdallError::InvalidInput(format!("Invalid SHA-256 digest {}", digest));
+        if digest.len() != 64 || !digest.is_ascii() {
+            Err(invalid())?
+        }
+
+        let mut bytes = [0u8; 32];
+        for (i, byte) in bytes.iter_mut().enumerate() {
+            *byte = u8::from_str_radix(&digest[2 * i..2 * i + 2], 16).map_err(|_| invalid())?;
+        }
+        self.expect(name, bytes);
+        Ok(())
+    }
+
+    ///Pins the expected digests listed in the format of 'sha256sum', one '<digest>  <name>' per line.
+    pub fn expect_sha256sums(&mut self, sums: &str) -> Result<(), HeimdallError> {
+        for line in sums.lines().filter(|line| !line.trim().is_empty()) {
+            match line.split_once(char::is_whitespace) {
+                //binary mode marks the name with a leading '*'
+                Some((digest, name)) => {
+                    let name = name.trim_start();
+                    self.expect_hex(name.strip_prefix('*').unwrap_or(name), digest)?
+                }
+                None => Err(HeimdallError::InvalidInput(format!("Invalid digest line {}", line)))?,
+            }
+        }
+        Ok(())
+    }
+}
+
+impl<L: ResourceLoader> ResourceLoader for VerifiedLoader<L> {
+    fn load(&self, name: &str) -> Result<Vec<u8>, HeimdallError> {
+        let expected = match self.digests.get(name) {
+            Some(digest) => digest,
+            None => Err(HeimdallError::UntrustedResource(format!(
+                "No digest expected for {}",
+                name
+            )))?,
+        };
+
+        let bytes = self.loader.load(name)?;
+        if &sha256(&bytes) != expected {
+            Err(HeimdallError::UntrustedResource(format!(
+                "Digest of {} does not match",
+                name
+            )))?
+        }
+        Ok(bytes)
+    }
+
+    fn pins_digests(&self) -> bool {
+        true
+    }
+}
+
+impl<L: ResourceLoader + ?Sized> ResourceLoader for &L {
+    fn load(&self, name: &str) -> Result<Vec<u8>, HeimdallError> {
+        (**self).load(name)
+    }
+
+    fn pins_digests(&self) -> bool {
+        (**self).pins_digests()
+    }
+}
+
+///Computes the SHA-256 digest of the bytes.
+pub fn sha256(bytes: &[u8]) -> [u8; 32] {
+    Sha256::digest(bytes).into()
+}
+
+///Loader for the ressources shipped with the crate, independent of the working directory.
+#[cfg(test)]
+pub(crate) fn test_loader() -> FileSystemLoader {
+    FileSystemLoader::new(concat!(env!("CARGO_MANIFEST_DIR"), "/lib"))
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    ///TEST: ressource loaders
+    #[test]
+    fn resource_loaders() {
+        let embedded = EmbeddedLoader::new().with("circuit.zkey", b"zkey");
+        assert_eq!(embedded.load("circuit.zkey").unwrap(), b"zkey".to_vec());
+        assert!(matches!(
+            embedded.load("circuit.dat"),
+            Err(HeimdallError::MissingResource(_))
+        ));
+
+        let mut memory = MemoryLoader::new();
+        memory.insert("circuit.zkey", b"zkey".to_vec());
+        assert_eq!(memory.load("circuit.zkey").unwrap(), b"zkey".to_vec());
+
+        assert!(test_loader().load("presentation_attribute.dat").is_ok());
+        assert!(matches!(
+            test_loader().load("missing.dat"),
+            Err(HeimdallError::MissingResource(_))
+        ));
+    }
+
+    ///TEST: ressource digest verification
+    #[test]
+    fn verified_loader() {
+        let mut memory = MemoryLoader::new();
+        memory.insert("circuit.zkey", b"zkey".to_vec());
+        memory.insert("circuit.dat", b"dat".to_vec());
+
+        let mut verified = VerifiedLoader::new(&memory);
+        verified
+            .expect_hex(
+                "circuit.zkey",
+                "fc27f5c82b9b6e0b7b2fd3aee1e8a0d8c58bb8c4e7e6b6d5a3f13e43fdc1a64d",
+            )
+            .unwrap();
+        verified.expect("circuit.dat", sha256(b"dat"));
+
+        assert_eq!(verified.load("circuit.dat").unwrap(), b"dat".to_vec());
+        assert!(matches!(
+            verified.load("circuit.zkey"),
+            Err(HeimdallError::UntrustedResource(_))
+        ));
+        assert!(matches!(
+            verified.load("circuit.wasm"),
+            Err(HeimdallError::UntrustedResource(_))
+        ));
+        assert!(verified.expect_hex("circuit.zkey", "abc").is_err());
+
+        let mut verified = VerifiedLoader::new(&memory);
+        verified
+            .expect_sha256sums(&format!(
+                "{}  circuit.dat\n\nfc27f5c82b9b6e0b7b2fd3aee1e8a0d8c58bb8c4e7e6b6d5a3f13e43fdc1a64d *circuit.zkey\n",
+                hex(&sha256(b"dat"))
+            ))
+            .unwrap();
+        assert_eq!(verified.load("circuit.dat").unwrap(), b"dat".to_vec());
+        assert!(matches!(
+            verified.load("circuit.zkey"),
+            Err(HeimdallError::UntrustedResource(_))
+        ));
+        assert!(verified.expect_sha256sums("circuit.dat").is_err());
+    }
+
+    //encodes the digest in hex as printed by 'sha256sum'
+    fn hex(digest: &[u8]) -> String {
+        digest.iter().map(|byte| format!("{:02x}", byte)).collect()
+    }
+}
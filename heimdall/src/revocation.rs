@@ -1,91 +1,1073 @@
-use std::{error::Error, str::FromStr};
+use std::{collections::{BTreeMap, BTreeSet}, str::FromStr, time::{SystemTime, UNIX_EPOCH}};
 
-use crypto::{HashFunction, merkle_tree::HashTree, SignatureFunction, Signature};
-use num_bigint::BigInt;
+use crypto::{HashFunction, merkle_tree::{MerkleProof, SparseMerkleTree}, SignatureFunction, Signature};
+use num_bigint::{BigInt, Sign};
 use num_traits::One;
+use rand::Rng;
 use serde::{Serialize, Deserialize};
 
+use crate::error::HeimdallError;
+
 ///Depth of the Merkle tree in the revocation registry, as defined in the circuit.
 pub const REVOC_TREE_DEPTH: usize = 13;
 
+///Magic bytes of the binary registry format.
+const REGISTRY_MAGIC: &[u8; 4] = b"HRVR";
+
+///Version of the binary registry format.
+/// Version 2 adds the id allocator, version 3 moves the event log into an optional trailing section.
+pub const REGISTRY_FORMAT_VERSION: u8 = 3;
+
 ///Maximum data points per tree leave.
 /// 
 /// Limitation is derived from the field used in the ZKP.
 pub const MAX_LEAF_SIZE: usize = 252;
 
+///Number of ids a revocation registry can hold.
+pub const REVOC_CAPACITY: usize = (1 << REVOC_TREE_DEPTH) * MAX_LEAF_SIZE;
+
+///Tolerated clock skew in milliseconds for timestamps signed in the future of the verifier.
+pub const MAX_CLOCK_SKEW: u128 = 5 * 60 * 1000;
+
 ///A struct representing a revocation registry.
+/// 
+/// The ids are stored as bits in the leaves of a sparse Merkle tree,
+///  so only leaves holding revoked ids take up space.
 #[derive(Serialize,Deserialize)]
 pub struct RevocationRegistry<H: HashFunction,S: SignatureFunction>{
-    #[serde(bound(serialize = "HashTree<H,2>: Serialize", deserialize = "HashTree<H,2>: Deserialize<'de>"))]
-    pub tree: HashTree<H,2>,
-    pub signature: Option<Signature<S>>
+    #[serde(bound(serialize = "SparseMerkleTree<H>: Serialize", deserialize = "SparseMerkleTree<H>: Deserialize<'de>"))]
+    pub tree: SparseMerkleTree<H>,
+    pub signed_root: Option<SignedRevocationRoot<S>>,
+    ///Hash-chained log of the status changes since 'base'.
+    log: Vec<RevocationEvent>,
+    ///Head of the changes preceding the log, dropped from the compact encoding.
+    #[serde(default)]
+    base: LogBase,
+    allocator: IdAllocator,
+}
+
+///A struct representing the head of the changes preceding the retained event log.
+#[derive(Debug,Clone,Default,PartialEq,Eq,Serialize,Deserialize)]
+struct LogBase{
+    ///Number of changes preceding the log.
+    version: u64,
+    hash: BigInt,
+    timestamp: u128,
+}
+
+///A enum representing the order in which the allocator assigns ids.
+#[derive(Debug,Clone,Copy,PartialEq,Eq,Serialize,Deserialize)]
+pub enum AllocationStrategy{
+    ///Assigns the next free id after the last assigned one.
+    Sequential,
+    ///Assigns a random free id, hiding the order of issuance.
+    Random,
+}
+
+///A struct tracking the ids of a revocation registry assigned to credentials.
+#[derive(Debug,Clone,PartialEq,Eq,Serialize,Deserialize)]
+pub struct IdAllocator{
+    allocated: BTreeSet<usize>,
+    ///Next id to consider for sequential allocation.
+    next: usize,
+    strategy: AllocationStrategy,
+}
+
+impl IdAllocator{
+
+    ///Random attempts before falling back to a scan for a free id.
+    const RANDOM_ATTEMPTS: usize = 64;
+
+    fn new(strategy: AllocationStrategy) -> Self{
+        IdAllocator { allocated: BTreeSet::new(), next: 0, strategy }
+    }
+
+    ///Assigns a free id below 'capacity'.
+    fn allocate(&mut self, capacity: usize) -> Result<usize, HeimdallError>{
+        if self.allocated.len() >= capacity {
+            Err(HeimdallError::RegistryFull { capacity })?;
+        }
+
+        let start = match self.strategy {
+            AllocationStrategy::Sequential => self.next % capacity,
+            AllocationStrategy::Random => {
+                let mut rng = rand::thread_rng();
+                let mut id = rng.gen_range(0..capacity);
+                for _ in 1..Self::RANDOM_ATTEMPTS {
+                    if !self.allocated.contains(&id) {
+                        break;
+                    }
+                    id = rng.gen_range(0..capacity);
+                }
+                id
+            },
+        };
+
+        //the capacity check guarantees a free id
+        let id = (start..capacity).chain(0..start)
+            .find(|id| !self.allocated.contains(id))
+            .expect("free id");
+        self.allocated.insert(id);
+        if self.strategy == AllocationStrategy::Sequential {
+            self.next = id + 1;
+        }
+        Ok(id)
+    }
+}
+
+///A enum representing a change of the revocation status of an id.
+#[derive(Debug,Clone,PartialEq,Eq,Serialize,Deserialize)]
+pub enum RevocationAction{
+    Revoke{ reason: String },
+    Reinstate,
+}
+
+///A struct representing an entry of the revocation event log.
+/// 
+/// Every event commits to its predecessor through 'previous',
+///  so the log can only be altered by recomputing all following events.
+/// Batch changes are logged as a single event.
+#[derive(Debug,Clone,PartialEq,Eq,Serialize,Deserialize)]
+pub struct RevocationEvent{
+    pub ids: Vec<usize>,
+    pub action: RevocationAction,
+    ///Time of the change in milliseconds since the unix epoch, also used for signing the root.
+    pub timestamp: u128,
+    ///Root of the registry after the change.
+    pub root: BigInt,
+    ///Hash of the previous event, 0 for the first event.
+    pub previous: BigInt,
+    pub hash: BigInt,
+}
+
+impl RevocationEvent{
+
+    ///Creates the event following the event with the hash 'previous'.
+    fn new<H: HashFunction>(ids: Vec<usize>, action: RevocationAction, timestamp: u128, root: BigInt, previous: BigInt)
+    -> Result<Self, HeimdallError>{
+        let hash = Self::compute_hash::<H>(&ids, &action, timestamp, &root, &previous)?;
+        Ok(RevocationEvent { ids, action, timestamp, root, previous, hash })
+    }
+
+    ///Computes the hash of the event chaining it to its predecessor.
+    fn compute_hash<H: HashFunction>(ids: &[usize], action: &RevocationAction, timestamp: u128, root: &BigInt, previous: &BigInt)
+    -> Result<BigInt, HeimdallError>{
+        let hasher = H::new();
+        let mut ids_hash = BigInt::from(0);
+        for id in ids {
+            ids_hash = hasher.hash_big_int(&[ids_hash, BigInt::from(*id)])?;
+        }
+        let (action, reason) = match action {
+            RevocationAction::Revoke { reason } => (BigInt::one(), hasher.hash_str(reason)?),
+            RevocationAction::Reinstate => (BigInt::from(0), BigInt::from(0)),
+        };
+        Ok(hasher.hash_big_int(&[
+            previous.clone(), ids_hash, action, reason, BigInt::from(timestamp), root.clone()
+        ])?)
+    }
+
+    ///Checks that the signed root was issued for this event.
+    pub fn matches<S: SignatureFunction>(&self, signed_root: &SignedRevocationRoot<S>) -> bool{
+        self.root == signed_root.root && self.timestamp == signed_root.timestamp
+    }
+}
+
+///A struct representing a revocation root signed by the issuer at a point in time.
+/// 
+/// The signature covers the hash of the root and the timestamp,
+///  allowing verifiers to reject outdated roots.
+#[derive(Debug,Serialize,Deserialize)]
+pub struct SignedRevocationRoot<S: SignatureFunction>{
+    pub root: BigInt,
+    ///Time of signing in milliseconds since the unix epoch.
+    pub timestamp: u128,
+    pub signature: Signature<S>
+}
+
+impl<S: SignatureFunction> SignedRevocationRoot<S>{
+
+    ///Signs the root together with the current time.
+    pub fn new<H: HashFunction>(root: BigInt, sk: BigInt) -> Result<Self, HeimdallError>{
+        Self::new_at::<H>(root, now()?, sk)
+    }
+
+    ///Signs the root together with the provided timestamp.
+    pub fn new_at<H: HashFunction>(root: BigInt, timestamp: u128, sk: BigInt) -> Result<Self, HeimdallError>{
+        let signature = S::sign(sk, Self::message::<H>(&root, timestamp)?)?;
+        Ok(SignedRevocationRoot { root, timestamp, signature })
+    }
+
+    ///Computes the signed message from the root and the timestamp.
+    fn message<H: HashFunction>(root: &BigInt, timestamp: u128) -> Result<BigInt, HeimdallError>{
+        Ok(H::new().hash_big_int(&[root.clone(), BigInt::from(timestamp)])?)
+    }
+
+    ///Checks that the root was signed by the issuer no longer than 'max_age' milliseconds ago.
+    /// Rejects roots signed more than 'MAX_CLOCK_SKEW' milliseconds in the future.
+    pub fn verify<H: HashFunction>(&self, pk_issuer: &[BigInt;2], max_age: u128)
+    -> Result<(),HeimdallError>{
+        if &self.signature.public_key_signer != pk_issuer {
+            Err(HeimdallError::SignatureInvalid("Revocation root not signed by the issuer.".to_owned()))?;
+        }
+
+        if !S::verify(&self.signature, &Self::message::<H>(&self.root, self.timestamp)?) {
+            Err(HeimdallError::SignatureInvalid("Signature of the revocation root invalid.".to_owned()))?;
+        }
+
+        check_freshness(self.timestamp, max_age)
+    }
+}
+
+///Implements the Clone trait for a SignedRevocationRoot.
+impl<S: SignatureFunction> Clone for SignedRevocationRoot<S>{
+    fn clone(&self) -> Self {
+        Self { root: self.root.clone(), timestamp: self.timestamp, signature: self.signature.clone() }
+    }
 }
 
 impl<H:HashFunction, S: SignatureFunction> RevocationRegistry<H,S>{
     
     ///Creates a empty revocation registry.
     /// If present, signs the root with the secret key.
-    pub fn new(secret_key: Option<BigInt>) ->Self{
-        let depth = (REVOC_TREE_DEPTH as f32).exp2() as usize;
-        let leaves = vec!["0".to_owned();depth];
-
-        let tree = HashTree::<H,2>::new(&leaves);
+    pub fn new(secret_key: Option<BigInt>) ->Result<Self, HeimdallError>{
+        let tree = SparseMerkleTree::<H>::new(REVOC_TREE_DEPTH as u32, "0")?;
 
-        let signature = match secret_key{
-            Some(sk) => Some(S::sign(sk, tree.get_root().clone())),
+        let signed_root = match secret_key{
+            Some(sk) => Some(SignedRevocationRoot::new::<H>(tree.get_root().clone(), sk)?),
             None => None,
         };
-        RevocationRegistry { tree, signature }
+        Ok(RevocationRegistry { tree, signed_root, log: Vec::new(), base: LogBase::default(),
+            allocator: IdAllocator::new(AllocationStrategy::Sequential) })
+    }
+
+    ///Retrieves the number of ids the registry can hold.
+    pub fn capacity(&self) -> usize{
+        self.tree.leave_count() * MAX_LEAF_SIZE
+    }
+
+    ///Sets the order of the ids assigned by 'allocate_id'.
+    pub fn set_allocation_strategy(&mut self, strategy: AllocationStrategy){
+        self.allocator.strategy = strategy;
+    }
+
+    ///Assigns a free id to a new credential.
+    /// Revoked ids count as assigned.
+    /// 
+    /// Fails with 'RegistryFull', should all ids be assigned.
+    pub fn allocate_id(&mut self) -> Result<usize, HeimdallError>{
+        self.allocator.allocate(self.capacity())
+    }
+
+    ///Frees an id, e.g. should the issuance of the credential have failed.
+    /// 
+    /// Fails, should the id not be assigned or be revoked,
+    ///  as a revoked id must never be reassigned.
+    pub fn release_id(&mut self, id: usize) -> Result<(), HeimdallError>{
+        if !self.allocator.allocated.contains(&id) {
+            Err(HeimdallError::InvalidInput(format!("Id {} is not allocated", id)))?;
+        }
+        if self.is_revoked(id)? {
+            Err(HeimdallError::RevocationUnchanged { id, revoked: true })?;
+        }
+        self.allocator.allocated.remove(&id);
+        Ok(())
+    }
+
+    ///Checks wether the id is assigned to a credential.
+    pub fn is_allocated(&self, id: usize) -> bool{
+        self.allocator.allocated.contains(&id)
+    }
+
+    ///Retrieves the number of assigned ids.
+    pub fn allocated_count(&self) -> usize{
+        self.allocator.allocated.len()
+    }
+
+    ///Rebuilds the registry from the event log, allowing auditors to reproduce every signed root.
+    /// The returned registry is not signed.
+    /// 
+    /// # Errors
+    /// 
+    /// The function fails, should an event not follow its predecessor,
+    ///  not be applicable or not result in the logged root.
+    pub fn replay(events: &[RevocationEvent]) -> Result<Self, HeimdallError>{
+        let mut registry = Self::new(None)?;
+
+        for (index, event) in events.iter().enumerate() {
+            let invalid = |msg: &str| HeimdallError::InvalidEventLog { index, msg: msg.to_owned() };
+
+            if event.previous != registry.last_hash() {
+                Err(invalid("Event does not follow its predecessor"))?;
+            }
+            if event.timestamp < registry.last_timestamp() {
+                Err(invalid("Event predates its predecessor"))?;
+            }
+            registry.apply(&event.ids, event.action.clone(), event.timestamp)
+                .map_err(|err| invalid(&err.to_string()))?;
+
+            if &registry.log[index] != event {
+                Err(invalid("Event does not match the replayed change"))?;
+            }
+        }
+
+        Ok(registry)
+    }
+
+    ///Retrieves the retained event log.
+    /// Lacks the changes before 'log_start', should the registry have been loaded without its log.
+    pub fn log(&self) -> &[RevocationEvent]{
+        &self.log
+    }
+
+    ///Retrieves the version of the first change in the retained event log.
+    pub fn log_start(&self) -> u64{
+        self.base.version
+    }
+
+    ///Replays the event log and checks that it results in the leaves of the registry.
+    /// 
+    /// # Errors
+    /// 
+    /// The function fails, should the log lack its first changes or not replay to the registry.
+    pub fn verify_log(&self) -> Result<(), HeimdallError>{
+        if self.base.version != 0 {
+            Err(HeimdallError::InvalidEventLog { index: 0,
+                msg: format!("Log lacks the first {} changes", self.base.version) })?;
+        }
+        let replayed = Self::replay(&self.log)?;
+        if replayed.tree.get_root() != self.tree.get_root() {
+            Err(HeimdallError::InvalidEventLog { index: self.log.len(),
+                msg: "Log does not result in the leaves of the registry".to_owned() })?;
+        }
+        Ok(())
+    }
+
+    fn last_hash(&self) -> BigInt{
+        self.log.last().map(|event| event.hash.clone()).unwrap_or_else(|| self.base.hash.clone())
+    }
+
+    fn last_timestamp(&self) -> u128{
+        self.log.last().map(|event| event.timestamp).unwrap_or(self.base.timestamp)
     }
 
-    ///Switches the status of the id.
-    pub fn update(&mut self, id: usize, sk: Option<BigInt>)
-    -> Result<(),Box<dyn Error>>{
-        if id >= ((REVOC_TREE_DEPTH as f32).exp2() * (MAX_LEAF_SIZE as f32)) as usize {
-            Err("Id not in Tree")?;
+    ///Locates the leaf and the bit of the id and parses the leaf.
+    /// Does not reflect pending changes of a batch.
+    fn locate(&self, id: usize) -> Result<(usize, usize, BigInt), HeimdallError>{
+        locate(&self.tree, id)
+    }
+
+    ///Retrieves the version of the registry, the number of changes.
+    pub fn version(&self) -> u64{
+        self.base.version + self.log.len() as u64
+    }
+
+    ///Collects the changes since 'version' for publication, signed with the secret key of the issuer.
+    /// 
+    /// The delta carries the current values of the changed leaves and the signed root,
+    ///  so replicas at 'version' or later can catch up without the full registry.
+    ///  The signature binds the versions to the signed root.
+    ///  Without the changes since 'version' in the log, a delta from version 0 holds all populated leaves.
+    /// 
+    /// # Errors
+    /// 
+    /// The function fails, should the version be unknown, its changes not be retained
+    ///  or the registry not carry a signed root matching its tree.
+    pub fn delta(&self, version: u64, sk: BigInt) -> Result<RevocationDelta<S>, HeimdallError>{
+        if version > self.version() {
+            Err(HeimdallError::InvalidInput(format!("Unknown registry version {}", version)))?;
         }
+        let signed_root = match &self.signed_root {
+            Some(signed_root) if &signed_root.root == self.tree.get_root() => signed_root.clone(),
+            _ => Err(HeimdallError::SignatureInvalid("Revocation registry not signed.".to_owned()))?,
+        };
+
+        let leaves = if version >= self.base.version {
+            let changed: BTreeSet<usize> = self.log[(version - self.base.version) as usize..].iter()
+                .flat_map(|event| event.ids.iter().map(|id| id / MAX_LEAF_SIZE))
+                .collect();
+            changed.into_iter()
+                .map(|index| Ok((index, self.tree.get_leave(index)?.to_owned())))
+                .collect::<Result<_, HeimdallError>>()?
+        } else if version == 0 {
+            self.tree.populated_leaves().map(|(index, leaf)| (index, leaf.to_owned())).collect()
+        } else {
+            Err(HeimdallError::InvalidInput(format!("Changes before version {} not retained", self.base.version)))?
+        };
+
+        let message = RevocationDelta::message::<H>(version, self.version(), &signed_root)?;
+        Ok(RevocationDelta { from_version: version, version: self.version(), leaves, signed_root, signature: S::sign(sk, message)? })
+    }
+
+    ///Revokes the id and logs the reason.
+    /// If present, signs the new root with the secret key.
+    /// 
+    /// Fails, should the id already be revoked.
+    pub fn revoke(&mut self, id: usize, reason: &str, sk: Option<BigInt>)
+    -> Result<(),HeimdallError>{
+        self.change(&[id], RevocationAction::Revoke { reason: reason.to_owned() }, sk)?;
+        Ok(())
+    }
 
-        let index_leaf = id / MAX_LEAF_SIZE;
-        let index_bit = id % MAX_LEAF_SIZE;
+    ///Revokes all ids at once, logs them as a single event and signs the new root only once.
+    /// 
+    /// Returns the indices of the changed leaves in ascending order, e.g. for publishing deltas.
+    /// 
+    /// # Errors
+    /// 
+    /// The function fails without modifying the registry,
+    ///  should an id be out of range, already revoked or listed twice.
+    pub fn revoke_batch(&mut self, ids: &[usize], reason: &str, sk: Option<BigInt>)
+    -> Result<Vec<usize>,HeimdallError>{
+        self.change(ids, RevocationAction::Revoke { reason: reason.to_owned() }, sk)
+    }
+
+    ///Reinstates the revoked id.
+    /// If present, signs the new root with the secret key.
+    /// 
+    /// Fails, should the id not be revoked.
+    pub fn reinstate(&mut self, id: usize, sk: Option<BigInt>)
+    -> Result<(),HeimdallError>{
+        self.change(&[id], RevocationAction::Reinstate, sk)?;
+        Ok(())
+    }
+
+    ///Applies the change at the current time and signs the new root.
+    fn change(&mut self, ids: &[usize], action: RevocationAction, sk: Option<BigInt>)
+    -> Result<Vec<usize>,HeimdallError>{
+        let now = now()?;
+        //keeps the log strictly ordered, should the clock go backwards or stand still
+        let timestamp = now.max(self.last_timestamp().saturating_add(1));
 
-        let value : BigInt= (BigInt::from_str(&self.tree.leaves[index_leaf]).unwrap() / BigInt::from(2).pow(index_bit as u32)) % 2;
-        if  value.is_one(){
-            self.tree.update(index_leaf,
-                 (BigInt::from_str(
-                    &self.tree.leaves[index_leaf]).unwrap() - BigInt::from(2).pow(index_bit as u32)
-                ).to_string());
-        }else {
-            self.tree.update(index_leaf,
-                 (BigInt::from_str(
-                    &self.tree.leaves[index_leaf]).unwrap() + BigInt::from(2).pow(index_bit as u32)
-                ).to_string());}
+        let changed_leaves = self.apply(ids, action, timestamp)?;
 
-        self.signature = match sk{
-            Some(sk) => Some(S::sign(sk, self.tree.get_root().clone())),
+        self.signed_root = match sk{
+            Some(sk) => Some(SignedRevocationRoot::new_at::<H>(self.tree.get_root().clone(), timestamp, sk)?),
             None => None,
         };
-        
+
+        Ok(changed_leaves)
+    }
+
+    ///Sets the status of the ids according to the action and logs the change.
+    /// Groups the ids by leaf, so every leaf and the nodes above are only updated once.
+    /// 
+    /// Returns the indices of the changed leaves in ascending order.
+    fn apply(&mut self, ids: &[usize], action: RevocationAction, timestamp: u128)
+    -> Result<Vec<usize>,HeimdallError>{
+        if ids.is_empty() {
+            Err(HeimdallError::InvalidInput("No ids to change".to_owned()))?;
+        }
+
+        let revoke = matches!(action, RevocationAction::Revoke { .. });
+        let mut leaves = BTreeMap::<usize, BigInt>::new();
+        for &id in ids {
+            let (index_leaf, index_bit, leaf) = self.locate(id)?;
+            let leaf = leaves.entry(index_leaf).or_insert(leaf);
+
+            let bit = BigInt::from(2).pow(index_bit as u32);
+            let revoked = ((&*leaf / &bit) % BigInt::from(2)).is_one();
+            if revoked == revoke {
+                Err(HeimdallError::RevocationUnchanged { id, revoked })?;
+            }
+
+            if revoke { *leaf += bit } else { *leaf -= bit }
+        }
+
+        let new_leaves: Vec<(usize, String)> = leaves.into_iter()
+            .map(|(index, leaf)| (index, leaf.to_string())).collect();
+        self.tree.update_leaves(&new_leaves)?;
+        //revoked ids are never assigned, even if revoked before their allocation
+        if revoke {
+            self.allocator.allocated.extend(ids.iter().copied());
+        }
+
+        let event = RevocationEvent::new::<H>(ids.to_vec(), action, timestamp,
+            self.tree.get_root().clone(), self.last_hash())?;
+        self.log.push(event);
+
+        Ok(new_leaves.into_iter().map(|(index, _)| index).collect())
+    }
+
+    ///Collects the revoked ids in ascending order.
+    fn revoked_ids(&self) -> Result<Vec<usize>, HeimdallError>{
+        let mut ids = Vec::new();
+        for (index, leaf) in self.tree.populated_leaves() {
+            let leaf = BigInt::from_str(leaf)
+                .map_err(|_| HeimdallError::InvalidInput(format!("Malformed revocation leaf {}", index)))?;
+            ids.extend((0..MAX_LEAF_SIZE).filter(|bit| leaf.bit(*bit as u64)).map(|bit| index * MAX_LEAF_SIZE + bit));
+        }
+        Ok(ids)
+    }
+
+    ///Checks that the registry carries a fresh root signed by the issuer matching its tree.
+    pub fn verify_signed_root(&self, pk_issuer: &[BigInt;2], max_age: u128)
+    -> Result<(),HeimdallError>{
+        let signed_root = match &self.signed_root {
+            Some(signed_root) => signed_root,
+            None => Err(HeimdallError::SignatureInvalid("Revocation registry not signed.".to_owned()))?,
+        };
+
+        if &signed_root.root != self.tree.get_root() {
+            Err(HeimdallError::SignatureInvalid("Signed root does not match the revocation registry.".to_owned()))?;
+        }
+
+        signed_root.verify::<H>(pk_issuer, max_age)
+    }
+
+    ///Retrieves the leaf at 'index', holding the status of MAX_LEAF_SIZE ids.
+    pub fn get_leaf(&self, index: usize)-> Result<&str,HeimdallError>{
+        Ok(self.tree.get_leave(index)?)
+    }
+
+    ///Checks wether an id is revoked.
+    pub fn is_revoked(&self, id: usize)-> Result<bool,HeimdallError>{
+        is_revoked(&self.tree, id)
+    }
+}
+
+///Implements the binary persistence of the registry.
+/// 
+/// The format stores the populated leaves, the signed root, the allocator and optionally the event log.
+/// Nodes are recomputed on load and checked against the signed root,
+///  the event log is only replayed on request with 'verify_log'.
+impl<H:HashFunction, S: SignatureFunction> RevocationRegistry<H,S>{
+
+    ///Encodes the registry without its event log in the compact binary format.
+    /// 
+    /// The size only depends on the revoked and allocated ids, not on the history of the registry.
+    /// 
+    /// # Layout
+    /// All integers are big endian, numbers are encoded in 32 bytes.
+    /// * magic "HRVR", format version (u8), tree depth (u8)
+    /// * leaf count (u32), per leaf: index (u64), value
+    /// * signed root flag (u8), if set: root, timestamp (u128), r8, s, public key
+    /// * allocation strategy (u8), next sequential id (u64), allocated count (u32), allocated ids (u64)
+    /// * log start: version (u64), hash, timestamp (u128)
+    /// * event count (u32), per event: id count (u32), ids (u64),
+    ///    action (u8), reason length (u32), reason, timestamp (u128), root, hash
+    pub fn to_bytes(&self) -> Result<Vec<u8>, HeimdallError>{
+        self.encode(false)
+    }
+
+    ///Encodes the registry together with its retained event log in the compact binary format.
+    pub fn to_bytes_with_log(&self) -> Result<Vec<u8>, HeimdallError>{
+        self.encode(true)
+    }
+
+    ///Encodes the registry, starting the log section at the last change, should the log be omitted.
+    fn encode(&self, with_log: bool) -> Result<Vec<u8>, HeimdallError>{
+        let mut buf = Vec::new();
+        buf.extend_from_slice(REGISTRY_MAGIC);
+        buf.push(REGISTRY_FORMAT_VERSION);
+        buf.push(self.tree.depth() as u8);
+
+        let leaves: Vec<(usize, &str)> = self.tree.populated_leaves().collect();
+        buf.extend_from_slice(&(leaves.len() as u32).to_be_bytes());
+        for (index, leaf) in leaves {
+            buf.extend_from_slice(&(index as u64).to_be_bytes());
+            let leaf = BigInt::from_str(leaf)
+                .map_err(|_| HeimdallError::InvalidInput(format!("Malformed revocation leaf {}", index)))?;
+            write_big_int(&mut buf, &leaf)?;
+        }
+
+        match &self.signed_root {
+            Some(signed_root) => {
+                buf.push(1);
+                write_big_int(&mut buf, &signed_root.root)?;
+                buf.extend_from_slice(&signed_root.timestamp.to_be_bytes());
+                let signature = &signed_root.signature;
+                for value in signature.r8.iter().chain([&signature.s]).chain(signature.public_key_signer.iter()) {
+                    write_big_int(&mut buf, value)?;
+                }
+            },
+            None => buf.push(0),
+        }
+
+        buf.push(match self.allocator.strategy {
+            AllocationStrategy::Sequential => 0,
+            AllocationStrategy::Random => 1,
+        });
+        buf.extend_from_slice(&(self.allocator.next as u64).to_be_bytes());
+        buf.extend_from_slice(&(self.allocator.allocated.len() as u32).to_be_bytes());
+        for id in &self.allocator.allocated {
+            buf.extend_from_slice(&(*id as u64).to_be_bytes());
+        }
+
+        let (base, log) = match with_log {
+            true => (self.base.clone(), self.log.as_slice()),
+            false => (LogBase { version: self.version(), hash: self.last_hash(), timestamp: self.last_timestamp() }, &[][..]),
+        };
+        buf.extend_from_slice(&base.version.to_be_bytes());
+        write_big_int(&mut buf, &base.hash)?;
+        buf.extend_from_slice(&base.timestamp.to_be_bytes());
+
+        buf.extend_from_slice(&(log.len() as u32).to_be_bytes());
+        for event in log {
+            buf.extend_from_slice(&(event.ids.len() as u32).to_be_bytes());
+            for id in &event.ids {
+                buf.extend_from_slice(&(*id as u64).to_be_bytes());
+            }
+            let reason = match &event.action {
+                RevocationAction::Revoke { reason } => { buf.push(1); reason.as_str() },
+                RevocationAction::Reinstate => { buf.push(0); "" },
+            };
+            buf.extend_from_slice(&(reason.len() as u32).to_be_bytes());
+            buf.extend_from_slice(reason.as_bytes());
+            buf.extend_from_slice(&event.timestamp.to_be_bytes());
+            write_big_int(&mut buf, &event.root)?;
+            write_big_int(&mut buf, &event.hash)?;
+        }
+
+        Ok(buf)
+    }
+
+    ///Decodes a registry from the compact binary format.
+    /// 
+    /// Does not replay the event log, use 'verify_log' to check it against the leaves.
+    ///  Registries of format version 1 and 2 are accepted, storing the log before the allocator.
+    ///  Version 1 treats every id in the event log as allocated.
+    /// 
+    /// # Errors
+    /// 
+    /// The function fails, should the bytes be malformed, the format version unknown,
+    ///  the signed root or the last event not match the recomputed tree,
+    ///  an allocated id exceed the capacity or a revoked id not be allocated.
+    ///  Revoked ids are added to the allocator of versions 1 and 2.
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self, HeimdallError>{
+        let mut reader = ByteReader { bytes, pos: 0 };
+        if reader.take(4)? != REGISTRY_MAGIC {
+            Err(HeimdallError::InvalidEncoding("Not a revocation registry".to_owned()))?;
+        }
+        let version = reader.u8()?;
+        if version == 0 || version > REGISTRY_FORMAT_VERSION {
+            Err(HeimdallError::InvalidEncoding(format!("Unsupported format version {}", version)))?;
+        }
+        let depth = reader.u8()?;
+        if depth as usize != REVOC_TREE_DEPTH {
+            Err(HeimdallError::InvalidEncoding(format!("Unsupported tree depth {}", depth)))?;
+        }
+
+        let mut leaves = Vec::new();
+        for _ in 0..reader.u32()? {
+            leaves.push((reader.usize()?, reader.big_int()?.to_string()));
+        }
+
+        let signed_root = match reader.u8()? {
+            0 => None,
+            1 => {
+                let root = reader.big_int()?;
+                let timestamp = reader.u128()?;
+                let r8 = [reader.big_int()?, reader.big_int()?];
+                let s = reader.big_int()?;
+                let public_key_signer = [reader.big_int()?, reader.big_int()?];
+                Some(SignedRevocationRoot { root, timestamp, signature: Signature::new(r8, s, public_key_signer) })
+            },
+            flag => Err(HeimdallError::InvalidEncoding(format!("Invalid signed root flag {}", flag)))?,
+        };
+
+        let mut allocator = None;
+        let mut base = LogBase::default();
+        if version >= 3 {
+            allocator = Some(read_allocator(&mut reader)?);
+            base = LogBase { version: reader.u64()?, hash: reader.big_int()?, timestamp: reader.u128()? };
+        }
+
+        let mut events = Vec::<RevocationEvent>::new();
+        for _ in 0..reader.u32()? {
+            let mut ids = Vec::new();
+            for _ in 0..reader.u32()? {
+                ids.push(reader.usize()?);
+            }
+            let flag = reader.u8()?;
+            let length = reader.u32()? as usize;
+            let reason = String::from_utf8(reader.take(length)?.to_vec())
+                .map_err(|_| HeimdallError::InvalidEncoding("Reason is not utf-8".to_owned()))?;
+            let action = match flag {
+                0 => RevocationAction::Reinstate,
+                1 => RevocationAction::Revoke { reason },
+                _ => Err(HeimdallError::InvalidEncoding(format!("Invalid action {}", flag)))?,
+            };
+            let timestamp = reader.u128()?;
+            let root = reader.big_int()?;
+            let hash = reader.big_int()?;
+            let previous = events.last().map(|event| event.hash.clone()).unwrap_or_else(|| base.hash.clone());
+            events.push(RevocationEvent { ids, action, timestamp, root, previous, hash });
+        }
+
+        let allocator = match allocator {
+            Some(allocator) => allocator,
+            None if version == 2 => read_allocator(&mut reader)?,
+            None => {
+                let mut allocator = IdAllocator::new(AllocationStrategy::Sequential);
+                allocator.allocated = events.iter().flat_map(|event| event.ids.iter().copied()).collect();
+                allocator.next = allocator.allocated.iter().next_back().map(|id| id + 1).unwrap_or(0);
+                allocator
+            },
+        };
+        reader.finish()?;
+
+        let mut registry = Self::new(None)?;
+        registry.tree.update_leaves(&leaves)?;
+        if let Some(signed_root) = &signed_root {
+            if &signed_root.root != registry.tree.get_root() {
+                Err(HeimdallError::InvalidEncoding("Stored root does not match the recomputed root".to_owned()))?;
+            }
+        }
+        if let Some(event) = events.last() {
+            if &event.root != registry.tree.get_root() {
+                Err(HeimdallError::InvalidEncoding("Stored leaves do not match the event log".to_owned()))?;
+            }
+        }
+
+        registry.log = events;
+        registry.base = base;
+        registry.allocator = allocator;
+
+        let capacity = registry.capacity();
+        if let Some(id) = registry.allocator.allocated.iter().next_back().filter(|id| **id >= capacity) {
+            Err(HeimdallError::InvalidEncoding(format!("Allocated id {} exceeds the capacity {}", id, capacity)))?;
+        }
+        for id in registry.revoked_ids()? {
+            if version < 3 {
+                registry.allocator.allocated.insert(id);
+            } else if !registry.allocator.allocated.contains(&id) {
+                Err(HeimdallError::InvalidEncoding(format!("Revoked id {} is not allocated", id)))?;
+            }
+        }
+        registry.signed_root = signed_root;
+        Ok(registry)
+    }
+}
+
+///Reads the id allocator of the binary registry format.
+fn read_allocator(reader: &mut ByteReader) -> Result<IdAllocator, HeimdallError>{
+    let strategy = match reader.u8()? {
+        0 => AllocationStrategy::Sequential,
+        1 => AllocationStrategy::Random,
+        flag => Err(HeimdallError::InvalidEncoding(format!("Invalid allocation strategy {}", flag)))?,
+    };
+    let next = reader.usize()?;
+    let mut allocated = BTreeSet::new();
+    for _ in 0..reader.u32()? {
+        allocated.insert(reader.usize()?);
+    }
+    Ok(IdAllocator { allocated, next, strategy })
+}
+
+///Appends the non-negative number in 32 bytes big endian.
+fn write_big_int(buf: &mut Vec<u8>, value: &BigInt) -> Result<(), HeimdallError>{
+    let (sign, bytes) = value.to_bytes_be();
+    if sign == Sign::Minus || bytes.len() > 32 {
+        Err(HeimdallError::InvalidInput(format!("{} does not fit into 32 bytes", value)))?;
+    }
+    buf.extend(std::iter::repeat(0).take(32 - bytes.len()));
+    buf.extend_from_slice(&bytes);
+    Ok(())
+}
+
+///Reads the binary registry format.
+struct ByteReader<'a>{
+    bytes: &'a [u8],
+    pos: usize,
+}
+
+impl<'a> ByteReader<'a>{
+
+    fn take(&mut self, len: usize) -> Result<&'a [u8], HeimdallError>{
+        if self.bytes.len() - self.pos < len {
+            Err(HeimdallError::InvalidEncoding("Unexpected end of data".to_owned()))?;
+        }
+        let slice = &self.bytes[self.pos..self.pos + len];
+        self.pos += len;
+        Ok(slice)
+    }
+
+    fn u8(&mut self) -> Result<u8, HeimdallError>{
+        Ok(self.take(1)?[0])
+    }
+
+    fn u32(&mut self) -> Result<u32, HeimdallError>{
+        Ok(u32::from_be_bytes(self.take(4)?.try_into().expect("length checked")))
+    }
+
+    fn u64(&mut self) -> Result<u64, HeimdallError>{
+        Ok(u64::from_be_bytes(self.take(8)?.try_into().expect("length checked")))
+    }
+
+    fn usize(&mut self) -> Result<usize, HeimdallError>{
+        let value = self.u64()?;
+        usize::try_from(value).map_err(|_| HeimdallError::InvalidEncoding(format!("Index {} too large", value)))
+    }
+
+    fn u128(&mut self) -> Result<u128, HeimdallError>{
+        Ok(u128::from_be_bytes(self.take(16)?.try_into().expect("length checked")))
+    }
+
+    fn big_int(&mut self) -> Result<BigInt, HeimdallError>{
+        Ok(BigInt::from_bytes_be(Sign::Plus, self.take(32)?))
+    }
+
+    ///Fails, should bytes remain.
+    fn finish(&self) -> Result<(), HeimdallError>{
+        if self.pos != self.bytes.len() {
+            Err(HeimdallError::InvalidEncoding("Trailing data".to_owned()))?;
+        }
+        Ok(())
+    }
+}
+
+///A struct representing the changes of a revocation registry between two versions.
+/// 
+/// Serializable with serde.
+#[derive(Debug,Serialize,Deserialize)]
+pub struct RevocationDelta<S: SignatureFunction>{
+    ///Version the delta applies to.
+    pub from_version: u64,
+    ///Version after applying the delta.
+    pub version: u64,
+    ///Indices and current values of the changed leaves.
+    pub leaves: Vec<(usize, String)>,
+    pub signed_root: SignedRevocationRoot<S>,
+    ///Signature of the issuer over the versions and the signed root.
+    pub signature: Signature<S>,
+}
+
+impl<S: SignatureFunction> RevocationDelta<S>{
+    ///Computes the signed message from the versions and the signed root.
+    fn message<H: HashFunction>(from_version: u64, version: u64, signed_root: &SignedRevocationRoot<S>)
+    -> Result<BigInt, HeimdallError>{
+        Ok(H::new().hash_big_int(&[
+            BigInt::from(from_version), BigInt::from(version), signed_root.root.clone(), BigInt::from(signed_root.timestamp)
+        ])?)
+    }
+}
+
+///Implements the Clone trait for a RevocationDelta.
+impl<S: SignatureFunction> Clone for RevocationDelta<S>{
+    fn clone(&self) -> Self {
+        Self { from_version: self.from_version, version: self.version,
+            leaves: self.leaves.clone(), signed_root: self.signed_root.clone(), signature: self.signature.clone() }
+    }
+}
+
+///A struct representing a copy of a revocation registry kept in sync with published deltas.
+/// 
+/// Allows holders to generate their revocation proofs locally
+///  without downloading the registry or its event log.
+#[derive(Serialize,Deserialize)]
+pub struct RevocationRegistryReplica<H: HashFunction,S: SignatureFunction>{
+    #[serde(bound(serialize = "SparseMerkleTree<H>: Serialize", deserialize = "SparseMerkleTree<H>: Deserialize<'de>"))]
+    tree: SparseMerkleTree<H>,
+    version: u64,
+    signed_root: Option<SignedRevocationRoot<S>>,
+    pk_issuer: [BigInt;2],
+}
+
+impl<H:HashFunction, S: SignatureFunction> RevocationRegistryReplica<H,S>{
+
+    ///Creates a replica of a empty registry, accepting roots signed by the issuer.
+    pub fn new(pk_issuer: [BigInt;2]) -> Result<Self, HeimdallError>{
+        let tree = SparseMerkleTree::<H>::new(REVOC_TREE_DEPTH as u32, "0")?;
+        Ok(RevocationRegistryReplica { tree, version: 0, signed_root: None, pk_issuer })
+    }
+
+    ///Applies the delta after checking the signatures of its versions and its root.
+    /// 
+    /// # Errors
+    /// 
+    /// The function fails without modifying the replica, should the delta not apply to the version,
+    ///  the delta or its root not be signed by the issuer, the root not be signed after the current root
+    ///  or the changed leaves not result in the signed root.
+    pub fn apply(&mut self, delta: &RevocationDelta<S>) -> Result<(), HeimdallError>{
+        if delta.from_version > self.version || delta.version <= self.version {
+            Err(HeimdallError::InvalidInput(format!("Delta from version {} to {} does not apply to version {}",
+                delta.from_version, delta.version, self.version)))?;
+        }
+        if delta.signature.public_key_signer != self.pk_issuer
+         || !S::verify(&delta.signature, &RevocationDelta::message::<H>(delta.from_version, delta.version, &delta.signed_root)?) {
+            Err(HeimdallError::SignatureInvalid("Revocation delta not signed by the issuer.".to_owned()))?;
+        }
+        //the age is checked by the verifier of the presentation
+        delta.signed_root.verify::<H>(&self.pk_issuer, u128::MAX)?;
+        if let Some(signed_root) = &self.signed_root {
+            if delta.signed_root.timestamp <= signed_root.timestamp {
+                Err(HeimdallError::TimestampOutOfRange { timestamp: delta.signed_root.timestamp, now: signed_root.timestamp })?;
+            }
+        }
+
+        let mut tree = self.tree.clone();
+        tree.update_leaves(&delta.leaves)?;
+        if tree.get_root() != &delta.signed_root.root {
+            Err(HeimdallError::SignatureInvalid("Signed root does not match the revocation delta.".to_owned()))?;
+        }
+
+        self.tree = tree;
+        self.version = delta.version;
+        self.signed_root = Some(delta.signed_root.clone());
         Ok(())
     }
 
-    pub fn get_leaves(&self)-> &Vec<String>{
-        &self.tree.leaves
+    ///Retrieves the version of the registry the replica is in sync with.
+    pub fn version(&self) -> u64{
+        self.version
     }
 
+    ///Retrieves the latest signed root, if a delta has been applied.
+    pub fn signed_root(&self) -> Option<&SignedRevocationRoot<S>>{
+        self.signed_root.as_ref()
+    }
+
+    ///Retrieves the root of the replicated registry.
+    pub fn get_root(&self) -> &BigInt{
+        self.tree.get_root()
+    }
+
+    ///Retrieves the leaf at 'index', holding the status of MAX_LEAF_SIZE ids.
+    pub fn get_leaf(&self, index: usize)-> Result<&str,HeimdallError>{
+        Ok(self.tree.get_leave(index)?)
+    }
+
+    ///Generates the Merkle proof for the leaf holding the status of the id.
+    pub fn generate_proof(&self, id: usize) -> Result<MerkleProof<H,2>, HeimdallError>{
+        let (index_leaf, _, _) = locate(&self.tree, id)?;
+        Ok(self.tree.generate_proof(index_leaf)?)
+    }
+
+    ///Checks wether an id is revoked.
+    pub fn is_revoked(&self, id: usize)-> Result<bool,HeimdallError>{
+        is_revoked(&self.tree, id)
+    }
+}
+
+///A trait representing a scheme committing to the set of revoked ids in a public root.
+/// 
+/// Implemented by the bit-vector registry and its replicas, whose witnesses the presentation circuits verify,
+///  and by the out-of-circuit 'IndexedRevocationRegistry' in 'indexed_revocation'.
+pub trait RevocationScheme<H: HashFunction>{
+    ///The witness proving the revocation status of an id against the root.
+    type Witness: RevocationWitness;
+
+    ///Retrieves the root committing to the revoked ids.
+    fn root(&self) -> &BigInt;
+
     ///Checks wether an id is revoked.
-    pub fn is_revoked(&self, id: usize)-> Result<bool,Box<dyn Error>>{
-        if id >= ((REVOC_TREE_DEPTH as f32).exp2() * (MAX_LEAF_SIZE as f32)) as usize {
-            Err("Id not in Tree")?;
+    fn is_revoked(&self, id: usize) -> Result<bool, HeimdallError>;
+
+    ///Generates the witness for the status of the id.
+    fn witness_for(&self, id: usize) -> Result<Self::Witness, HeimdallError>;
+}
+
+///A trait representing the witness of a revocation scheme.
+pub trait RevocationWitness{
+    ///Checks that the witness proves the id not to be revoked under the root.
+    fn verify_unrevoked(&self, id: usize, root: &BigInt) -> Result<bool, HeimdallError>;
+}
+
+///A struct representing the witness of the bit-vector registry.
+/// 
+/// Consists of the leaf holding the bit of the id and the Merkle proof of the leaf.
+/// Reveals the block of MAX_LEAF_SIZE ids the id belongs to.
+#[derive(Debug,Clone,Serialize,Deserialize)]
+pub struct BitVectorWitness<H: HashFunction>{
+    pub leaf: BigInt,
+    #[serde(bound(serialize = "MerkleProof<H,2>: Serialize", deserialize = "MerkleProof<H,2>: Deserialize<'de>"))]
+    pub proof: MerkleProof<H,2>,
+}
+
+impl<H: HashFunction> RevocationWitness for BitVectorWitness<H>{
+    fn verify_unrevoked(&self, id: usize, root: &BigInt) -> Result<bool, HeimdallError>{
+        //the path is untrusted, checked before computing the index of the leaf from it
+        if self.proof.path.len() != REVOC_TREE_DEPTH || self.proof.path.iter().any(|position| *position > 1) {
+            return Ok(false);
         }
+        let index_leaf = self.proof.index();
+        let bit = BigInt::from(2).pow((id % MAX_LEAF_SIZE) as u32);
+
+        Ok(index_leaf == id / MAX_LEAF_SIZE
+            && self.proof.lemma.first() == Some(&H::new().hash_str(&self.leaf.to_string())?)
+            && self.proof.lemma.last() == Some(root)
+            && !((&self.leaf / bit) % BigInt::from(2)).is_one()
+            && self.proof.verify()?)
+    }
+}
 
-        let index_leaf = id / MAX_LEAF_SIZE;
-        let index_bit = id % MAX_LEAF_SIZE;
+///Generates the bit-vector witness for the id from the tree.
+fn bit_vector_witness<H: HashFunction>(tree: &SparseMerkleTree<H>, id: usize) -> Result<BitVectorWitness<H>, HeimdallError>{
+    let (index_leaf, _, leaf) = locate(tree, id)?;
+    Ok(BitVectorWitness { leaf, proof: tree.generate_proof(index_leaf)? })
+}
 
-        let value : BigInt= (BigInt::from_str(&self.tree.leaves[index_leaf]).unwrap() / BigInt::from(2).pow(index_bit as u32)) % 2;
-        Ok(value.is_one())
+impl<H:HashFunction, S: SignatureFunction> RevocationScheme<H> for RevocationRegistry<H,S>{
+    type Witness = BitVectorWitness<H>;
+
+    fn root(&self) -> &BigInt{
+        self.tree.get_root()
+    }
+
+    fn is_revoked(&self, id: usize) -> Result<bool, HeimdallError>{
+        is_revoked(&self.tree, id)
+    }
+
+    fn witness_for(&self, id: usize) -> Result<Self::Witness, HeimdallError>{
+        bit_vector_witness(&self.tree, id)
     }
 }
 
+impl<H:HashFunction, S: SignatureFunction> RevocationScheme<H> for RevocationRegistryReplica<H,S>{
+    type Witness = BitVectorWitness<H>;
+
+    fn root(&self) -> &BigInt{
+        self.tree.get_root()
+    }
+
+    fn is_revoked(&self, id: usize) -> Result<bool, HeimdallError>{
+        is_revoked(&self.tree, id)
+    }
+
+    fn witness_for(&self, id: usize) -> Result<Self::Witness, HeimdallError>{
+        bit_vector_witness(&self.tree, id)
+    }
+}
+
+///Locates the leaf and the bit of the id in the tree and parses the leaf.
+fn locate<H: HashFunction>(tree: &SparseMerkleTree<H>, id: usize) -> Result<(usize, usize, BigInt), HeimdallError>{
+    let len = tree.leave_count() * MAX_LEAF_SIZE;
+    if id >= len {
+        Err(HeimdallError::IndexOutOfRange { index: id, len })?;
+    }
+
+    let index_leaf = id / MAX_LEAF_SIZE;
+    let index_bit = id % MAX_LEAF_SIZE;
+
+    let leaf = BigInt::from_str(tree.get_leave(index_leaf)?)
+        .map_err(|_| HeimdallError::InvalidInput(format!("Malformed revocation leaf {}", index_leaf)))?;
+    Ok((index_leaf, index_bit, leaf))
+}
+
+///Checks wether the bit of the id is set in the tree.
+fn is_revoked<H: HashFunction>(tree: &SparseMerkleTree<H>, id: usize) -> Result<bool, HeimdallError>{
+    let (_index_leaf, index_bit, leaf) = locate(tree, id)?;
+
+    let value : BigInt= (leaf / BigInt::from(2).pow(index_bit as u32)) % 2;
+    Ok(value.is_one())
+}
+
+
+///Retrieves the current time in milliseconds since the unix epoch.
+pub(crate) fn now() -> Result<u128,HeimdallError>{
+    SystemTime::now().duration_since(UNIX_EPOCH).map(|now| now.as_millis())
+        .map_err(|err| HeimdallError::ClockUnavailable(err.to_string()))
+}
+
+///Checks that the timestamp lies at most 'max_age' milliseconds in the past
+/// and at most 'MAX_CLOCK_SKEW' milliseconds in the future.
+pub(crate) fn check_freshness(timestamp: u128, max_age: u128) -> Result<(),HeimdallError>{
+    let now = now()?;
+    if timestamp > now.saturating_add(MAX_CLOCK_SKEW) || now.saturating_sub(timestamp) > max_age {
+        Err(HeimdallError::TimestampOutOfRange { timestamp, now })?;
+    }
+    Ok(())
+}
 
 #[cfg(test)]
 mod test{
@@ -98,14 +1080,406 @@ mod test{
     pub fn revocation_registry() {
         let issuer_sk = BigInt::from_str("2951225162891973271265230278305932248884420185229553697262927872").unwrap();
         let mut rev_reg =
-         RevocationRegistry::<PoseidonHasher,PoseidonSignature>::new(Some(issuer_sk.clone()));
+         RevocationRegistry::<PoseidonHasher,PoseidonSignature>::new(Some(issuer_sk.clone())).unwrap();
 
         std::fs::write("revocation_registry_test.json", serde_json::to_string(&rev_reg).unwrap()).unwrap();
 
-        rev_reg.update(255 as usize, Some(issuer_sk.clone())).unwrap();
+        rev_reg.revoke(255 as usize, "key compromise", Some(issuer_sk.clone())).unwrap();
 
         assert!(rev_reg.is_revoked(255).unwrap() == true);
         assert!(rev_reg.is_revoked(200).unwrap() == false);
+        assert!(matches!(rev_reg.is_revoked(usize::MAX), Err(HeimdallError::IndexOutOfRange { .. })));
+
+        let issuer_pk = PoseidonSignature::get_public_keys(issuer_sk.clone()).unwrap();
+        assert!(rev_reg.verify_signed_root(&issuer_pk, 60*1000).is_ok());
+
+        rev_reg.reinstate(255 as usize, None).unwrap();
+        assert!(rev_reg.verify_signed_root(&issuer_pk, 60*1000).is_err());
+    }
+
+    ///TEST: explicit status changes and the event log
+    #[test]
+    pub fn revocation_event_log() {
+        let issuer_sk = BigInt::from_str("2951225162891973271265230278305932248884420185229553697262927872").unwrap();
+        let mut rev_reg =
+         RevocationRegistry::<PoseidonHasher,PoseidonSignature>::new(None).unwrap();
+
+        assert!(matches!(rev_reg.reinstate(7, None),
+            Err(HeimdallError::RevocationUnchanged { id: 7, revoked: false })));
+        rev_reg.revoke(7, "lost device", None).unwrap();
+        assert!(matches!(rev_reg.revoke(7, "lost device", None),
+            Err(HeimdallError::RevocationUnchanged { id: 7, revoked: true })));
+        rev_reg.revoke(300, "", None).unwrap();
+        rev_reg.reinstate(7, Some(issuer_sk.clone())).unwrap();
+
+        assert!(!rev_reg.is_revoked(7).unwrap());
+        assert!(rev_reg.is_revoked(300).unwrap());
+        assert_eq!(rev_reg.log().len(), 3);
+
+        //the log reproduces the registry and its signed roots
+        let replayed = RevocationRegistry::<PoseidonHasher,PoseidonSignature>::replay(rev_reg.log()).unwrap();
+        assert_eq!(replayed.tree.get_root(), rev_reg.tree.get_root());
+        assert_eq!(replayed.log(), rev_reg.log());
+        assert!(rev_reg.log()[2].matches(rev_reg.signed_root.as_ref().unwrap()));
+
+        let mut tampered = rev_reg.log().to_vec();
+        tampered[0].action = RevocationAction::Revoke { reason: "other".to_owned() };
+        assert!(matches!(RevocationRegistry::<PoseidonHasher,PoseidonSignature>::replay(&tampered),
+            Err(HeimdallError::InvalidEventLog { index: 0, .. })));
+
+        let mut tampered = rev_reg.log().to_vec();
+        tampered.remove(1);
+        assert!(matches!(RevocationRegistry::<PoseidonHasher,PoseidonSignature>::replay(&tampered),
+            Err(HeimdallError::InvalidEventLog { index: 1, .. })));
+    }
+
+    ///TEST: signed revocation root freshness
+    #[test]
+    pub fn signed_revocation_root() {
+        let issuer_sk = BigInt::from_str("2951225162891973271265230278305932248884420185229553697262927872").unwrap();
+        let issuer_pk = PoseidonSignature::get_public_keys(issuer_sk.clone()).unwrap();
+        let other_pk = PoseidonSignature::get_public_keys(BigInt::from(42)).unwrap();
+
+        let signed_root = SignedRevocationRoot::<PoseidonSignature>::new::<PoseidonHasher>(BigInt::from(1234), issuer_sk.clone()).unwrap();
+        assert!(signed_root.verify::<PoseidonHasher>(&issuer_pk, 60*1000).is_ok());
+        assert!(signed_root.verify::<PoseidonHasher>(&other_pk, 60*1000).is_err());
+
+        let mut tampered_root = signed_root.clone();
+        tampered_root.root = BigInt::from(4321);
+        assert!(tampered_root.verify::<PoseidonHasher>(&issuer_pk, 60*1000).is_err());
+
+        let outdated_root = SignedRevocationRoot::<PoseidonSignature>::new_at::<PoseidonHasher>(BigInt::from(1234), 1000, issuer_sk.clone()).unwrap();
+        assert!(matches!(outdated_root.verify::<PoseidonHasher>(&issuer_pk, 60*1000), Err(HeimdallError::TimestampOutOfRange { .. })));
+
+        //future-dated roots are only accepted within the clock skew
+        let skewed_root = SignedRevocationRoot::<PoseidonSignature>::new_at::<PoseidonHasher>(BigInt::from(1234), now().unwrap() + MAX_CLOCK_SKEW / 2, issuer_sk.clone()).unwrap();
+        assert!(skewed_root.verify::<PoseidonHasher>(&issuer_pk, 60*1000).is_ok());
+        let future_root = SignedRevocationRoot::<PoseidonSignature>::new_at::<PoseidonHasher>(BigInt::from(1234), now().unwrap() + 2 * MAX_CLOCK_SKEW, issuer_sk.clone()).unwrap();
+        assert!(matches!(future_root.verify::<PoseidonHasher>(&issuer_pk, u128::MAX), Err(HeimdallError::TimestampOutOfRange { .. })));
+    }
+
+    ///TEST: batch revocation
+    #[test]
+    pub fn revocation_batch() {
+        let issuer_sk = BigInt::from_str("2951225162891973271265230278305932248884420185229553697262927872").unwrap();
+        let issuer_pk = PoseidonSignature::get_public_keys(issuer_sk.clone()).unwrap();
+        let mut rev_reg =
+         RevocationRegistry::<PoseidonHasher,PoseidonSignature>::new(None).unwrap();
+        let mut single_reg =
+         RevocationRegistry::<PoseidonHasher,PoseidonSignature>::new(None).unwrap();
+
+        let ids = [MAX_LEAF_SIZE * 3 + 1, 5, 1, MAX_LEAF_SIZE * 3];
+        let changed_leaves = rev_reg.revoke_batch(&ids, "issuer compromise", Some(issuer_sk.clone())).unwrap();
+        assert_eq!(changed_leaves, vec![0, 3]);
+        for id in ids {
+            assert!(rev_reg.is_revoked(id).unwrap());
+            single_reg.revoke(id, "issuer compromise", None).unwrap();
+        }
+        assert_eq!(rev_reg.tree.get_root(), single_reg.tree.get_root());
+        assert_eq!(rev_reg.log().len(), 1);
+        assert!(rev_reg.verify_signed_root(&issuer_pk, 60*1000).is_ok());
+
+        //failing batches leave the registry untouched
+        let root = rev_reg.tree.get_root().clone();
+        assert!(matches!(rev_reg.revoke_batch(&[7, 5], "", None),
+            Err(HeimdallError::RevocationUnchanged { id: 5, revoked: true })));
+        assert!(matches!(rev_reg.revoke_batch(&[7, 7], "", None),
+            Err(HeimdallError::RevocationUnchanged { id: 7, revoked: true })));
+        assert!(rev_reg.revoke_batch(&[7, usize::MAX], "", None).is_err());
+        assert!(rev_reg.revoke_batch(&[], "", None).is_err());
+        assert!(!rev_reg.is_revoked(7).unwrap());
+        assert_eq!(rev_reg.tree.get_root(), &root);
+        assert_eq!(rev_reg.log().len(), 1);
+
+        let replayed = RevocationRegistry::<PoseidonHasher,PoseidonSignature>::replay(rev_reg.log()).unwrap();
+        assert_eq!(replayed.tree.get_root(), rev_reg.tree.get_root());
+    }
+
+    ///Mock of the channel an issuer publishes the registry deltas on.
+    struct MockPublisher{
+        registry: RevocationRegistry<PoseidonHasher,PoseidonSignature>,
+        sk: BigInt,
+        published: Vec<String>,
+    }
+
+    impl MockPublisher{
+        ///Publishes the changes since the last publication as json.
+        fn publish(&mut self){
+            let version = self.published.last()
+                .map(|delta| serde_json::from_str::<RevocationDelta<PoseidonSignature>>(delta).unwrap().version)
+                .unwrap_or(0);
+            let delta = self.registry.delta(version, self.sk.clone()).unwrap();
+            self.published.push(serde_json::to_string(&delta).unwrap());
+        }
+
+        fn fetch(&self, index: usize) -> RevocationDelta<PoseidonSignature>{
+            serde_json::from_str(&self.published[index]).unwrap()
+        }
+    }
+
+    ///TEST: delta publication and replica sync
+    #[test]
+    pub fn revocation_replica() {
+        let issuer_sk = BigInt::from_str("2951225162891973271265230278305932248884420185229553697262927872").unwrap();
+        let issuer_pk = PoseidonSignature::get_public_keys(issuer_sk.clone()).unwrap();
+        let mut publisher = MockPublisher{
+            registry: RevocationRegistry::new(Some(issuer_sk.clone())).unwrap(),
+            sk: issuer_sk.clone(),
+            published: Vec::new(),
+        };
+
+        publisher.registry.revoke(5, "", Some(issuer_sk.clone())).unwrap();
+        publisher.registry.revoke_batch(&[600, 601, 7000], "", Some(issuer_sk.clone())).unwrap();
+        publisher.publish();
+        publisher.registry.reinstate(600, Some(issuer_sk.clone())).unwrap();
+        publisher.publish();
+        assert_eq!(publisher.fetch(0).leaves.iter().map(|(i, _)| *i).collect::<Vec<_>>(), vec![0, 2, 27]);
+        assert_eq!(publisher.fetch(1).leaves.len(), 1);
+
+        let mut replica = RevocationRegistryReplica::<PoseidonHasher,PoseidonSignature>::new(issuer_pk.clone()).unwrap();
+        replica.apply(&publisher.fetch(0)).unwrap();
+        assert_eq!(replica.version(), 2);
+        assert!(replica.is_revoked(600).unwrap());
+        replica.apply(&publisher.fetch(1)).unwrap();
+        assert_eq!(replica.version(), 3);
+
+        let registry = &publisher.registry;
+        assert_eq!(replica.get_root(), registry.tree.get_root());
+        for id in [5, 600, 601, 7000, 8] {
+            assert_eq!(replica.is_revoked(id).unwrap(), registry.is_revoked(id).unwrap());
+            let proof = replica.generate_proof(id).unwrap();
+            assert_eq!(proof.lemma, registry.tree.generate_proof(id / MAX_LEAF_SIZE).unwrap().lemma);
+        }
+
+        //outdated, tampered and foreign deltas are rejected
+        assert!(replica.apply(&publisher.fetch(1)).is_err());
+
+        let mut fresh = RevocationRegistryReplica::<PoseidonHasher,PoseidonSignature>::new(issuer_pk.clone()).unwrap();
+        assert!(fresh.apply(&publisher.fetch(1)).is_err());
+        let mut tampered = publisher.fetch(0);
+        tampered.leaves[0].1 = "0".to_owned();
+        assert!(matches!(fresh.apply(&tampered), Err(HeimdallError::SignatureInvalid(_))));
+        assert_eq!(fresh.version(), 0);
+
+        let other_pk = PoseidonSignature::get_public_keys(BigInt::from(42)).unwrap();
+        let mut foreign = RevocationRegistryReplica::<PoseidonHasher,PoseidonSignature>::new(other_pk).unwrap();
+        assert!(foreign.apply(&publisher.fetch(0)).is_err());
+
+        //a full sync covers all changes
+        let full = publisher.registry.delta(0, issuer_sk.clone()).unwrap();
+        fresh.apply(&full).unwrap();
+        assert_eq!(fresh.get_root(), replica.get_root());
+
+        //an old delta re-versioned to roll back the replica is rejected
+        publisher.registry.revoke(8, "", Some(issuer_sk.clone())).unwrap();
+        replica.apply(&publisher.registry.delta(3, issuer_sk.clone()).unwrap()).unwrap();
+        let mut replayed = publisher.fetch(1);
+        replayed.from_version = 0;
+        replayed.version = u64::MAX;
+        assert!(matches!(replica.apply(&replayed), Err(HeimdallError::SignatureInvalid(_))));
+        let mut replayed = publisher.fetch(0);
+        replayed.version = u64::MAX;
+        replayed.signature = PoseidonSignature::sign(issuer_sk.clone(),
+            RevocationDelta::message::<PoseidonHasher>(0, u64::MAX, &replayed.signed_root).unwrap()).unwrap();
+        assert!(matches!(replica.apply(&replayed), Err(HeimdallError::TimestampOutOfRange { .. })));
+        assert_eq!(replica.version(), 4);
+        assert!(replica.is_revoked(8).unwrap());
+
+        let unsigned = RevocationRegistry::<PoseidonHasher,PoseidonSignature>::new(None).unwrap();
+        assert!(unsigned.delta(0, issuer_sk.clone()).is_err());
+        assert!(publisher.registry.delta(5, issuer_sk.clone()).is_err());
+    }
+
+    ///TEST: binary persistence of the registry
+    #[test]
+    pub fn revocation_registry_bytes() {
+        let issuer_sk = BigInt::from_str("2951225162891973271265230278305932248884420185229553697262927872").unwrap();
+        let issuer_pk = PoseidonSignature::get_public_keys(issuer_sk.clone()).unwrap();
+        let mut rev_reg =
+         RevocationRegistry::<PoseidonHasher,PoseidonSignature>::new(None).unwrap();
+        rev_reg.revoke_batch(&[3, 900, 2000], "lost device", None).unwrap();
+        rev_reg.reinstate(900, Some(issuer_sk.clone())).unwrap();
 
+        let bytes = rev_reg.to_bytes().unwrap();
+        let full = rev_reg.to_bytes_with_log().unwrap();
+        assert!(bytes.len() < full.len());
+        assert!(full.len() < serde_json::to_string(&rev_reg).unwrap().len() / 4);
+
+        //the compact encoding drops the log, but keeps the version and the hash chain
+        let mut loaded = RevocationRegistry::<PoseidonHasher,PoseidonSignature>::from_bytes(&bytes).unwrap();
+        assert_eq!(loaded.tree.get_root(), rev_reg.tree.get_root());
+        assert!(loaded.log().is_empty());
+        assert_eq!(loaded.version(), 2);
+        assert_eq!(loaded.log_start(), 2);
+        assert!(loaded.is_revoked(2000).unwrap());
+        assert!(loaded.verify_signed_root(&issuer_pk, 60*1000).is_ok());
+        assert_eq!(loaded.to_bytes().unwrap(), bytes);
+        assert!(matches!(loaded.verify_log(), Err(HeimdallError::InvalidEventLog { index: 0, .. })));
+        assert_eq!(loaded.delta(0, issuer_sk.clone()).unwrap().leaves.len(), 2);
+        assert!(loaded.delta(1, issuer_sk.clone()).is_err());
+        loaded.revoke(5, "", Some(issuer_sk.clone())).unwrap();
+        assert_eq!(loaded.version(), 3);
+        assert_eq!(loaded.log()[0].previous, rev_reg.log()[1].hash);
+        assert_eq!(loaded.delta(2, issuer_sk.clone()).unwrap().leaves, vec![(0, loaded.get_leaf(0).unwrap().to_owned())]);
+
+        //the log is only replayed on request
+        let loaded = RevocationRegistry::<PoseidonHasher,PoseidonSignature>::from_bytes(&full).unwrap();
+        assert_eq!(loaded.log(), rev_reg.log());
+        assert!(loaded.verify_log().is_ok());
+        assert_eq!(loaded.to_bytes_with_log().unwrap(), full);
+
+        let unsigned = RevocationRegistry::<PoseidonHasher,PoseidonSignature>::new(None).unwrap();
+        let loaded = RevocationRegistry::<PoseidonHasher,PoseidonSignature>::from_bytes(&unsigned.to_bytes().unwrap()).unwrap();
+        assert!(loaded.signed_root.is_none());
+        assert_eq!(loaded.tree.get_root(), unsigned.tree.get_root());
+
+        //malformed data is rejected
+        let decode = |bytes: &[u8]| RevocationRegistry::<PoseidonHasher,PoseidonSignature>::from_bytes(bytes);
+        let mut tampered = bytes.clone();
+        tampered[4] = REGISTRY_FORMAT_VERSION + 1;
+        assert!(matches!(decode(&tampered), Err(HeimdallError::InvalidEncoding(_))));
+        //last byte of the first leaf
+        let mut tampered = bytes.clone();
+        tampered[6 + 4 + 8 + 31] ^= 1;
+        assert!(matches!(decode(&tampered), Err(HeimdallError::InvalidEncoding(_))));
+        //last byte of the signed root
+        let mut tampered = bytes.clone();
+        tampered[6 + 4 + 2 * 40 + 1 + 31] ^= 1;
+        assert!(matches!(decode(&tampered), Err(HeimdallError::InvalidEncoding(_))));
+        //last byte of the root of the last event
+        let mut tampered = full.clone();
+        let len = tampered.len();
+        tampered[len - 33] ^= 1;
+        assert!(matches!(decode(&tampered), Err(HeimdallError::InvalidEncoding(_))));
+        //last byte of the hash of the last event
+        let mut tampered = full.clone();
+        tampered[len - 1] ^= 1;
+        assert!(matches!(decode(&tampered).unwrap().verify_log(), Err(HeimdallError::InvalidEventLog { index: 1, .. })));
+        assert!(decode(&bytes[..bytes.len() - 1]).is_err());
+        assert!(decode(&full[..full.len() - 1]).is_err());
+        assert!(decode(&[bytes.as_slice(), &[0]].concat()).is_err());
     }
-}
\ No newline at end of file
+
+    ///TEST: id allocation
+    #[test]
+    pub fn revocation_id_allocation() {
+        let mut rev_reg =
+         RevocationRegistry::<PoseidonHasher,PoseidonSignature>::new(None).unwrap();
+        assert_eq!(rev_reg.capacity(), REVOC_CAPACITY);
+
+        assert_eq!(rev_reg.allocate_id().unwrap(), 0);
+        assert_eq!(rev_reg.allocate_id().unwrap(), 1);
+        rev_reg.release_id(0).unwrap();
+        assert!(rev_reg.release_id(0).is_err());
+        assert_eq!(rev_reg.allocate_id().unwrap(), 2);
+
+        //revoked ids are never reassigned
+        rev_reg.revoke(1, "", None).unwrap();
+        assert!(rev_reg.release_id(1).is_err());
+
+        //ids revoked before their allocation are never assigned either
+        rev_reg.revoke(4, "", None).unwrap();
+        assert!(rev_reg.is_allocated(4));
+        assert_eq!(rev_reg.allocate_id().unwrap(), 3);
+        assert_eq!(rev_reg.allocate_id().unwrap(), 5);
+
+        rev_reg.set_allocation_strategy(AllocationStrategy::Random);
+        let ids: BTreeSet<usize> = (0..100).map(|_| rev_reg.allocate_id().unwrap()).collect();
+        assert_eq!(ids.len(), 100);
+        assert!((1..=5).all(|id| !ids.contains(&id)));
+        assert!(ids.iter().all(|id| *id < REVOC_CAPACITY));
+        assert_eq!(rev_reg.allocated_count(), 105);
+
+        //the allocator is persisted together with the registry
+        let loaded = RevocationRegistry::<PoseidonHasher,PoseidonSignature>::from_bytes(&rev_reg.to_bytes().unwrap()).unwrap();
+        assert!(ids.iter().all(|id| loaded.is_allocated(*id)));
+        assert_eq!(loaded.allocator, rev_reg.allocator);
+
+        //revoked ids must be allocated and allocated ids within the capacity
+        let mut unallocated = RevocationRegistry::<PoseidonHasher,PoseidonSignature>::new(None).unwrap();
+        unallocated.revoke(300, "", None).unwrap();
+        unallocated.allocator.allocated.remove(&300);
+        assert!(matches!(RevocationRegistry::<PoseidonHasher,PoseidonSignature>::from_bytes(&unallocated.to_bytes().unwrap()),
+            Err(HeimdallError::InvalidEncoding(_))));
+        let mut exceeding = RevocationRegistry::<PoseidonHasher,PoseidonSignature>::new(None).unwrap();
+        exceeding.allocator.allocated.insert(REVOC_CAPACITY);
+        assert!(matches!(RevocationRegistry::<PoseidonHasher,PoseidonSignature>::from_bytes(&exceeding.to_bytes().unwrap()),
+            Err(HeimdallError::InvalidEncoding(_))));
+
+        //exhausting a registry
+        let mut allocator = IdAllocator::new(AllocationStrategy::Random);
+        let ids: BTreeSet<usize> = (0..10).map(|_| allocator.allocate(10).unwrap()).collect();
+        assert_eq!(ids.len(), 10);
+        assert!(matches!(allocator.allocate(10), Err(HeimdallError::RegistryFull { capacity: 10 })));
+    }
+
+    ///TEST: loading registries of format version 1
+    #[test]
+    pub fn revocation_registry_bytes_v1() {
+        let mut rev_reg =
+         RevocationRegistry::<PoseidonHasher,PoseidonSignature>::new(None).unwrap();
+        rev_reg.revoke_batch(&[3, 900], "", None).unwrap();
+
+        //versions 1 and 2 store the log before the allocator and lack the log start
+        let full = rev_reg.to_bytes_with_log().unwrap();
+        let leaves = 6 + 4 + 2 * 40 + 1;
+        let allocator = leaves + 13 + 8 * rev_reg.allocated_count();
+        let log = allocator + 8 + 32 + 16;
+        let v2 = [&full[..leaves], &full[log..], &full[leaves..allocator]].concat();
+        let v1 = [&full[..leaves], &full[log..]].concat();
+
+        let mut loaded = RevocationRegistry::<PoseidonHasher,PoseidonSignature>::from_bytes(&[&v2[..4], &[2], &v2[5..]].concat()).unwrap();
+        assert_eq!(loaded.allocator, rev_reg.allocator);
+
+        //revoked ids missing from the allocator of earlier versions are allocated on load
+        let mut legacy = rev_reg.allocator.clone();
+        legacy.allocated.remove(&900);
+        let mut encoded = vec![0];
+        encoded.extend_from_slice(&(legacy.next as u64).to_be_bytes());
+        encoded.extend_from_slice(&(legacy.allocated.len() as u32).to_be_bytes());
+        encoded.extend(legacy.allocated.iter().flat_map(|id| (*id as u64).to_be_bytes()));
+        let legacy_bytes = [&full[..4], &[2], &full[5..leaves], &full[log..], &encoded].concat();
+        assert!(RevocationRegistry::<PoseidonHasher,PoseidonSignature>::from_bytes(&legacy_bytes).unwrap().is_allocated(900));
+        assert_eq!(loaded.log(), rev_reg.log());
+        assert!(loaded.verify_log().is_ok());
+
+        loaded = RevocationRegistry::<PoseidonHasher,PoseidonSignature>::from_bytes(&[&v1[..4], &[1], &v1[5..]].concat()).unwrap();
+        assert!(loaded.is_allocated(3) && loaded.is_allocated(900));
+        assert_eq!(loaded.allocated_count(), 2);
+        assert_eq!(loaded.tree.get_root(), rev_reg.tree.get_root());
+        assert!(loaded.verify_log().is_ok());
+    }
+
+    ///Checks the witnesses of a scheme for revoked and unrevoked ids.
+    fn check_scheme<R: RevocationScheme<PoseidonHasher>>(scheme: &R, revoked: &[usize], unrevoked: &[usize]){
+        for id in revoked {
+            assert!(RevocationScheme::is_revoked(scheme, *id).unwrap());
+        }
+        for id in unrevoked {
+            assert!(!RevocationScheme::is_revoked(scheme, *id).unwrap());
+            let witness = scheme.witness_for(*id).unwrap();
+            assert!(witness.verify_unrevoked(*id, scheme.root()).unwrap());
+            assert!(!witness.verify_unrevoked(*id, &BigInt::from(1)).unwrap());
+        }
+    }
+
+    ///TEST: bit-vector registry as revocation scheme
+    #[test]
+    pub fn revocation_scheme_bit_vector() {
+        let mut rev_reg =
+         RevocationRegistry::<PoseidonHasher,PoseidonSignature>::new(None).unwrap();
+        rev_reg.revoke_batch(&[3, 300], "", None).unwrap();
+        check_scheme(&rev_reg, &[3, 300], &[0, 4, 301, 5000]);
+
+        //the witness of a revoked id and the witness of another block do not prove the id unrevoked
+        assert!(!rev_reg.witness_for(3).unwrap().verify_unrevoked(3, rev_reg.root()).unwrap());
+        assert!(!rev_reg.witness_for(4).unwrap().verify_unrevoked(4 + MAX_LEAF_SIZE, rev_reg.root()).unwrap());
+
+        //malformed paths are rejected before computing the index of the leaf
+        for path in [vec![0; 64], vec![0; REVOC_TREE_DEPTH - 1], [vec![2], vec![0; REVOC_TREE_DEPTH - 1]].concat()] {
+            let mut malformed = rev_reg.witness_for(4).unwrap();
+            malformed.proof.path = path;
+            assert!(!malformed.verify_unrevoked(4, rev_reg.root()).unwrap());
+        }
+    }
+}
@@ -0,0 +1,274 @@
+use std::collections::{BTreeMap, BTreeSet};
+
+use crypto::{HashFunction, SignatureFunction};
+use num_bigint::BigInt;
+use serde::{Serialize, Deserialize};
+
+use crate::{credential::{Credential, META_SIZE}, error::HeimdallError, presentation::Presentation,
+    revocation::{RevocationRegistry, SignedRevocationRoot, REVOC_CAPACITY}, zkp::Circuit};
+
+///The state of a registry managed by a 'RevocationRegistryManager'.
+#[derive(Debug,Clone,Copy,PartialEq,Eq,Serialize,Deserialize)]
+pub enum RegistryStatus{
+    ///New credentials are issued with ids of the registry.
+    Active,
+    ///The registry no longer assigns ids, but still revokes the credentials it holds.
+    Retired,
+}
+
+///A struct managing the revocation registries of an issuer.
+///
+/// Credentials are issued from the active registry,
+///  which is retired and replaced by a new one as soon as it is full.
+/// The registries are named '<prefix>-<n>', the name being stored in the credentials.
+///
+/// Serializable with serde, checking the registries on deserialization.
+#[derive(Serialize,Deserialize)]
+#[serde(try_from = "ManagerState<H,S>", bound(deserialize = "RevocationRegistry<H,S>: Deserialize<'de>"))]
+pub struct RevocationRegistryManager<H: HashFunction,S: SignatureFunction>{
+    prefix: String,
+    ///Maximum number of ids assigned per registry.
+    capacity: usize,
+    #[serde(bound(serialize = "RevocationRegistry<H,S>: Serialize"))]
+    registries: BTreeMap<String, RevocationRegistry<H,S>>,
+    ///Names of the registries in the order of creation, the last one being active.
+    order: Vec<String>,
+}
+
+///The unchecked state of a deserialized 'RevocationRegistryManager'.
+#[derive(Deserialize)]
+struct ManagerState<H: HashFunction,S: SignatureFunction>{
+    prefix: String,
+    capacity: usize,
+    #[serde(bound(deserialize = "RevocationRegistry<H,S>: Deserialize<'de>"))]
+    registries: BTreeMap<String, RevocationRegistry<H,S>>,
+    order: Vec<String>,
+}
+
+///Checks that the manager holds an active registry and exactly the registries in 'order'.
+impl<H: HashFunction, S: SignatureFunction> TryFrom<ManagerState<H,S>> for RevocationRegistryManager<H,S>{
+    type Error = HeimdallError;
+
+    fn try_from(state: ManagerState<H,S>) -> Result<Self, HeimdallError>{
+        check_capacity(state.capacity)?;
+        if state.order.is_empty() {
+            Err(HeimdallError::InvalidEncoding("Manager without registry".to_owned()))?;
+        }
+        let names: BTreeSet<&String> = state.order.iter().collect();
+        if names.len() != state.order.len() || !names.iter().copied().eq(state.registries.keys()) {
+            Err(HeimdallError::InvalidEncoding("Registries do not match their order".to_owned()))?;
+        }
+        Ok(RevocationRegistryManager { prefix: state.prefix, capacity: state.capacity,
+            registries: state.registries, order: state.order })
+    }
+}
+
+impl<H: HashFunction, S: SignatureFunction> RevocationRegistryManager<H,S>{
+
+    ///Creates a manager with a first active registry using the full capacity of a registry.
+    /// If present, signs the root with the secret key.
+    pub fn new(prefix: &str, sk: Option<BigInt>) -> Result<Self, HeimdallError>{
+        Self::with_capacity(prefix, REVOC_CAPACITY, sk)
+    }
+
+    ///Creates a manager assigning at most 'capacity' ids per registry.
+    /// If present, signs the root of the first registry with the secret key.
+    pub fn with_capacity(prefix: &str, capacity: usize, sk: Option<BigInt>) -> Result<Self, HeimdallError>{
+        check_capacity(capacity)?;
+
+        let mut manager = RevocationRegistryManager { prefix: prefix.to_owned(), capacity,
+            registries: BTreeMap::new(), order: Vec::new() };
+        manager.rollover(sk)?;
+        Ok(manager)
+    }
+
+    ///Retires the active registry and opens a new one.
+    /// If present, signs the root of the new registry with the secret key.
+    ///
+    /// Returns the name of the new active registry.
+    pub fn rollover(&mut self, sk: Option<BigInt>) -> Result<&str, HeimdallError>{
+        let name = format!("{}-{}", self.prefix, self.order.len());
+        self.registries.insert(name.clone(), RevocationRegistry::new(sk)?);
+        self.order.push(name);
+        Ok(self.active())
+    }
+
+    ///Retrieves the name of the active registry.
+    pub fn active(&self) -> &str{
+        //checked on creation and deserialization
+        self.order.last().expect("manager without registry")
+    }
+
+    ///Retrieves the names of the retired registries in the order of creation.
+    pub fn retired(&self) -> &[String]{
+        self.order.split_last().map_or(&[], |(_, retired)| retired)
+    }
+
+    ///Retrieves the status of the registry, if managed.
+    pub fn status(&self, name: &str) -> Option<RegistryStatus>{
+        if !self.registries.contains_key(name) {
+            None
+        } else if name == self.active() {
+            Some(RegistryStatus::Active)
+        } else {
+            Some(RegistryStatus::Retired)
+        }
+    }
+
+    ///Resolves the registry by its name.
+    pub fn resolve(&self, name: &str) -> Result<&RevocationRegistry<H,S>, HeimdallError>{
+        self.registries.get(name).ok_or_else(|| HeimdallError::UnknownRegistry(name.to_owned()))
+    }
+
+    ///Resolves the registry by its name for updates.
+    pub fn resolve_mut(&mut self, name: &str) -> Result<&mut RevocationRegistry<H,S>, HeimdallError>{
+        self.registries.get_mut(name).ok_or_else(|| HeimdallError::UnknownRegistry(name.to_owned()))
+    }
+
+    ///Resolves the registry holding the id of the credential, e.g. for generating a presentation.
+    pub fn resolve_credential(&self, cred: &Credential<H,S>) -> Result<&RevocationRegistry<H,S>, HeimdallError>{
+        self.resolve(registry_name(cred)?)
+    }
+
+    ///Generates a new credential with an id of the active registry.
+    /// Rolls over to a new registry signed with 'sk_issuer', should the active registry be full.
+    pub fn issue(&mut self, attributes: &Vec<String>, pk_holder: &[BigInt;2], expiration: u128,
+    credential_type: &str, delegatable: bool, sk_issuer: BigInt) -> Result<Credential<H,S>, HeimdallError>{
+        if self.resolve(self.active())?.allocated_count() >= self.capacity {
+            self.rollover(Some(sk_issuer.clone()))?;
+        }
+
+        let name = self.active().to_owned();
+        let registry = self.resolve_mut(&name)?;
+        Credential::issue(attributes, registry, pk_holder, expiration, credential_type, delegatable, &name, sk_issuer)
+    }
+
+    ///Revokes the credential in the registry holding its id.
+    /// If present, signs the new root with the secret key.
+    pub fn revoke(&mut self, cred: &Credential<H,S>, reason: &str, sk: Option<BigInt>) -> Result<(), HeimdallError>{
+        let name = registry_name(cred)?;
+        let id = cred.attributes[0].parse::<usize>().map_err(|_|
+            HeimdallError::InvalidInput(format!("Malformed credential id {}", cred.attributes[0])))?;
+        self.resolve_mut(name)?.revoke(id, reason, sk)
+    }
+
+    ///Retrieves the signed root of the registry.
+    pub fn signed_root(&self, name: &str) -> Result<&SignedRevocationRoot<S>, HeimdallError>{
+        self.resolve(name)?.signed_root.as_ref().ok_or_else(||
+            HeimdallError::SignatureInvalid(format!("Revocation registry {} not signed.", name)))
+    }
+
+    ///Checks the revocation root of the presentation
+    ///  against the signed root of the registry named in the presentation.
+    /// Fails, should the registry be unknown or unsigned, or the root not verify.
+    pub fn verify_revocation_root(&self, presentation: &Presentation<H,S>, pk_issuer: &[BigInt;2],
+    max_age: u128, circuit: &Circuit) -> Result<(), HeimdallError>{
+        let signed_root = self.signed_root(&presentation.public_signals.revocation_registry)?;
+        presentation.verify_revocation_root(signed_root, pk_issuer, max_age, circuit)
+    }
+}
+
+///Fails, should the capacity not fit into a registry.
+fn check_capacity(capacity: usize) -> Result<(), HeimdallError>{
+    if capacity == 0 || capacity > REVOC_CAPACITY {
+        Err(HeimdallError::InvalidInput(format!("Capacity {} outside of 1..={}", capacity, REVOC_CAPACITY)))?;
+    }
+    Ok(())
+}
+
+///Retrieves the name of the registry from the meta attributes of the credential.
+fn registry_name<H: HashFunction, S: SignatureFunction>(cred: &Credential<H,S>) -> Result<&str, HeimdallError>{
+    if cred.attributes.len() < META_SIZE {
+        Err(HeimdallError::InvalidInput(format!("Credential with {} attributes lacks the meta attributes",
+            cred.attributes.len())))?
+    }
+    Ok(&cred.attributes[4])
+}
+
+#[cfg(test)]
+mod test {
+    use std::str::FromStr;
+
+    use crypto::poseidon::{PoseidonHasher, PoseidonSignature};
+
+    use super::*;
+    use crate::{presentation::AttributePresentation, zkp::attribute};
+
+    ///TEST: registry rollover and resolution
+    #[test]
+    fn revocation_registry_manager() {
+        let issuer_sk = BigInt::from_str("2951225162891973271265230278305932248884420185229553697262927872").unwrap();
+        let issuer_pk = PoseidonSignature::get_public_keys(issuer_sk.clone()).unwrap();
+        let mut manager = RevocationRegistryManager::<PoseidonHasher,PoseidonSignature>::with_capacity(
+            "issuer", 2, Some(issuer_sk.clone())).unwrap();
+        assert_eq!(manager.active(), "issuer-0");
+        assert!(manager.retired().is_empty());
+
+        let creds: Vec<_> = (0..3).map(|_| manager.issue(&vec!["John".to_owned()], &issuer_pk, 365,
+            "Identity", false, issuer_sk.clone()).unwrap()).collect();
+        assert_eq!(creds[0].attributes[4], "issuer-0");
+        assert_eq!(creds[1].attributes[4], "issuer-0");
+        assert_eq!(creds[2].attributes[4], "issuer-1");
+        assert_eq!(manager.active(), "issuer-1");
+        assert_eq!(manager.retired(), &["issuer-0".to_owned()]);
+        assert_eq!(manager.status("issuer-0"), Some(RegistryStatus::Retired));
+        assert_eq!(manager.status("issuer-1"), Some(RegistryStatus::Active));
+        assert_eq!(manager.status("issuer-2"), None);
+        assert!(manager.signed_root("issuer-1").is_ok());
+
+        //retired registries still revoke their credentials
+        manager.revoke(&creds[1], "", Some(issuer_sk.clone())).unwrap();
+        let registry = manager.resolve_credential(&creds[1]).unwrap();
+        assert!(registry.is_revoked(creds[1].attributes[0].parse().unwrap()).unwrap());
+        assert!(registry.verify_signed_root(&issuer_pk, 60*1000).is_ok());
+        assert!(manager.resolve_credential(&creds[2]).unwrap().log().is_empty());
+
+        //credentials lacking the meta attributes are rejected
+        let mut truncated = creds[0].clone();
+        truncated.attributes.clear();
+        assert!(matches!(manager.revoke(&truncated, "", None), Err(HeimdallError::InvalidInput(_))));
+
+        assert!(matches!(manager.resolve("other"), Err(HeimdallError::UnknownRegistry(_))));
+
+        //the revocation root of a presentation is checked against the registry it names
+        let mut attr_pres = AttributePresentation::new(&creds[2], BigInt::from(1678460108000u64),
+            manager.resolve_credential(&creds[2]).unwrap(), None, None, BigInt::from(1234), vec![]).unwrap();
+        let presentation = &mut attr_pres.presentation;
+        assert!(matches!(manager.verify_revocation_root(presentation, &issuer_pk, 60*1000, &attribute::CIRCUIT),
+            Err(HeimdallError::InvalidInput(_))));
+        presentation.public_signals.revocation_registry = "other".to_owned();
+        assert!(matches!(manager.verify_revocation_root(presentation, &issuer_pk, 60*1000, &attribute::CIRCUIT),
+            Err(HeimdallError::UnknownRegistry(_))));
+        let unsigned = RevocationRegistryManager::<PoseidonHasher,PoseidonSignature>::new("other", None).unwrap();
+        presentation.public_signals.revocation_registry = "other-0".to_owned();
+        assert!(matches!(unsigned.verify_revocation_root(presentation, &issuer_pk, 60*1000, &attribute::CIRCUIT),
+            Err(HeimdallError::SignatureInvalid(_))));
+
+        assert!(RevocationRegistryManager::<PoseidonHasher,PoseidonSignature>::with_capacity(
+            "issuer", REVOC_CAPACITY + 1, None).is_err());
+    }
+
+    ///TEST: deserialization of the manager
+    #[test]
+    fn revocation_registry_manager_serde() {
+        let mut manager = RevocationRegistryManager::<PoseidonHasher,PoseidonSignature>::with_capacity(
+            "issuer", 2, None).unwrap();
+        manager.rollover(None).unwrap();
+        let json = serde_json::to_value(&manager).unwrap();
+
+        let loaded: RevocationRegistryManager<PoseidonHasher,PoseidonSignature> = serde_json::from_value(json.clone()).unwrap();
+        assert_eq!(loaded.active(), "issuer-1");
+        assert_eq!(loaded.retired(), &["issuer-0".to_owned()]);
+
+        let decode = |mutate: &dyn Fn(&mut serde_json::Value)| {
+            let mut json = json.clone();
+            mutate(&mut json);
+            serde_json::from_value::<RevocationRegistryManager<PoseidonHasher,PoseidonSignature>>(json)
+        };
+        assert!(decode(&|json| json["order"] = serde_json::json!([])).is_err());
+        assert!(decode(&|json| json["order"] = serde_json::json!(["issuer-0", "issuer-0"])).is_err());
+        assert!(decode(&|json| json["order"] = serde_json::json!(["issuer-1"])).is_err());
+        assert!(decode(&|json| json["order"] = serde_json::json!(["issuer-0", "issuer-2"])).is_err());
+        assert!(decode(&|json| json["capacity"] = serde_json::json!(0)).is_err());
+    }
+}
@@ -0,0 +1,304 @@
+//! Status list export of the revocation registry.
+//!
+//! Allows verifiers without Groth16 verification to check the revocation status
+//!  of credentials presented in the clear.
+//! Modeled on the W3C Bitstring Status List: the bit at position 'id' is set for revoked ids,
+//!  starting with the most significant bit of the first byte, and the bitstring is gzip-compressed.
+
+use std::io::{Read, Write};
+
+use crypto::{merkle_tree::SparseMerkleTree, HashFunction, Signature, SignatureFunction};
+use flate2::{read::GzDecoder, write::GzEncoder, Compression};
+use num_bigint::BigInt;
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    error::HeimdallError,
+    resource::sha256,
+    revocation::{
+        check_freshness, now, RevocationRegistry, MAX_LEAF_SIZE, REVOC_CAPACITY, REVOC_TREE_DEPTH,
+    },
+};
+
+///A struct representing the signed and compressed revocation status of all ids of a registry.
+///
+/// Serializable with serde.
+#[derive(Debug, Serialize, Deserialize)]
+pub struct StatusList<S: SignatureFunction> {
+    ///Gzip-compressed bitstring holding the status of every id.
+    pub encoded_list: Vec<u8>,
+    ///Number of ids in the bitstring.
+    pub length: usize,
+    ///Root of the registry, tying the list to the roots used in the ZKPs.
+    pub root: BigInt,
+    ///Time of signing in milliseconds since the unix epoch.
+    pub timestamp: u128,
+    pub signature: Signature<S>,
+}
+
+///A struct representing a verified status list, ready for checking ids.
+#[derive(Debug, Clone)]
+pub struct StatusListChecker {
+    bits: Vec<u8>,
+    length: usize,
+    root: BigInt,
+    timestamp: u128,
+}
+
+impl<H: HashFunction, S: SignatureFunction> RevocationRegistry<H, S> {
+    ///Exports the status of all ids as a status list signed with the secret key of the issuer.
+    ///
+    /// The id 'id' maps to the bit 'id % MAX_LEAF_SIZE' of the leaf 'id / MAX_LEAF_SIZE'
+    ///  in the registry and to the bit 'id' in the status list.
+    pub fn export_status_list(&self, sk: BigInt) -> Result<StatusList<S>, HeimdallError> {
+        let length = self.capacity();
+        let mut bits = vec![0u8; length.div_ceil(8)];
+
+        for (index, leaf) in self.tree.populated_leaves() {
+            let leaf = leaf.parse::<BigInt>().map_err(|_| {
+                HeimdallError::InvalidInput(format!("Malformed revocation leaf {}", index))
+            })?;
+            for bit in 0..MAX_LEAF_SIZE {
+                if leaf.bit(bit as u64) {
+                    let id = index * MAX_LEAF_SIZE + bit;
+                    bits[id / 8] |= 0x80 >> (id % 8);
+                }
+            }
+        }
+
+        let mut encoder = GzEncoder::new(Vec::new(), Compression::best());
+        encoder
+            .write_all(&bits)
+            .and_then(|_| encoder.finish())
+            .map_err(|err| HeimdallError::InvalidEncoding(err.to_string()))
+            .and_then(|encoded_list| {
+                let root = self.tree.get_root().clone();
+                let timestamp = now()?;
+                let message = message::<H>(&encoded_list, length, &root, timestamp)?;
+                Ok(StatusList {
+                    encoded_list,
+                    length,
+                    root,
+                    timestamp,
+                    signature: S::sign(sk, message)?,
+                })
+            })
+    }
+}
+
+impl<S: SignatureFunction> StatusList<S> {
+    ///Verifies the status list and decompresses it for checking ids.
+    ///
+    /// # Errors
+    ///
+    /// The function fails, should the list not be signed by the issuer
+    ///  no longer than 'max_age' milliseconds ago and not in the future beyond 'MAX_CLOCK_SKEW',
+    ///  not decompress or not match the signed root of the registry.
+    pub fn open<H: HashFunction>(
+        &self,
+        pk_issuer: &[BigInt; 2],
+        max_age: u128,
+    ) -> Result<StatusListChecker, HeimdallError> {
+        if &self.signature.public_key_signer != pk_issuer {
+            Err(HeimdallError::SignatureInvalid(
+                "Status list not signed by the issuer.".to_owned(),
+            ))?;
+        }
+        let message = message::<H>(&self.encoded_list, self.length, &self.root, self.timestamp)?;
+        if !S::verify(&self.signature, &message) {
+            Err(HeimdallError::SignatureInvalid(
+                "Signature of the status list invalid.".to_owned(),
+            ))?;
+        }
+        check_freshness(self.timestamp, max_age)?;
+
+        if self.length != REVOC_CAPACITY {
+            Err(HeimdallError::InvalidEncoding(format!(
+                "Status list of length {} does not match a registry",
+                self.length
+            )))?;
+        }
+        let size = self.length.div_ceil(8);
+        let mut bits = Vec::with_capacity(size);
+        //bounds the decompressed size
+        GzDecoder::new(self.encoded_list.as_slice())
+            .take(size as u64 + 1)
+            .read_to_end(&mut bits)
+            .map_err(|err| HeimdallError::InvalidEncoding(err.to_string()))?;
+        if bits.len() != size {
+            Err(HeimdallError::InvalidEncoding(format!(
+                "Status list of {} bytes, expected {}",
+                bits.len(),
+                size
+            )))?;
+        }
+
+        let checker = StatusListChecker {
+            bits,
+            length: self.length,
+            root: self.root.clone(),
+            timestamp: self.timestamp,
+        };
+        if checker.compute_root::<H>()? != self.root {
+            Err(HeimdallError::InvalidEncoding(
+                "Status list does not match the root of the registry".to_owned(),
+            ))?;
+        }
+        Ok(checker)
+    }
+}
+
+impl StatusListChecker {
+    ///Checks wether an id is revoked.
+    pub fn is_revoked(&self, id: usize) -> Result<bool, HeimdallError> {
+        if id >= self.length {
+            Err(HeimdallError::IndexOutOfRange {
+                index: id,
+                len: self.length,
+            })?;
+        }
+        Ok(self.bits[id / 8] & (0x80 >> (id % 8)) != 0)
+    }
+
+    ///Retrieves the root of the registry the list was exported from.
+    pub fn root(&self) -> &BigInt {
+        &self.root
+    }
+
+    ///Retrieves the time of signing in milliseconds since the unix epoch.
+    pub fn timestamp(&self) -> u128 {
+        self.timestamp
+    }
+
+    ///Recomputes the root of the registry from the bitstring.
+    fn compute_root<H: HashFunction>(&self) -> Result<BigInt, HeimdallError> {
+        let mut leaves = Vec::<(usize, BigInt)>::new();
+        for (byte_index, byte) in self.bits.iter().enumerate() {
+            for offset in 0..8 {
+                if byte & (0x80 >> offset) == 0 {
+                    continue;
+                }
+                let id = byte_index * 8 + offset;
+                let index = id / MAX_LEAF_SIZE;
+                if leaves.last().map(|(i, _)| *i) != Some(index) {
+                    leaves.push((index, BigInt::from(0)));
+                }
+                let leaf = &mut leaves.last_mut().expect("leaf pushed").1;
+                leaf.set_bit((id % MAX_LEAF_SIZE) as u64, true);
+            }
+        }
+
+        let mut tree = SparseMerkleTree::<H>::new(REVOC_TREE_DEPTH as u32, "0")?;
+        let leaves: Vec<(usize, String)> = leaves
+            .into_iter()
+            .map(|(index, leaf)| (index, leaf.to_string()))
+            .collect();
+        tree.update_leaves(&leaves)?;
+        Ok(tree.get_root().clone())
+    }
+}
+
+///Computes the signed message from the compressed list, its length, the root and the timestamp.
+fn message<H: HashFunction>(
+    encoded_list: &[u8],
+    length: usize,
+    root: &BigInt,
+    timestamp: u128,
+) -> Result<BigInt, HeimdallError> {
+    //truncated to fit into the field of the hash function
+    let digest = sha256(encoded_list);
+    let digest = BigInt::from_bytes_be(num_bigint::Sign::Plus, &digest[..31]);
+    Ok(H::new().hash_big_int(&[
+        digest,
+        BigInt::from(length),
+        root.clone(),
+        BigInt::from(timestamp),
+    ])?)
+}
+
+#[cfg(test)]
+mod test {
+    use std::str::FromStr;
+
+    use crypto::poseidon::{PoseidonHasher, PoseidonSignature};
+
+    use super::*;
+    use crate::revocation::MAX_CLOCK_SKEW;
+
+    ///TEST: status list export and check
+    #[test]
+    fn status_list() {
+        let issuer_sk =
+            BigInt::from_str("2951225162891973271265230278305932248884420185229553697262927872")
+                .unwrap();
+        let issuer_pk = PoseidonSignature::get_public_keys(issuer_sk.clone()).unwrap();
+        let mut revoc_reg =
+            RevocationRegistry::<PoseidonHasher, PoseidonSignature>::new(None).unwrap();
+        let revoked = [0, 7, 8, 251, 252, 255, 5000, REVOC_CAPACITY - 1];
+        revoc_reg.revoke_batch(&revoked, "", None).unwrap();
+
+        let status_list = revoc_reg.export_status_list(issuer_sk.clone()).unwrap();
+        assert!(status_list.encoded_list.len() < 2048);
+        assert_eq!(&status_list.root, revoc_reg.tree.get_root());
+
+        let json = serde_json::to_string(&status_list).unwrap();
+        let status_list: StatusList<PoseidonSignature> = serde_json::from_str(&json).unwrap();
+        let checker = status_list
+            .open::<PoseidonHasher>(&issuer_pk, 60 * 1000)
+            .unwrap();
+        for id in [0, 1, 7, 8, 9, 250, 251, 252, 253, 255, 5000, 5001, REVOC_CAPACITY - 1] {
+            assert_eq!(
+                checker.is_revoked(id).unwrap(),
+                revoc_reg.is_revoked(id).unwrap()
+            );
+        }
+        assert!(checker.is_revoked(REVOC_CAPACITY).is_err());
+
+        //tampered lists are rejected
+        let other_pk = PoseidonSignature::get_public_keys(BigInt::from(42)).unwrap();
+        assert!(status_list.open::<PoseidonHasher>(&other_pk, 60 * 1000).is_err());
+
+        let mut tampered = revoc_reg.export_status_list(issuer_sk.clone()).unwrap();
+        tampered.root = BigInt::from(1);
+        assert!(matches!(
+            tampered.open::<PoseidonHasher>(&issuer_pk, 60 * 1000),
+            Err(HeimdallError::SignatureInvalid(_))
+        ));
+
+        //a correctly signed list not matching the root is rejected
+        let mut forged = revoc_reg.export_status_list(issuer_sk.clone()).unwrap();
+        let mut encoder = GzEncoder::new(Vec::new(), Compression::best());
+        encoder.write_all(&vec![0u8; REVOC_CAPACITY.div_ceil(8)]).unwrap();
+        forged.encoded_list = encoder.finish().unwrap();
+        resign(&mut forged, &issuer_sk);
+        assert!(matches!(
+            forged.open::<PoseidonHasher>(&issuer_pk, 60 * 1000),
+            Err(HeimdallError::InvalidEncoding(_))
+        ));
+
+        //outdated and future-dated lists are rejected
+        let mut dated = revoc_reg.export_status_list(issuer_sk.clone()).unwrap();
+        dated.timestamp -= 2 * 60 * 1000;
+        resign(&mut dated, &issuer_sk);
+        assert!(matches!(
+            dated.open::<PoseidonHasher>(&issuer_pk, 60 * 1000),
+            Err(HeimdallError::TimestampOutOfRange { .. })
+        ));
+        assert!(dated.open::<PoseidonHasher>(&issuer_pk, 3 * 60 * 1000).is_ok());
+
+        dated.timestamp += 2 * 60 * 1000 + MAX_CLOCK_SKEW + 60 * 1000;
+        resign(&mut dated, &issuer_sk);
+        assert!(matches!(
+            dated.open::<PoseidonHasher>(&issuer_pk, u128::MAX),
+            Err(HeimdallError::TimestampOutOfRange { .. })
+        ));
+    }
+
+    ///Signs the list again after altering it.
+    fn resign(list: &mut StatusList<PoseidonSignature>, sk: &BigInt) {
+        let message =
+            message::<PoseidonHasher>(&list.encoded_list, list.length, &list.root, list.timestamp)
+                .unwrap();
+        list.signature = PoseidonSignature::sign(sk.clone(), message).unwrap();
+    }
+}
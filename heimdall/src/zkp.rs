@@ -1,19 +1,223 @@
-///default circuit location depending on the target
-#[cfg(target_os = "android")]
-const CIRCUITS_PATH: &str = "/data/data/com.cguthmann.vidar/circuits";
-#[cfg(not(target_os = "android"))]
-const CIRCUITS_PATH: &str = "lib";
+use ark_circom_service::{ArkCircomFullProof, Bn254, ProverContext, VerifierKeyStore};
+#[cfg(all(feature = "wasm", not(feature = "witnesscalc")))]
+use ark_circom_service::wasm_witness::WasmWitnessCalculator;
+#[cfg(feature = "witnesscalc")]
+use witness_utils::create::WitnessCalcFn;
+use witness_utils::{create::WitnessCalculator, Witness};
+
+use crate::{error::HeimdallError, resource::{ResourceLoader, VerifiedLoader}};
+
+#[cfg(not(any(feature = "witnesscalc", feature = "wasm")))]
+compile_error!("Either the feature 'witnesscalc' or 'wasm' is required for the witness generation.");
+
+///A struct describing the positions of the public outputs of a presentation circuit.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct OutputLayout {
+    pub meta_type: usize,
+    pub revocation_root: usize,
+    pub revocation_registry: usize,
+    pub revoked: usize,
+    pub link_back: usize,
+    pub delegatable: usize,
+    pub challenge: usize,
+    pub expiration_date: usize,
+
+    ///Position of the first output specific to the content of the presentation.
+    pub content: usize,
+    ///Positions of the outputs of the polygon circuit, if the circuit proves a polygon.
+    pub polygon: Option<PolygonLayout>,
+}
 
-///Provides witness generation for the attribute presentation circuit.
-pub mod attribute {
+///A struct describing the positions of the public outputs specific to the polygon circuit.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct PolygonLayout {
+    pub in_bound: usize,
+    ///Position of the index vector, which ends with the vertices.
+    pub index: usize,
+    pub vert_x: usize,
+    pub vert_y: usize,
+}
 
-    use super::*;
-    use std::{error::Error};
+///Layout of the meta outputs shared by the presentation circuits.
+pub const META_LAYOUT: OutputLayout = OutputLayout {
+    meta_type: 0,
+    revocation_root: 1,
+    revocation_registry: 2,
+    revoked: 3,
+    link_back: 4,
+    delegatable: 5,
+    challenge: 6,
+    expiration_date: 7,
+    content: 8,
+    polygon: None,
+};
+
+///Expected SHA-256 digests of the circuit definitions shipped in 'lib', in the format of 'sha256sum'.
+///
+/// Generated alongside the circuits with 'sha256sum *.dat > SHA256SUMS'.
+/// The proving keys and wasm artifacts are generated for each deployment and not shipped,
+///  their digests are pinned by the caller with 'pinned_loader'.
+pub const RESOURCE_DIGESTS: &str = include_str!("../lib/SHA256SUMS");
+
+///Checks the ressources of 'loader' against 'RESOURCE_DIGESTS' and the digests 'sums'.
+///
+/// # Arguments
+/// * 'loader' - Provides the ressources of the circuits.
+///
+/// * 'sums' - Digests of the proving keys and wasm artifacts in the format of 'sha256sum *.wasm *.zkey'.
+///
+pub fn pinned_loader<L: ResourceLoader>(loader: L, sums: &str) -> Result<VerifiedLoader<L>, HeimdallError> {
+    let mut verified = VerifiedLoader::new(loader);
+    verified.expect_sha256sums(RESOURCE_DIGESTS)?;
+    verified.expect_sha256sums(sums)?;
+    Ok(verified)
+}
+
+///A struct describing a presentation circuit.
+/// 
+/// Adding a circuit only requires linking its witnesscalc library
+///  and registering a descriptor in 'PRESENTATION_CIRCUITS'.
+pub struct Circuit {
+    ///Name of the circuit, used for the verification key.
+    pub name: &'static str,
+    ///Witness generation provided by witnesscalc.
+    #[cfg(feature = "witnesscalc")]
+    pub witnesscalc: WitnessCalcFn,
+    ///Ressource holding the circuit definition for witnesscalc.
+    pub dat: &'static str,
+    ///Ressource holding the wasm witness generation provided by circom.
+    pub wasm: &'static str,
+    ///Ressource holding the proving key.
+    pub zkey: &'static str,
+    pub layout: OutputLayout,
+}
+
+impl Circuit {
+    ///Loads the circuit definition for witnesscalc.
+    pub fn load_dat(&self, loader: &dyn ResourceLoader) -> Result<Vec<u8>, HeimdallError> {
+        self.load(loader, self.dat)
+    }
+
+    ///Loads the wasm witness generation.
+    pub fn load_wasm(&self, loader: &dyn ResourceLoader) -> Result<Vec<u8>, HeimdallError> {
+        self.load(loader, self.wasm)
+    }
+
+    ///Loads the proving key.
+    pub fn load_zkey(&self, loader: &dyn ResourceLoader) -> Result<Vec<u8>, HeimdallError> {
+        self.load(loader, self.zkey)
+    }
+
+    ///Loads the ressource, checking it against the digests shipped with the circuits,
+    ///  unless the loader pins the digests itself.
+    fn load(&self, loader: &dyn ResourceLoader, name: &str) -> Result<Vec<u8>, HeimdallError> {
+        if loader.pins_digests() {
+            return loader.load(name);
+        }
+        pinned_loader(loader, "")?.load(name)
+    }
+
+    ///Loads the circuit definition required by witnesscalc.
+    #[cfg(feature = "witnesscalc")]
+    fn load_definition(&self, loader: &dyn ResourceLoader) -> Result<Vec<u8>, HeimdallError> {
+        self.load_dat(loader)
+    }
+
+    ///Loads the circuit definition required by the wasm witness generation.
+    #[cfg(all(feature = "wasm", not(feature = "witnesscalc")))]
+    fn load_definition(&self, loader: &dyn ResourceLoader) -> Result<Vec<u8>, HeimdallError> {
+        self.load_wasm(loader)
+    }
+
+    ///Generates the witness for the json input with witnesscalc.
+    #[cfg(feature = "witnesscalc")]
+    fn calculate_witness(&self, json: &str, circuit: &Vec<u8>) -> Result<Witness, HeimdallError> {
+        Ok(self.witnesscalc.calculate_witness(json, circuit)?)
+    }
+
+    ///Generates the witness for the json input with the wasm artifact.
+    #[cfg(all(feature = "wasm", not(feature = "witnesscalc")))]
+    fn calculate_witness(&self, json: &str, circuit: &Vec<u8>) -> Result<Witness, HeimdallError> {
+        Ok(WasmWitnessCalculator.calculate_witness(json, circuit)?)
+    }
+
+    ///Loads and parses the ressources of the circuit for repeated proofs.
+    /// 
+    /// Fails, should a ressource not match its pinned digest.
+    /// 
+    /// # Arguments
+    /// * 'loader' - Provides the circuit definition generated by circom,
+    ///               the .dat file for witnesscalc or the .wasm file otherwise,
+    ///               and the proving key for the circuit, pinned with 'pinned_loader'.
+    /// 
+    pub fn prover(&'static self, loader: &dyn ResourceLoader) -> Result<CircuitProver, HeimdallError> {
+        let definition = self.load_definition(loader)?;
+
+        let context = ProverContext::from_zkey(&self.load_zkey(loader)?)?;
+
+        Ok(CircuitProver { circuit: self, definition, context })
+    }
+
+    ///Generates a proof for the json input.
+    /// 
+    /// Loads and parses the ressources on every call,
+    ///  use a 'CircuitProver' for repeated proofs.
+    /// 
+    /// # Arguments
+    /// * 'json' - The input of the circuit.
+    /// 
+    /// * 'loader' - Provides the circuit definition and the proving key for the circuit.
+    /// 
+    pub fn prove(
+        &'static self, json: &str, loader: &dyn ResourceLoader
+    ) -> Result<ArkCircomFullProof<Bn254>, HeimdallError> {
+        self.prover(loader)?.prove(json)
+    }
+}
+
+///A struct holding the loaded and parsed ressources of a circuit.
+/// 
+/// Meant to be reused across proofs,
+///  as loading and parsing the proving key dominates the proving time.
+pub struct CircuitProver {
+    circuit: &'static Circuit,
+    definition: Vec<u8>,
+    context: ProverContext,
+}
+
+impl CircuitProver {
+    ///Retrieves the descriptor of the circuit.
+    pub fn circuit(&self) -> &'static Circuit {
+        self.circuit
+    }
+
+    ///Fails, should the prover belong to a circuit other than 'circuit'.
+    pub fn ensure_circuit(&self, circuit: &Circuit) -> Result<(), HeimdallError> {
+        if self.circuit.name != circuit.name {
+            Err(HeimdallError::InvalidInput(format!(
+                "Prover for {} used for {}",
+                self.circuit.name, circuit.name
+            )))?
+        }
+        Ok(())
+    }
+
+    ///Generates a proof for the json input.
+    pub fn prove(&self, json: &str) -> Result<ArkCircomFullProof<Bn254>, HeimdallError> {
+        let witness = self.circuit.calculate_witness(json, &self.definition)?;
+
+        let proof = self.context.prove(&witness)?;
+
+        Ok(proof)
+    }
+}
 
-    use ark_circom_service::{create_proof_from_witness, ArkCircomFullProof, Bn254};
-    use witness_utils::{create::WITNESS_SIZE_GUESS, read::read_wtns};
+///Provides the descriptor of the attribute presentation circuit.
+pub mod attribute {
+    use super::*;
 
     //Link to external witness generation provided by witnesscalc
+    #[cfg(feature = "witnesscalc")]
     #[link(name = "presentation_attribute", kind = "static")]
     extern "C" {
         fn witnesscalc_presentation_attribute(
@@ -28,97 +232,24 @@ pub mod attribute {
         ) -> i32;
     }
 
-    ///Generates a witness for the attribute presentation circuit.
-    /// 
-    /// # Arguments
-    /// * 'circuit' -  The circuit definition provided by circom in the .dat file.
-    ///                 If not provided, the function attempts to read it from
-    ///                  the default directory for the system.
-    /// 
-    /// * 'zkey' - The proving key for the circuit.
-    ///            If not provided, the function attempts to read it from
-    ///               the default directory for the system.
-    /// 
-    pub fn proof_attribute_presentation(
-        json: &str,circuit: Option<&Vec<u8>>, zkey: Option<&Vec<u8>>
-    ) -> Result<ArkCircomFullProof<Bn254>, Box<dyn Error>> {
-        let circuit = match circuit {
-            Some(res) => res.clone(),
-            None => match get_ressource("presentation_attribute.dat") {
-                Ok(res) => res,
-                Err(er) => Err(er)?,
-            },
-        }; 
-
-        let mut wtns: Vec<u8> = Vec::with_capacity(WITNESS_SIZE_GUESS as usize);
-        let mut witness_size: u64 = WITNESS_SIZE_GUESS;
-
-        let mut error: Vec<u8> = Vec::with_capacity(200);
-
-        let mut result;
-        unsafe {
-            result = witnesscalc_presentation_attribute(
-                circuit.as_ptr(),
-                circuit.len() as u64,
-                json.as_ptr(),
-                json.len() as u64,
-                wtns.as_mut_ptr(),
-                &mut witness_size,
-                error.as_mut_ptr(),
-                200,
-            );
-        }
-
-        if result == 1 {
-            Err(result.to_string())?
-        } else if result == 2 {
-            wtns = Vec::with_capacity(witness_size as usize);
-            unsafe {
-                result = witnesscalc_presentation_attribute(
-                    circuit.as_ptr(),
-                    circuit.len() as u64,
-                    json.as_ptr(),
-                    json.len() as u64,
-                    wtns.as_mut_ptr(),
-                    &mut witness_size,
-                    error.as_mut_ptr(),
-                    200,
-                );
-            };
-
-            if result != 0 {
-                Err(result.to_string())?
-            }
-        }
-        unsafe {
-            wtns.set_len(witness_size as usize);
-        }
-        let witness = read_wtns(&wtns);
-
-        let zkey = match zkey {
-            Some(res) => res.clone(),
-            None => match get_ressource("presentation_attribute.zkey") {
-                Ok(res) => res,
-                Err(er) => Err(er)?,
-            },
-        }; 
-
-        let proof = create_proof_from_witness(&witness, &zkey)?;
-
-        Ok(proof)
-    }
+    ///Descriptor of the attribute presentation circuit.
+    pub static CIRCUIT: Circuit = Circuit {
+        name: "presentation_attribute",
+        #[cfg(feature = "witnesscalc")]
+        witnesscalc: witnesscalc_presentation_attribute,
+        dat: "presentation_attribute.dat",
+        wasm: "presentation_attribute.wasm",
+        zkey: "presentation_attribute.zkey",
+        layout: META_LAYOUT,
+    };
 }
 
-///Provides witness generation for the range presentation circuit.
+///Provides the descriptor of the range presentation circuit.
 pub mod range {
     use super::*;
 
-    use std::{error::Error};
-
-    use ark_circom_service::{create_proof_from_witness, ArkCircomFullProof, Bn254};
-    use witness_utils::{create::WITNESS_SIZE_GUESS, read::read_wtns};
-
     //Link to external witness generation provided by witnesscalc
+    #[cfg(feature = "witnesscalc")]
     #[link(name = "presentation_range", kind = "static")]
     extern "C" {
         fn witnesscalc_presentation_range(
@@ -133,98 +264,24 @@ pub mod range {
         ) -> i32;
     }
 
-    ///Generates a witness for the range presentation circuit.
-    /// 
-    /// # Arguments
-    /// * 'circuit' -  The circuit definition provided by circom in the .dat file.
-    ///                 If not provided, the function attempts to read it from
-    ///                  the default directory for the system.
-    /// 
-    /// * 'zkey' - The proving key for the circuit.
-    ///            If not provided, the function attempts to read it from
-    ///               the default directory for the system.
-    /// 
-    pub fn proof_range_presentation(
-        json: &str,circuit: Option<&Vec<u8>>, zkey: Option<&Vec<u8>>
-    ) -> Result<ArkCircomFullProof<Bn254>, Box<dyn Error>> {
-        let circuit = match circuit {
-            Some(res) => res.clone(),
-            None => match get_ressource("presentation_range.dat")  {
-                Ok(res) => res,
-                Err(er) => Err(er)?,
-            },
-        }; 
-
-        let mut wtns: Vec<u8> = Vec::with_capacity(WITNESS_SIZE_GUESS as usize);
-        let mut witness_size: u64 = WITNESS_SIZE_GUESS;
-
-        let mut error: Vec<u8> = Vec::with_capacity(200);
-
-        let mut result;
-        unsafe {
-            result = witnesscalc_presentation_range(
-                circuit.as_ptr(),
-                circuit.len() as u64,
-                json.as_ptr(),
-                json.len() as u64,
-                wtns.as_mut_ptr(),
-                &mut witness_size,
-                error.as_mut_ptr(),
-                200,
-            );
-        }
-
-        if result == 1 {
-            Err(result.to_string())?
-        } else if result == 2 {
-            wtns = Vec::with_capacity(witness_size as usize);
-            unsafe {
-                result = witnesscalc_presentation_range(
-                    circuit.as_ptr(),
-                    circuit.len() as u64,
-                    json.as_ptr(),
-                    json.len() as u64,
-                    wtns.as_mut_ptr(),
-                    &mut witness_size,
-                    error.as_mut_ptr(),
-                    200,
-                );
-            };
-
-            if result != 0 {
-                Err(result.to_string())?
-            }
-        }
-        unsafe {
-            wtns.set_len(witness_size as usize);
-        }
-        let witness = read_wtns(&wtns);
-
-        
-        let zkey = match zkey {
-            Some(res) => res.clone(),
-            None => match get_ressource("presentation_range.zkey")  {
-                Ok(res) => res,
-                Err(er) => Err(er)?,
-            },
-        }; 
-
-        let proof = create_proof_from_witness(&witness, &zkey)?;
-
-        Ok(proof)
-    }
-
+    ///Descriptor of the range presentation circuit.
+    pub static CIRCUIT: Circuit = Circuit {
+        name: "presentation_range",
+        #[cfg(feature = "witnesscalc")]
+        witnesscalc: witnesscalc_presentation_range,
+        dat: "presentation_range.dat",
+        wasm: "presentation_range.wasm",
+        zkey: "presentation_range.zkey",
+        layout: META_LAYOUT,
+    };
 }
 
-///Provides witness generation for the polygon presentation circuit.
+///Provides the descriptor of the polygon presentation circuit.
 pub mod polygon {
     use super::*;
-    use std::{error::Error};
 
-    use ark_circom_service::{create_proof_from_witness, ArkCircomFullProof, Bn254};
-    use witness_utils::{create::WITNESS_SIZE_GUESS, read::read_wtns};
-    
     //Link to external witness generation provided by witnesscalc
+    #[cfg(feature = "witnesscalc")]
     #[link(name = "presentation_polygon", kind = "static")]
     extern "C" {
         fn witnesscalc_presentation_polygon(
@@ -239,99 +296,32 @@ pub mod polygon {
         ) -> i32;
     }
 
-    ///Generates a witness for the polygon presentation circuit.
-    /// 
-    /// # Arguments
-    /// * 'circuit' -  The circuit definition provided by circom in the .dat file.
-    ///                 If not provided, the function attempts to read it from
-    ///                  the default directory for the system.
-    /// 
-    /// * 'zkey' - The proving key for the circuit.
-    ///            If not provided, the function attempts to read it from
-    ///               the default directory for the system.
-    /// 
-    pub fn proof_polygon_presentation(
-        json: &str,circuit: Option<&Vec<u8>>, zkey: Option<&Vec<u8>>
-    ) -> Result<ArkCircomFullProof<Bn254>, Box<dyn Error>> {
-        let circuit = match circuit {
-            Some(res) => res.clone(),
-            None => match get_ressource("presentation_polygon.dat") {
-                Ok(res) => res,
-                Err(er) => Err(er)?,
-            },
-        }; 
-
-        let mut wtns: Vec<u8> = Vec::with_capacity(WITNESS_SIZE_GUESS as usize);
-        let mut witness_size: u64 = WITNESS_SIZE_GUESS;
-
-        let mut error: Vec<u8> = Vec::with_capacity(500);
-
-        let mut result;
-        unsafe {
-            result = witnesscalc_presentation_polygon(
-                circuit.as_ptr(),
-                circuit.len() as u64,
-                json.as_ptr(),
-                json.len() as u64,
-                wtns.as_mut_ptr(),
-                &mut witness_size,
-                error.as_mut_ptr(),
-                200,
-            );
-        }
-
-        if result == 1 {
-            Err(result.to_string())?
-        } else if result == 2 {
-            wtns = Vec::with_capacity(witness_size as usize);
-            unsafe {
-                result = witnesscalc_presentation_polygon(
-                    circuit.as_ptr(),
-                    circuit.len() as u64,
-                    json.as_ptr(),
-                    json.len() as u64,
-                    wtns.as_mut_ptr(),
-                    &mut witness_size,
-                    error.as_mut_ptr(),
-                    200,
-                );
-            };
-
-            if result != 0 {
-                Err(result.to_string())?
-            }
-        }
-        unsafe {
-            wtns.set_len(witness_size as usize);
-        }
-        let witness = read_wtns(&wtns);
-
-        
-        let zkey = match zkey {
-            Some(res) => res.clone(),
-            None => match get_ressource("presentation_polygon.zkey") {
-                Ok(res) => res,
-                Err(er) => Err(er)?,
-            },
-        }; 
-
-        let proof = create_proof_from_witness(&witness, &zkey)?;
-
-        Ok(proof)
-    }
-
+    ///Descriptor of the polygon presentation circuit.
+    pub static CIRCUIT: Circuit = Circuit {
+        name: "presentation_polygon",
+        #[cfg(feature = "witnesscalc")]
+        witnesscalc: witnesscalc_presentation_polygon,
+        dat: "presentation_polygon.dat",
+        wasm: "presentation_polygon.wasm",
+        zkey: "presentation_polygon.zkey",
+        layout: OutputLayout {
+            polygon: Some(PolygonLayout {
+                in_bound: 8,
+                index: 9,
+                vert_x: 45,
+                vert_y: 95,
+            }),
+            ..META_LAYOUT
+        },
+    };
 }
 
-///Provides witness generation for the delegation presentation circuit.
+///Provides the descriptor of the delegation presentation circuit.
 pub mod delegation {
     use super::*;
 
-    use std::{error::Error};
-
-    use ark_circom_service::{create_proof_from_witness, ArkCircomFullProof, Bn254};
-    use witness_utils::{create::WITNESS_SIZE_GUESS, read::read_wtns};
-    
     //Link to external witness generation provided by witnesscalc
+    #[cfg(feature = "witnesscalc")]
     #[link(name = "presentation_delegation", kind = "static")]
     extern "C" {
         fn witnesscalc_presentation_delegation(
@@ -346,93 +336,172 @@ pub mod delegation {
         ) -> i32;
     }
 
-    ///Generates a witness for the delegation presentation circuit.
-    /// 
-    /// # Arguments
-    /// * 'circuit' -  The circuit definition provided by circom in the .dat file.
-    ///                 If not provided, the function attempts to read it from
-    ///                  the default directory for the system.
-    /// 
-    /// * 'zkey' - The proving key for the circuit.
-    ///            If not provided, the function attempts to read it from
-    ///               the default directory for the system.
-    /// 
-    pub fn proof_delegation_presentation(
-        json: &str,circuit: Option<&Vec<u8>>, zkey: Option<&Vec<u8>>
-    ) -> Result<ArkCircomFullProof<Bn254>, Box<dyn Error>> {
-        let circuit = match circuit {
-            Some(res) => res.clone(),
-            None => match get_ressource("presentation_delegation.dat") {
-                Ok(res) => res,
-                Err(er) => Err(er)?,
-            },
-        }; 
-        let mut wtns: Vec<u8> = Vec::with_capacity(WITNESS_SIZE_GUESS as usize);
-        let mut witness_size: u64 = WITNESS_SIZE_GUESS;
-
-        let mut error: Vec<u8> = Vec::with_capacity(500);
-
-        let mut result;
-        unsafe {
-            result = witnesscalc_presentation_delegation(
-                circuit.as_ptr(),
-                circuit.len() as u64,
-                json.as_ptr(),
-                json.len() as u64,
-                wtns.as_mut_ptr(),
-                &mut witness_size,
-                error.as_mut_ptr(),
-                200,
-            );
+    ///Descriptor of the delegation presentation circuit.
+    pub static CIRCUIT: Circuit = Circuit {
+        name: "presentation_delegation",
+        #[cfg(feature = "witnesscalc")]
+        witnesscalc: witnesscalc_presentation_delegation,
+        dat: "presentation_delegation.dat",
+        wasm: "presentation_delegation.wasm",
+        zkey: "presentation_delegation.zkey",
+        layout: META_LAYOUT,
+    };
+}
+
+///Registry of the presentation circuits.
+pub static PRESENTATION_CIRCUITS: [&Circuit; 4] = [
+    &attribute::CIRCUIT,
+    &range::CIRCUIT,
+    &polygon::CIRCUIT,
+    &delegation::CIRCUIT,
+];
+
+///Loads the verification keys of all presentation circuits.
+/// 
+/// The returned store pins the keys a verifier trusts,
+///  taken from the proving keys matching the digests pinned by the loader, see 'pinned_loader'.
+pub fn load_verifier_key_store(loader: &dyn ResourceLoader) -> Result<VerifierKeyStore, HeimdallError> {
+    let mut key_store = VerifierKeyStore::new();
+
+    for circuit in PRESENTATION_CIRCUITS {
+        key_store.insert_from_zkey(circuit.name, &circuit.load_zkey(loader)?)?;
+    }
+
+    Ok(key_store)
+}
+
+///Loader for the ressources in 'lib', pinning the proving keys and wasm artifacts generated locally.
+#[cfg(test)]
+pub(crate) fn test_prover_loader() -> VerifiedLoader<crate::resource::FileSystemLoader> {
+    use crate::resource::{sha256, test_loader};
+
+    let mut loader = pinned_loader(test_loader(), "").unwrap();
+    for name in PRESENTATION_CIRCUITS.iter().flat_map(|circuit| [circuit.wasm, circuit.zkey]) {
+        if let Ok(bytes) = test_loader().load(name) {
+            loader.expect(name, sha256(&bytes));
+        }
+    }
+    loader
+}
+
+#[cfg(test)]
+mod test {
+    use witness_utils::WitnessError;
+
+    use crate::resource::{sha256, test_loader, MemoryLoader};
+
+    use super::*;
+
+    //checks that the witness generation fails with the message of witnesscalc
+    fn assert_witnesscalc_failed<T>(result: Result<T, HeimdallError>) {
+        match result {
+            Err(HeimdallError::Witness(WitnessError::WitnessCalcFailed(msg))) => {
+                assert!(msg.contains(": "), "error message missing in '{}'", msg)
+            }
+            Err(err) => panic!("unexpected error {}", err),
+            Ok(_) => panic!("malformed input accepted"),
         }
+    }
 
-        if result == 1 {
-            Err(result.to_string())?
-        } else if result == 2 {
-            wtns = Vec::with_capacity(witness_size as usize);
-            unsafe {
-                result = witnesscalc_presentation_delegation(
-                    circuit.as_ptr(),
-                    circuit.len() as u64,
-                    json.as_ptr(),
-                    json.len() as u64,
-                    wtns.as_mut_ptr(),
-                    &mut witness_size,
-                    error.as_mut_ptr(),
-                    200,
-                );
-            };
-
-            if result != 0 {
-                Err(result.to_string())?
+    ///TEST: witness generation with malformed input json
+    #[test]
+    fn malformed_input_json() {
+        for circuit in PRESENTATION_CIRCUITS {
+            let definition = circuit.load_definition(&test_prover_loader()).unwrap();
+            for json in ["{\"values\":[", "{\"unknownSignal\":\"1\"}"] {
+                assert_witnesscalc_failed(circuit.calculate_witness(json, &definition));
             }
         }
-        unsafe {
-            wtns.set_len(witness_size as usize);
+    }
+
+    ///TEST: ressources checked against the shipped digests
+    #[test]
+    fn resource_digests() {
+        for circuit in PRESENTATION_CIRCUITS {
+            assert!(circuit.load_dat(&test_loader()).is_ok());
+
+            let mut tampered = MemoryLoader::new();
+            let mut dat = test_loader().load(circuit.dat).unwrap();
+            dat[0] ^= 1;
+            tampered.insert(circuit.dat, dat);
+            assert!(matches!(
+                circuit.load_dat(&tampered),
+                Err(HeimdallError::UntrustedResource(_))
+            ));
         }
-        let witness = read_wtns(&wtns);
 
-        
-        let zkey = match zkey {
-            Some(res) => res.clone(),
-            None => match get_ressource("presentation_delegation.zkey") {
-                Ok(res) => res,
-                Err(er) => Err(er)?,
-            },
-        }; 
+        let mut unknown = MemoryLoader::new();
+        unknown.insert("presentation_unknown.zkey", b"zkey".to_vec());
+        assert!(matches!(
+            attribute::CIRCUIT.load(&unknown, "presentation_unknown.zkey"),
+            Err(HeimdallError::UntrustedResource(_))
+        ));
+    }
 
-        let proof = create_proof_from_witness(&witness, &zkey)?;
+    ///TEST: proving keys pinned by the caller pass the digest checks of the provers
+    #[test]
+    fn pinned_proving_keys() {
+        let mut memory = MemoryLoader::new();
+        for circuit in PRESENTATION_CIRCUITS {
+            for name in [circuit.dat, circuit.wasm] {
+                if let Ok(bytes) = test_loader().load(name) {
+                    memory.insert(name, bytes);
+                }
+            }
+            memory.insert(circuit.zkey, b"zkey".to_vec());
+        }
 
-        Ok(proof)
+        //the default check rejects the proving keys not shipped with the circuits
+        assert!(matches!(
+            load_verifier_key_store(&memory),
+            Err(HeimdallError::UntrustedResource(_))
+        ));
+
+        let sums = format!("{}  presentation_attribute.zkey", hex(&sha256(b"zkey")));
+        let pinned = pinned_loader(&memory, &sums).unwrap();
+        assert_eq!(attribute::CIRCUIT.load_zkey(&pinned).unwrap(), b"zkey".to_vec());
+        assert!(matches!(
+            range::CIRCUIT.load_zkey(&pinned),
+            Err(HeimdallError::UntrustedResource(_))
+        ));
+        //fails parsing the key, past the digest checks
+        for result in [attribute::CIRCUIT.prover(&pinned).map(|_| ()),
+            load_verifier_key_store(&pinned).map(|_| ())] {
+            assert!(!matches!(
+                result,
+                Ok(_) | Err(HeimdallError::UntrustedResource(_)) | Err(HeimdallError::MissingResource(_))
+            ));
+        }
+
+        //the provers of the tests load the locally generated keys
+        for circuit in PRESENTATION_CIRCUITS {
+            if test_loader().load(circuit.zkey).is_ok() {
+                assert!(circuit.prover(&test_prover_loader()).is_ok());
+            }
+        }
     }
 
-}
+    //encodes the digest in hex as printed by 'sha256sum'
+    fn hex(digest: &[u8]) -> String {
+        digest.iter().map(|byte| format!("{:02x}", byte)).collect()
+    }
 
-///Attempts to load the specified ressoruce at the default location for the system.
-fn get_ressource(name: &str) -> Result<Vec<u8>, Box<dyn std::error::Error>> {
-    match std::fs::read(format!("{}/{}", CIRCUITS_PATH, name)) {
-        Ok(res) => Ok(res),
-        Err(err) => Err(err)?,
+    ///TEST: decoding of witnesscalc error messages
+    #[test]
+    fn witnesscalc_error_message() {
+        let mut buffer = vec![0u8; witness_utils::create::ERROR_MSG_SIZE];
+        buffer[..13].copy_from_slice(b"Signal absent");
+        assert_eq!(
+            witness_utils::create::witnesscalc_error(1, &buffer).to_string(),
+            "Witness calculation failed: Return code 1: Signal absent"
+        );
+
+        //truncated messages are not null terminated
+        let buffer = vec![b'a'; 4];
+        assert_eq!(witness_utils::create::decode_error_msg(&buffer), "aaaa");
+        assert_eq!(
+            witness_utils::create::witnesscalc_error(1, &[0; 4]).to_string(),
+            "Witness calculation failed: Return code 1"
+        );
     }
 }
-
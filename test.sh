@@ -0,0 +1,13 @@
+#!/bin/sh
+# Builds and tests the workspace for every supported feature combination.
+set -e
+
+cargo test --workspace
+
+#witness generation from the wasm artifacts, without the native witnesscalc libraries
+cargo test -p ark-circom-service --no-default-features --features wasm
+cargo test -p heimdall --no-default-features --features wasm
+
+#hash trees built in parallel with rayon
+cargo test -p crypto --features parallel
+cargo test -p heimdall --features parallel
@@ -0,0 +1,37 @@
+use std::{error::Error, fmt, io};
+
+///Errors raised while creating or reading a witness.
+#[derive(Debug)]
+pub enum WitnessError {
+    ///The binary witness does not follow the wtns format.
+    MalformedWitness(String),
+    ///The witness generation of witnesscalc failed.
+    WitnessCalcFailed(String),
+    ///The witness could not be read.
+    Io(io::Error),
+}
+
+impl fmt::Display for WitnessError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            WitnessError::MalformedWitness(msg) => write!(f, "Malformed witness: {}", msg),
+            WitnessError::WitnessCalcFailed(msg) => write!(f, "Witness calculation failed: {}", msg),
+            WitnessError::Io(err) => write!(f, "Reading the witness failed: {}", err),
+        }
+    }
+}
+
+impl Error for WitnessError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            WitnessError::Io(err) => Some(err),
+            _ => None,
+        }
+    }
+}
+
+impl From<io::Error> for WitnessError {
+    fn from(err: io::Error) -> Self {
+        WitnessError::Io(err)
+    }
+}
@@ -1,6 +1,10 @@
+pub mod error;
+
 use std::collections::HashMap;
 use num_bigint::BigInt;
 
+pub use error::WitnessError;
+
 ///A struct representing a R1CS witness.
 pub struct Witness{
     pub version: u32,
@@ -31,84 +35,211 @@ pub mod read{
     use byteorder::{ByteOrder,LittleEndian};
     use num_bigint::{Sign};
 
+    //retrieves 'len' bytes at 'pos', failing if the binary is too short
+    fn slice(full_wtns: &Vec<u8>, pos: usize, len: usize) -> Result<&[u8], WitnessError>
+    {
+        match pos.checked_add(len) {
+            Some(end) if end <= full_wtns.len() => Ok(&full_wtns[pos..end]),
+            _ => Err(WitnessError::MalformedWitness(
+                format!("Unexpected end of witness at byte {}", pos)))
+        }
+    }
+
+    //retrieves the start of the first section of type 'section_type'
+    fn section_start(witness: &Witness, section_type: u32) -> Result<usize, WitnessError>
+    {
+        match witness.sections.get(&section_type) {
+            Some(section) => Ok(section[0].0),
+            None => Err(WitnessError::MalformedWitness(
+                format!("Missing section {}", section_type)))
+        }
+    }
+
     //reads the first part of the binary, describing the structure
     fn read_wtns_descriptor(full_wtns: &Vec<u8>, witness: &mut Witness)
+    -> Result<(), WitnessError>
     {
 
         let mut pos: usize = 0;
-        let _file_type = &full_wtns[pos..pos+4];
+        let _file_type = slice(full_wtns, pos, 4)?;
         pos +=4;
 
-        witness.version = LittleEndian::read_u32(&full_wtns[pos..pos+4]);
+        witness.version = LittleEndian::read_u32(slice(full_wtns, pos, 4)?);
         pos +=4;
 
-        witness.n_sections = LittleEndian::read_u32(&full_wtns[pos..pos+4]);
+        witness.n_sections = LittleEndian::read_u32(slice(full_wtns, pos, 4)?);
         pos +=4;
 
         for _i in 0..witness.n_sections {
-            let ht = LittleEndian::read_u32(&full_wtns[pos..pos+4]);
+            let ht = LittleEndian::read_u32(slice(full_wtns, pos, 4)?);
             pos +=4;
-            let hl = LittleEndian::read_u64(&full_wtns[pos..pos+8]) as usize;
+            let hl = LittleEndian::read_u64(slice(full_wtns, pos, 8)?) as usize;
             pos +=8;
             witness.sections.entry(ht).or_insert_with(Vec::new).push((pos,hl));
-            pos += hl;
+            pos = match pos.checked_add(hl) {
+                Some(pos) => pos,
+                None => Err(WitnessError::MalformedWitness("Section length overflow".to_owned()))?,
+            };
         }
         
-    
+        Ok(())
     }
 
     //reads the witness header, returns (FieldDataSize, FieldPrime,numConstraintss)
     fn read_wtns_header(full_wtns: &Vec<u8>, witness: &mut Witness)
+    -> Result<(), WitnessError>
     {
         
-        let mut pos = witness.sections.get(&1).unwrap()[0].0;
+        let mut pos = section_start(witness, 1)?;
 
-        witness.field_element_size = LittleEndian::read_u32(&full_wtns[pos..pos+4]) as usize;
+        witness.field_element_size = LittleEndian::read_u32(slice(full_wtns, pos, 4)?) as usize;
         pos +=4;
-        witness.field_prime = BigInt::from_bytes_le(Sign::Plus,&full_wtns[pos..pos+witness.field_element_size]);
+        if witness.field_element_size != 32 {
+            Err(WitnessError::MalformedWitness(
+                format!("Unsupported field element size {}", witness.field_element_size)))?;
+        }
+        witness.field_prime = BigInt::from_bytes_le(Sign::Plus,slice(full_wtns, pos, witness.field_element_size)?);
         pos +=witness.field_element_size;
 
-        witness.num_constraints = LittleEndian::read_u32(&full_wtns[pos..pos+4]);
+        witness.num_constraints = LittleEndian::read_u32(slice(full_wtns, pos, 4)?);
         
+        Ok(())
     }
 
     //transcodes binary witness to Witness struct
     pub fn read_wtns(full_wtns: &Vec<u8>)
-    -> Witness{
+    -> Result<Witness, WitnessError>{
         
         let mut witness = Witness::new();
 
-        read_wtns_descriptor(&full_wtns, &mut witness);
+        read_wtns_descriptor(&full_wtns, &mut witness)?;
 
-        read_wtns_header(&full_wtns, &mut witness);
-        let mut pos = witness.sections.get(&2).unwrap()[0].0;
+        read_wtns_header(&full_wtns, &mut witness)?;
+        let mut pos = section_start(&witness, 2)?;
 
         for _i in 0 .. witness.num_constraints {
-            witness.assignment.push({
-                full_wtns[pos..pos+witness.field_element_size].try_into().unwrap()
-            }
-            );
+            let mut element = [0u8;32];
+            element.copy_from_slice(slice(full_wtns, pos, witness.field_element_size)?);
+            witness.assignment.push(element);
             pos += witness.field_element_size;
         }
-        witness
+        Ok(witness)
     }
 
     //loads binary witness from file
     pub fn read_wtns_from_file(file_name: &str)
-    -> Vec<[u8;32]>{
-        let full_wtns: Vec<u8> = read(file_name).unwrap();
+    -> Result<Vec<[u8;32]>, WitnessError>{
+        let full_wtns: Vec<u8> = read(file_name)?;
         
-        let witness = read_wtns(&full_wtns);
-        witness.assignment
+        let witness = read_wtns(&full_wtns)?;
+        Ok(witness.assignment)
     }
 }
 
 
 
 ///Provides functionality around creating a witness.
-/// Because witnesscalc generates C++ libraries,
-///  implementing a generic creation function is not possible
+/// Generic over the backend through the 'WitnessCalculator' trait.
 pub mod create{
+    use super::{read::read_wtns, Witness, WitnessError};
+
     /// Suggestion for a generic witness size
     pub const WITNESS_SIZE_GUESS: u64 = 4*1024*1024;
+
+    /// Size of the buffer receiving the error message of witnesscalc
+    pub const ERROR_MSG_SIZE: usize = 256;
+
+    ///Decodes the error message written by witnesscalc.
+    /// 
+    /// The message ends at the first null byte.
+    ///  Truncated messages are not terminated by witnesscalc and span the whole buffer.
+    pub fn decode_error_msg(buffer: &[u8]) -> String{
+        let end = buffer.iter().position(|&byte| byte == 0).unwrap_or(buffer.len());
+        String::from_utf8_lossy(&buffer[..end]).trim().to_owned()
+    }
+
+    ///Converts a failed witnesscalc call into an error carrying its message.
+    /// 
+    /// # Arguments
+    /// * 'code' - The return code of witnesscalc.
+    /// * 'error_buffer' - The buffer passed to witnesscalc for the error message.
+    pub fn witnesscalc_error(code: i32, error_buffer: &[u8]) -> WitnessError{
+        let msg = decode_error_msg(error_buffer);
+        if msg.is_empty() {
+            WitnessError::WitnessCalcFailed(format!("Return code {}", code))
+        } else {
+            WitnessError::WitnessCalcFailed(format!("Return code {}: {}", code, msg))
+        }
+    }
+
+    ///Signature of the witness generation functions exported by witnesscalc.
+    pub type WitnessCalcFn = unsafe extern "C" fn(
+        circuit_buffer: *const u8,
+        circuit_size: u64,
+        json_buffer: *const u8,
+        json_size: u64,
+        wtns_buffer: *mut u8,
+        wtns_size: *mut u64,
+        error_buffer: *mut u8,
+        error_msg_maxsize: u64,
+    ) -> i32;
+
+    ///A trait for backends generating the witness of a circuit.
+    pub trait WitnessCalculator {
+        ///Generates the witness for the json input.
+        /// 
+        /// # Arguments
+        /// * 'circuit' - The circuit definition required by the backend.
+        fn calculate_witness(&self, json: &str, circuit: &Vec<u8>) -> Result<Witness, WitnessError>;
+    }
+
+    ///Implements the witness generation for the functions exported by witnesscalc.
+    /// 
+    /// The circuit definition is the .dat file provided by circom,
+    ///  which needs to be consistant with the witnesscalc library.
+    impl WitnessCalculator for WitnessCalcFn {
+        fn calculate_witness(&self, json: &str, circuit: &Vec<u8>) -> Result<Witness, WitnessError> {
+            let mut wtns: Vec<u8> = Vec::with_capacity(WITNESS_SIZE_GUESS as usize);
+            let mut witness_size: u64 = WITNESS_SIZE_GUESS;
+
+            let mut error: Vec<u8> = vec![0; ERROR_MSG_SIZE];
+
+            let mut result;
+            unsafe {
+                result = self(
+                    circuit.as_ptr(),
+                    circuit.len() as u64,
+                    json.as_ptr(),
+                    json.len() as u64,
+                    wtns.as_mut_ptr(),
+                    &mut witness_size,
+                    error.as_mut_ptr(),
+                    ERROR_MSG_SIZE as u64,
+                );
+            }
+
+            if result == 2 {
+                wtns = Vec::with_capacity(witness_size as usize);
+                unsafe {
+                    result = self(
+                        circuit.as_ptr(),
+                        circuit.len() as u64,
+                        json.as_ptr(),
+                        json.len() as u64,
+                        wtns.as_mut_ptr(),
+                        &mut witness_size,
+                        error.as_mut_ptr(),
+                        ERROR_MSG_SIZE as u64,
+                    );
+                };
+            }
+            if result != 0 {
+                Err(witnesscalc_error(result, &error))?
+            }
+            unsafe {
+                wtns.set_len(witness_size as usize);
+            }
+            read_wtns(&wtns)
+        }
+    }
 }